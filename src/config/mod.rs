@@ -2,6 +2,9 @@ use dotenvy::dotenv;
 use std::env;
 use uuid::Uuid;
 
+use crate::media::formats::FormatRegistry;
+use crate::server::throttle::RateLimits;
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub http_address: String,
@@ -10,7 +13,14 @@ pub struct Config {
     pub multicast_address: String,
     pub multicast_port: u16,
     pub media_directory: String,
+    pub state_directory: String,
     pub udn: String,
+    pub rate_limits: RateLimits,
+    pub formats: FormatRegistry,
+    /// How long each photo stays on screen in a slideshow.
+    pub slideshow_secs: u64,
+    /// URL given with `--cast-url`, cast once a device is connected.
+    pub cast_url: Option<String>,
 }
 
 /// Detects the machine's outbound LAN IP by opening a UDP socket and checking
@@ -59,6 +69,26 @@ impl Config {
             return Err("MEDIA_DIRECTORY cannot be empty".to_string());
         }
 
+        let state_directory =
+            env::var("STATE_DIRECTORY").unwrap_or_else(|_| "./.rustcast".to_string());
+        if state_directory.trim().is_empty() {
+            return Err("STATE_DIRECTORY cannot be empty".to_string());
+        }
+
+        let rate_limits = RateLimits::parse(&env::var("RATE_LIMITS").unwrap_or_default())
+            .map_err(|e| format!("RATE_LIMITS: {}", e))?;
+
+        let slideshow_secs: u64 = env::var("SLIDESHOW_SECONDS")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse()
+            .ok()
+            .filter(|&secs| secs > 0)
+            .ok_or_else(|| "SLIDESHOW_SECONDS must be a positive number".to_string())?;
+
+        let formats = FormatRegistry::builtin()
+            .with_overrides(&env::var("MEDIA_FORMATS").unwrap_or_default())
+            .map_err(|e| format!("MEDIA_FORMATS: {}", e))?;
+
         let http_address = env::var("HTTP_ADDRESS").unwrap_or_else(|_| detect_local_ip());
 
         Ok(Config {
@@ -70,7 +100,52 @@ impl Config {
                 .unwrap_or_else(|_| "239.255.255.250".to_string()),
             multicast_port,
             media_directory,
+            state_directory,
             udn: env::var("UDN").unwrap_or_else(|_| format!("uuid:{}", Uuid::new_v4())),
+            rate_limits,
+            formats,
+            slideshow_secs,
+            cast_url: None,
         })
     }
+
+    /// Base URL renderers use to reach this server, e.g. `http://192.168.1.10:8080`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}:{}", self.http_address, self.http_port)
+    }
+
+    /// HTTP URL of a media file given its path relative to the media root.
+    pub fn media_url(&self, relative_path: &str) -> String {
+        format!("{}/media/{}", self.base_url(), relative_path)
+    }
+
+    /// HTTP URL of the cover art of a media file or folder, given its path
+    /// relative to the media root. The `/art` endpoint serves it.
+    pub fn art_url(&self, relative_path: &str) -> String {
+        format!("{}/art/{}", self.base_url(), relative_path)
+    }
+
+    /// HTTP URL under which the `/proxy` endpoint relays the remote
+    /// resource registered as `id`.
+    pub fn proxy_url(&self, id: usize) -> String {
+        format!("{}/proxy/{}", self.base_url(), id)
+    }
+
+    /// HTTP URL of a subtitle file given its path relative to the media
+    /// root, converted to `format` (an extension such as `srt`) and shifted
+    /// by `offset_ms` by the `/subtitle` endpoint. The offset is part of the
+    /// URL, so renderers that cache subtitles fetch them again when it
+    /// changes.
+    pub fn subtitle_url(&self, relative_path: &str, format: &str, offset_ms: i64) -> String {
+        let mut url = format!(
+            "{}/subtitle/{}?format={}",
+            self.base_url(),
+            relative_path,
+            format
+        );
+        if offset_ms != 0 {
+            url.push_str(&format!("&offset={}", offset_ms));
+        }
+        url
+    }
 }
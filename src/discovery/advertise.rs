@@ -1,23 +1,48 @@
-use crate::config::Config;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::SystemTime;
+
+use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, sleep, Duration};
+
+use crate::config::Config;
+use crate::server::description::{MEDIA_SERVER, SERVICES};
 
 const NOTIFY_INTERVAL_SECS: u64 = 30;
 const CACHE_MAX_AGE: u32 = 1800;
+const SERVER_TOKEN: &str = "Rust/1.0 UPnP/1.0 RustCast/0.1";
 
-/// Returns the three (NT, USN) pairs for a UPnP MediaServer announcement:
+/// Upper bound on the MX value we honour; UPnP 1.1 caps it at 5 seconds.
+const MAX_MX_SECS: u64 = 5;
+
+/// Pause after a failed receive, so a socket that keeps failing (e.g. the
+/// interface went down) does not spin the loop and flood the log.
+const RECV_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Returns the (NT, USN) pairs for a UPnP MediaServer announcement:
 ///   1. upnp:rootdevice
 ///   2. uuid:<UDN>
 ///   3. urn:schemas-upnp-org:device:MediaServer:1
-fn notify_entries(udn: &str) -> [(&str, String); 3] {
-    [
-        ("upnp:rootdevice", format!("{}::upnp:rootdevice", udn)),
-        (udn, udn.to_string()),
+///   4. one entry per service type in the device description
+fn notify_entries(udn: &str) -> Vec<(String, String)> {
+    let mut entries = vec![
         (
-            "urn:schemas-upnp-org:device:MediaServer:1",
-            format!("{}::urn:schemas-upnp-org:device:MediaServer:1", udn),
+            "upnp:rootdevice".to_string(),
+            format!("{}::upnp:rootdevice", udn),
         ),
-    ]
+        (udn.to_string(), udn.to_string()),
+        (
+            MEDIA_SERVER.to_string(),
+            format!("{}::{}", udn, MEDIA_SERVER),
+        ),
+    ];
+    entries.extend(SERVICES.iter().map(|service| {
+        (
+            service.service_type.to_string(),
+            format!("{}::{}", udn, service.service_type),
+        )
+    }));
+    entries
 }
 
 fn build_alive_messages(location: &str, udn: &str, multicast_host: &str) -> Vec<String> {
@@ -31,10 +56,10 @@ fn build_alive_messages(location: &str, udn: &str, multicast_host: &str) -> Vec<
          LOCATION: {}\r\n\
          NT: {}\r\n\
          NTS: ssdp:alive\r\n\
-         SERVER: Rust/1.0 UPnP/1.0 RustCast/0.1\r\n\
+         SERVER: {}\r\n\
          USN: {}\r\n\
          \r\n",
-                multicast_host, CACHE_MAX_AGE, location, nt, usn
+                multicast_host, CACHE_MAX_AGE, location, nt, SERVER_TOKEN, usn
             )
         })
         .collect()
@@ -65,7 +90,7 @@ async fn send_messages(socket: &UdpSocket, messages: &[String], target: &str) {
     }
 }
 
-/// Sends ssdp:byebye for all three notification types. Called on shutdown.
+/// Sends ssdp:byebye for every notification type. Called on shutdown.
 pub async fn send_notify_byebye(config: &Config) {
     let target = format!("{}:{}", config.multicast_address, config.multicast_port);
     let messages = build_byebye_messages(&config.udn, &target);
@@ -113,3 +138,141 @@ pub fn start_ssdp_advertiser(config: Config) -> tokio::task::JoinHandle<()> {
         }
     })
 }
+
+/// Extracts a case-insensitive header value from an SSDP request.
+fn header_value<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+    message.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Parses an M-SEARCH request and returns (ST, MX seconds), or None if the
+/// datagram is not a well-formed ssdp:discover search.
+fn parse_msearch(message: &str) -> Option<(String, u64)> {
+    if !message.starts_with("M-SEARCH * HTTP/1.1") {
+        return None;
+    }
+    if header_value(message, "MAN")?.trim_matches('"') != "ssdp:discover" {
+        return None;
+    }
+    let st = header_value(message, "ST")?.to_string();
+    let mx = header_value(message, "MX")
+        .and_then(|mx| mx.parse::<u64>().ok())
+        .unwrap_or(1)
+        .min(MAX_MX_SECS);
+    Some((st, mx))
+}
+
+/// Returns the (ST, USN) pairs to answer for a given search target.
+fn search_matches(st: &str, udn: &str) -> Vec<(String, String)> {
+    let entries = notify_entries(udn);
+    if st == "ssdp:all" {
+        entries
+    } else {
+        entries.into_iter().filter(|(nt, _)| nt == st).collect()
+    }
+}
+
+fn build_search_response(location: &str, st: &str, usn: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\n\
+         CACHE-CONTROL: max-age={}\r\n\
+         DATE: {}\r\n\
+         EXT:\r\n\
+         LOCATION: {}\r\n\
+         SERVER: {}\r\n\
+         ST: {}\r\n\
+         USN: {}\r\n\
+         \r\n",
+        CACHE_MAX_AGE,
+        httpdate::fmt_http_date(SystemTime::now()),
+        location,
+        SERVER_TOKEN,
+        st,
+        usn
+    )
+}
+
+/// Binds the SSDP multicast port with address reuse (other UPnP stacks on the
+/// same host usually hold it too) and joins the multicast group.
+fn bind_multicast(group: Ipv4Addr, port: u16) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port).into())?;
+    socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Spawns a background task that listens on the SSDP multicast group and
+/// answers M-SEARCH requests matching this server. Each response is unicast
+/// back to the searcher after a random delay within the request's MX window.
+pub fn start_ssdp_responder(config: Config) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let group: Ipv4Addr = match config.multicast_address.parse() {
+            Ok(group) => group,
+            Err(_) => {
+                eprintln!(
+                    "SSDP responder: invalid multicast address '{}'",
+                    config.multicast_address
+                );
+                return;
+            }
+        };
+        let socket = match bind_multicast(group, config.multicast_port) {
+            Ok(socket) => socket,
+            Err(e) => {
+                eprintln!("SSDP responder: failed to join multicast group: {}", e);
+                return;
+            }
+        };
+        let location = format!("{}/description.xml", config.base_url());
+
+        let mut buf = [0u8; 2048];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("SSDP responder: recv error: {}", e);
+                    sleep(RECV_ERROR_BACKOFF).await;
+                    continue;
+                }
+            };
+            let message = String::from_utf8_lossy(&buf[..len]);
+            let Some((st, mx)) = parse_msearch(&message) else {
+                continue;
+            };
+
+            let matches = search_matches(&st, &config.udn);
+            if matches.is_empty() {
+                continue;
+            }
+            let responses: Vec<String> = matches
+                .iter()
+                .map(|(st, usn)| build_search_response(&location, st, usn))
+                .collect();
+            tokio::spawn(send_search_responses(peer, responses, mx));
+        }
+    })
+}
+
+async fn send_search_responses(peer: SocketAddr, responses: Vec<String>, mx: u64) {
+    let delay_ms = rand::random_range(0..=mx.max(1) * 1000);
+    sleep(Duration::from_millis(delay_ms)).await;
+
+    match UdpSocket::bind("0.0.0.0:0").await {
+        Ok(socket) => {
+            for response in &responses {
+                if let Err(e) = socket.send_to(response.as_bytes(), peer).await {
+                    eprintln!("SSDP responder: send to {} failed: {}", peer, e);
+                }
+            }
+        }
+        Err(e) => eprintln!("SSDP responder: failed to bind reply socket: {}", e),
+    }
+}
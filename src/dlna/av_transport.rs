@@ -86,6 +86,29 @@ pub async fn seek(
     .map(|_| ())
 }
 
+/// Playback position of the current track as `H:MM:SS`, from
+/// GetPositionInfo's `RelTime`.
+pub async fn get_position(
+    client: &SoapClient,
+    url: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let body = soap::build_action(
+        AV_TRANSPORT,
+        "GetPositionInfo",
+        "<InstanceID>0</InstanceID>",
+    );
+    let response = soap::send(
+        client,
+        url,
+        &soap::action_header(AV_TRANSPORT, "GetPositionInfo"),
+        &body,
+    )
+    .await?;
+    soap::extract_tag(&response, "RelTime")
+        .filter(|time| !time.is_empty() && time != "NOT_IMPLEMENTED")
+        .ok_or_else(|| "renderer did not report a position".into())
+}
+
 pub async fn get_transport_state(
     client: &SoapClient,
     url: &str,
@@ -22,3 +22,20 @@ pub async fn prepare_connection(
     .await
     .map(|_| ())
 }
+
+/// The renderer's Sink protocolInfo list: every `protocol:network:mime:info`
+/// it can play, comma separated.
+pub async fn get_protocol_info(
+    client: &SoapClient,
+    url: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let body = soap::build_action(CONNECTION_MANAGER, "GetProtocolInfo", "");
+    let response = soap::send(
+        client,
+        url,
+        &soap::action_header(CONNECTION_MANAGER, "GetProtocolInfo"),
+        &body,
+    )
+    .await?;
+    Ok(soap::extract_tag(&response, "Sink").unwrap_or_default())
+}
@@ -1,31 +1,248 @@
+use crate::dlna::profile::profile_name;
+use crate::media::art::Thumbnail;
+use crate::media::finder::Subtitle;
+use crate::media::formats;
+use crate::media::manager::{MediaClass, MediaFile};
+use crate::media::seek::format_npt;
 use crate::soap::xml_escape;
 
+/// Opening tag of every DIDL-Lite document, with the namespaces used by
+/// RustCast's items and containers.
+const DIDL_OPEN: &str = r#"<DIDL-Lite xmlns="urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:upnp="urn:schemas-upnp-org:metadata-1-0/upnp/" xmlns:dlna="urn:schemas-dlna-org:metadata-1-0/" xmlns:sec="http://www.sec.co.kr/""#;
+
+/// DLNA.ORG_FLAGS bits advertising streaming transfer mode and related support.
+const DLNA_FLAGS: &str = "01700000000000000000000000000000";
+
+/// DLNA.ORG_FLAGS for thumbnails and photos: interactive and background
+/// transfer modes.
+const DLNA_IMAGE_FLAGS: &str = "00f00000000000000000000000000000";
+
+/// DLNA features for streamed media: the protocolInfo fourth field and the
+/// `contentFeatures.dlna.org` header. DLNA.ORG_PN names the media profile
+/// when one applies; DLNA.ORG_OP=01 means byte-range seeks only, 11 adds
+/// time-based seeks, offered for files with a seek index. Photos are
+/// flagged for interactive rather than streaming transfer.
+pub fn dlna_features(file: Option<&MediaFile>, time_seek: bool) -> String {
+    let profile = file
+        .and_then(profile_name)
+        .map(|name| format!("DLNA.ORG_PN={};", name))
+        .unwrap_or_default();
+    let flags = if file.is_some_and(|file| file.class() == MediaClass::Image) {
+        DLNA_IMAGE_FLAGS
+    } else {
+        DLNA_FLAGS
+    };
+    format!(
+        "{}DLNA.ORG_OP={};DLNA.ORG_FLAGS={}",
+        profile,
+        if time_seek { "11" } else { "01" },
+        flags
+    )
+}
+
+/// DLNA features of a remote resource relayed by the `/proxy` endpoint:
+/// no profile, as nothing is known of the content, and byte seeks only
+/// when the origin accepts ranges.
+pub fn relay_features(class: MediaClass, byte_seek: bool) -> String {
+    format!(
+        "DLNA.ORG_OP={};DLNA.ORG_FLAGS={}",
+        if byte_seek { "01" } else { "00" },
+        if class == MediaClass::Image {
+            DLNA_IMAGE_FLAGS
+        } else {
+            DLNA_FLAGS
+        }
+    )
+}
+
+/// `contentFeatures.dlna.org` of a thumbnail served by the `/art` endpoint.
+pub fn thumbnail_features(profile: Thumbnail) -> String {
+    format!(
+        "DLNA.ORG_PN={};DLNA.ORG_OP=00;DLNA.ORG_FLAGS={}",
+        profile.profile_id(),
+        DLNA_IMAGE_FLAGS
+    )
+}
+
 /// Builds a DIDL-Lite XML metadata string, already XML-escaped for embedding
 /// directly in a SOAP body (as the value of CurrentURIMetaData).
-pub fn build(title: &str, media_url: &str, mime_type: &str, subtitle_url: Option<&str>) -> String {
-    let title_esc = xml_escape(title);
-    let url_esc = xml_escape(media_url);
-    let mime_esc = xml_escape(mime_type);
-
-    let (sec_ns, subtitle_elements) = match subtitle_url {
-        Some(url) => {
-            let esc = xml_escape(url);
-            (
-                r#" xmlns:sec="http://www.sec.co.kr/""#.to_string(),
-                format!(
-                    r#"<res protocolInfo="http-get:*:text/srt:*">{}</res><sec:CaptionInfoEx sec:type="srt">{}</sec:CaptionInfoEx>"#,
-                    esc, esc
-                ),
+pub fn build(
+    file: &MediaFile,
+    media_url: &str,
+    subtitles: &[Subtitle],
+    time_seek: bool,
+    art_url: Option<&str>,
+) -> String {
+    let didl = wrap_didl(&item_element(
+        "0", "-1", file, media_url, subtitles, time_seek, art_url,
+    ));
+
+    // Must be XML-escaped when embedded as text content inside the SOAP envelope
+    xml_escape(&didl)
+}
+
+/// Like `build`, for a remote resource cast through the `/proxy` endpoint.
+pub fn build_remote(title: &str, proxy_url: &str, mime_type: &str, byte_seek: bool) -> String {
+    let class = formats::class_of_mime(mime_type);
+    let didl = format!(
+        r#"{}><item id="0" parentID="-1" restricted="1"><dc:title>{}</dc:title><upnp:class>{}</upnp:class><res protocolInfo="http-get:*:{}:{}">{}</res></item></DIDL-Lite>"#,
+        DIDL_OPEN,
+        xml_escape(title),
+        upnp_class(class),
+        xml_escape(mime_type),
+        relay_features(class, byte_seek),
+        xml_escape(proxy_url)
+    );
+    xml_escape(&didl)
+}
+
+/// Wraps already-rendered `<item>`/`<container>` elements in a DIDL-Lite root.
+/// The result is not escaped; callers embedding it in SOAP must escape it.
+pub fn wrap_didl(objects: &str) -> String {
+    format!("{}>{}</DIDL-Lite>", DIDL_OPEN, objects)
+}
+
+/// Maps a media class to the UPnP object class used for browsing.
+pub fn upnp_class(class: MediaClass) -> &'static str {
+    match class {
+        MediaClass::Video => "object.item.videoItem",
+        MediaClass::Audio => "object.item.audioItem.musicTrack",
+        MediaClass::Image => "object.item.imageItem.photo",
+        MediaClass::Other => "object.item",
+    }
+}
+
+/// Renders a single DIDL-Lite `<item>` for a streamable file.
+pub fn item_element(
+    id: &str,
+    parent_id: &str,
+    file: &MediaFile,
+    media_url: &str,
+    subtitles: &[Subtitle],
+    time_seek: bool,
+    art_url: Option<&str>,
+) -> String {
+    let mime_type = file.mime_type();
+    format!(
+        r#"<item id="{}" parentID="{}" restricted="1"><dc:title>{}</dc:title><upnp:class>{}</upnp:class>{}{}<res protocolInfo="http-get:*:{}:{}"{}>{}</res>{}</item>"#,
+        xml_escape(id),
+        xml_escape(parent_id),
+        xml_escape(&file.name),
+        upnp_class(file.class()),
+        artist_elements(file),
+        album_art(art_url),
+        xml_escape(mime_type),
+        dlna_features(Some(file), time_seek),
+        res_attributes(file),
+        xml_escape(media_url),
+        subtitle_elements(subtitles)
+    )
+}
+
+/// `upnp:artist` and `dc:creator` for a file whose tags name an artist;
+/// music apps list and search by either.
+fn artist_elements(file: &MediaFile) -> String {
+    match file.info.as_ref().and_then(|info| info.artist.as_deref()) {
+        Some(artist) => {
+            let artist = xml_escape(artist);
+            format!(
+                "<upnp:artist>{}</upnp:artist><dc:creator>{}</dc:creator>",
+                artist, artist
             )
         }
-        None => (String::new(), String::new()),
+        None => String::new(),
+    }
+}
+
+/// `upnp:albumArtURI` elements for each thumbnail profile of the art at
+/// `art_url`; empty when there is no art.
+fn album_art(art_url: Option<&str>) -> String {
+    let Some(url) = art_url else {
+        return String::new();
     };
+    Thumbnail::ALL
+        .into_iter()
+        .map(|profile| {
+            format!(
+                r#"<upnp:albumArtURI dlna:profileID="{}">{}</upnp:albumArtURI>"#,
+                profile.profile_id(),
+                xml_escape(&format!("{}?profile={}", url, profile.profile_id()))
+            )
+        })
+        .collect()
+}
 
-    let didl = format!(
-        r#"<DIDL-Lite xmlns="urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:upnp="urn:schemas-upnp-org:metadata-1-0/upnp/"{}><item id="0" parentID="-1" restricted="1"><dc:title>{}</dc:title><res protocolInfo="http-get:*:{}:DLNA.ORG_OP=01">{}</res>{}</item></DIDL-Lite>"#,
-        sec_ns, title_esc, mime_esc, url_esc, subtitle_elements
-    );
+/// One `<res>` per subtitle track, then Samsung's `sec:CaptionInfoEx` for
+/// the first one, which renderers that take a single track will show.
+fn subtitle_elements(subtitles: &[Subtitle]) -> String {
+    let mut elements: String = subtitles
+        .iter()
+        .map(|subtitle| {
+            format!(
+                r#"<res protocolInfo="http-get:*:{}:*">{}</res>"#,
+                xml_escape(subtitle.mime_type()),
+                xml_escape(&subtitle.url)
+            )
+        })
+        .collect();
+    if let Some(first) = subtitles.first() {
+        elements.push_str(&format!(
+            r#"<sec:CaptionInfoEx sec:type="{}">{}</sec:CaptionInfoEx>"#,
+            first.format.extension(),
+            xml_escape(&first.url)
+        ));
+    }
+    elements
+}
 
-    // Must be XML-escaped when embedded as text content inside the SOAP envelope
-    xml_escape(&didl)
+/// Optional `<res>` attributes: the file size plus whatever probing found.
+/// Renderers use `duration` for their progress bar and to allow seeking.
+fn res_attributes(file: &MediaFile) -> String {
+    let mut attributes = format!(r#" size="{}""#, file.size);
+    let Some(info) = &file.info else {
+        return attributes;
+    };
+    if let Some(seconds) = info.duration_secs() {
+        attributes.push_str(&format!(r#" duration="{}""#, format_npt(seconds)));
+    }
+    if let Some((width, height)) = info.resolution {
+        attributes.push_str(&format!(r#" resolution="{}x{}""#, width, height));
+    }
+    if let Some(bitrate) = info.bitrate {
+        // UPnP counts bytes per second
+        attributes.push_str(&format!(r#" bitrate="{}""#, bitrate / 8));
+    }
+    if let Some(channels) = info.audio_channels {
+        attributes.push_str(&format!(r#" nrAudioChannels="{}""#, channels));
+    }
+    if let Some(rate) = info.sample_rate {
+        attributes.push_str(&format!(r#" sampleFrequency="{}""#, rate));
+    }
+    attributes
+}
+
+/// Class of plain folders and of the virtual views.
+pub const STORAGE_FOLDER: &str = "object.container.storageFolder";
+
+/// Class of containers listing a playlist's entries in order.
+pub const PLAYLIST_CONTAINER: &str = "object.container.playlistContainer";
+
+/// Renders a DIDL-Lite `<container>` of the given `upnp:class`.
+pub fn container_element(
+    id: &str,
+    parent_id: &str,
+    title: &str,
+    class: &str,
+    child_count: usize,
+    art_url: Option<&str>,
+) -> String {
+    format!(
+        r#"<container id="{}" parentID="{}" restricted="1" searchable="1" childCount="{}"><dc:title>{}</dc:title><upnp:class>{}</upnp:class>{}</container>"#,
+        xml_escape(id),
+        xml_escape(parent_id),
+        child_count,
+        xml_escape(title),
+        class,
+        album_art(art_url)
+    )
 }
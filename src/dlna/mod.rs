@@ -1,3 +1,5 @@
 pub mod av_transport;
 pub mod connection_manager;
 pub mod metadata;
+pub mod profile;
+pub mod search;
@@ -0,0 +1,114 @@
+//! DLNA media format profiles (`DLNA.ORG_PN`).
+//!
+//! Strict renderers (Sony, LG) only play items whose protocolInfo names a
+//! profile they support. The profile is derived from the container and the
+//! probed codecs and resolution, or else taken from the format registry;
+//! files that fit no profile get none, which lenient renderers accept and
+//! strict ones would reject anyway.
+
+use crate::media::manager::MediaFile;
+use crate::media::probe::MediaInfo;
+
+/// The DLNA profile name for a file, if one applies.
+pub fn profile_name(file: &MediaFile) -> Option<&str> {
+    let ext = file.extension();
+    let empty = MediaInfo::default();
+    let info = file.info.as_ref().unwrap_or(&empty);
+
+    match ext.as_str() {
+        "jpg" | "jpeg" => Some(jpeg_profile(info.resolution)),
+        "png" => Some("PNG_LRG"),
+        "mp3" => mp3_profile(info),
+        "m4a" => aac_profile(info),
+        "mp4" | "m4v" => mp4_video_profile(info),
+        "mkv" => mkv_video_profile(info),
+        _ => None,
+    }
+    .or(file.format.profile.as_deref())
+}
+
+/// JPEG_SM up to 640x480, JPEG_MED up to 1024x768, JPEG_LRG beyond; files
+/// of unknown size are assumed large.
+fn jpeg_profile(resolution: Option<(u32, u32)>) -> &'static str {
+    match resolution {
+        Some((w, h)) if w <= 640 && h <= 480 => "JPEG_SM",
+        Some((w, h)) if w <= 1024 && h <= 768 => "JPEG_MED",
+        _ => "JPEG_LRG",
+    }
+}
+
+/// MP3 covers MPEG-1 Layer III at 32/44.1/48 kHz; MP3X the extended rates.
+fn mp3_profile(info: &MediaInfo) -> Option<&'static str> {
+    match info.sample_rate {
+        Some(32000 | 44100 | 48000) | None => Some("MP3"),
+        Some(_) => Some("MP3X"),
+    }
+}
+
+fn aac_profile(info: &MediaInfo) -> Option<&'static str> {
+    if info.audio_codec.as_deref() != Some("aac") {
+        return None;
+    }
+    if info.audio_channels.is_some_and(|channels| channels > 2) {
+        Some("AAC_MULT5_ISO")
+    } else if info.bitrate.is_some_and(|rate| rate <= 320_000) {
+        Some("AAC_ISO_320")
+    } else {
+        Some("AAC_ISO")
+    }
+}
+
+fn mp4_video_profile(info: &MediaInfo) -> Option<&'static str> {
+    let audio = info.audio_codec.as_deref();
+    match info.video_codec.as_deref()? {
+        "h264" => match (definition(info.resolution)?, audio) {
+            (Definition::Sd, Some("aac") | None) => Some("AVC_MP4_MP_SD_AAC_MULT5"),
+            (Definition::Sd, Some("ac3")) => Some("AVC_MP4_MP_SD_AC3"),
+            (Definition::Hd720, Some("aac") | None) => Some("AVC_MP4_MP_HD_720p_AAC"),
+            (Definition::Hd1080, Some("aac") | None) => Some("AVC_MP4_MP_HD_1080i_AAC"),
+            (Definition::Hd720 | Definition::Hd1080, Some("ac3")) => Some("AVC_MP4_MP_HD_AC3"),
+            _ => None,
+        },
+        "mpeg4" => match audio {
+            Some("aac") | None => Some("MPEG4_P2_MP4_ASP_AAC"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The Matroska AVC profiles are all HD; High Profile is the superset, so it
+/// is used as the codec profile level is not probed. Nothing above full HD
+/// fits them.
+fn mkv_video_profile(info: &MediaInfo) -> Option<&'static str> {
+    if info.video_codec.as_deref()? != "h264" {
+        return None;
+    }
+    definition(info.resolution)?;
+    match info.audio_codec.as_deref() {
+        Some("aac") | None => Some("AVC_MKV_HP_HD_AAC_MULT5"),
+        Some("ac3") => Some("AVC_MKV_HP_HD_AC3"),
+        Some("mp3") => Some("AVC_MKV_HP_HD_MPEG1_L3"),
+        _ => None,
+    }
+}
+
+enum Definition {
+    Sd,
+    Hd720,
+    Hd1080,
+}
+
+/// Buckets a resolution by the limits of the AVC SD/HD profiles. Unknown
+/// resolutions are treated as full HD so the profile does not undersell;
+/// anything larger (4K/UHD) fits no profile, and renderers that trust the
+/// profile would refuse or stutter on it.
+fn definition(resolution: Option<(u32, u32)>) -> Option<Definition> {
+    match resolution {
+        Some((w, h)) if w <= 720 && h <= 576 => Some(Definition::Sd),
+        Some((w, h)) if w <= 1280 && h <= 720 => Some(Definition::Hd720),
+        Some((w, h)) if w <= 1920 && h <= 1080 => Some(Definition::Hd1080),
+        Some(_) => None,
+        None => Some(Definition::Hd1080),
+    }
+}
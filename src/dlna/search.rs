@@ -0,0 +1,438 @@
+//! Parser and evaluator for the UPnP ContentDirectory search-criteria grammar:
+//!
+//! ```text
+//! searchCrit  ::= searchExp | '*'
+//! searchExp   ::= relExp | searchExp logOp searchExp | '(' searchExp ')'
+//! logOp       ::= 'and' | 'or'                 ('and' binds tighter)
+//! relExp      ::= property binOp quotedVal | property 'exists' boolVal
+//! binOp       ::= '=' | '!=' | '<' | '<=' | '>' | '>='
+//!               | 'contains' | 'doesNotContain' | 'derivedfrom'
+//! ```
+
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Criteria {
+    /// `*` — matches every object.
+    All,
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare {
+        property: String,
+        op: Op,
+        value: String,
+    },
+    Exists {
+        property: String,
+        exists: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    DoesNotContain,
+    DerivedFrom,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Quoted(String),
+    Word(String),
+}
+
+/// Parses a SearchCriteria string. An empty string is treated like `*`.
+pub fn parse(input: &str) -> Result<Criteria, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() || trimmed == "*" {
+        return Ok(Criteria::All);
+    }
+
+    let tokens = tokenize(trimmed)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(format!("unexpected token {:?}", parser.tokens[parser.pos]));
+    }
+    Ok(Criteria::Expr(expr))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    const OPERATOR_CHARS: &[char] = &['=', '!', '<', '>'];
+
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => match chars.next() {
+                        Some(escaped) => value.push(escaped),
+                        None => return Err("unterminated escape".to_string()),
+                    },
+                    Some('"') => break,
+                    Some(other) => value.push(other),
+                    None => return Err("unterminated quoted value".to_string()),
+                }
+            }
+            tokens.push(Token::Quoted(value));
+        } else if OPERATOR_CHARS.contains(&c) {
+            let mut op = String::new();
+            while let Some(&c) = chars.peek() {
+                if !OPERATOR_CHARS.contains(&c) {
+                    break;
+                }
+                op.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(op));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                    break;
+                }
+                if OPERATOR_CHARS.contains(&c) {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_primary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Word(property)) => self.parse_relation(property),
+            other => Err(format!("expected property, found {:?}", other)),
+        }
+    }
+
+    fn parse_relation(&mut self, property: String) -> Result<Expr, String> {
+        let op = match self.next() {
+            Some(Token::Word(op)) => op,
+            other => return Err(format!("expected operator, found {:?}", other)),
+        };
+
+        if op.eq_ignore_ascii_case("exists") {
+            let exists = match self.next() {
+                Some(Token::Word(value)) if value.eq_ignore_ascii_case("true") => true,
+                Some(Token::Word(value)) if value.eq_ignore_ascii_case("false") => false,
+                other => return Err(format!("expected true/false, found {:?}", other)),
+            };
+            return Ok(Expr::Exists { property, exists });
+        }
+
+        let op = match op.as_str() {
+            "=" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            _ if op.eq_ignore_ascii_case("contains") => Op::Contains,
+            _ if op.eq_ignore_ascii_case("doesNotContain") => Op::DoesNotContain,
+            _ if op.eq_ignore_ascii_case("derivedfrom") => Op::DerivedFrom,
+            _ => return Err(format!("unknown operator '{}'", op)),
+        };
+
+        match self.next() {
+            Some(Token::Quoted(value)) => Ok(Expr::Compare {
+                property,
+                op,
+                value,
+            }),
+            other => Err(format!("expected quoted value, found {:?}", other)),
+        }
+    }
+}
+
+impl Criteria {
+    /// Fails on the first property that is not one of `searchable`, which
+    /// could otherwise never match and would go unnoticed.
+    pub fn check_properties(&self, searchable: &[&str]) -> Result<(), String> {
+        match self {
+            Criteria::All => Ok(()),
+            Criteria::Expr(expr) => expr.check_properties(searchable),
+        }
+    }
+
+    /// Evaluates the criteria against one object. `lookup` returns the value
+    /// of a property (e.g. `dc:title`, `upnp:class`, `@id`) or None when the
+    /// object does not have it.
+    pub fn matches(&self, lookup: &dyn Fn(&str) -> Option<String>) -> bool {
+        match self {
+            Criteria::All => true,
+            Criteria::Expr(expr) => expr.matches(lookup),
+        }
+    }
+}
+
+impl Expr {
+    fn check_properties(&self, searchable: &[&str]) -> Result<(), String> {
+        match self {
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.check_properties(searchable)?;
+                right.check_properties(searchable)
+            }
+            Expr::Compare { property, .. } | Expr::Exists { property, .. } => {
+                if searchable.contains(&property.as_str()) {
+                    Ok(())
+                } else {
+                    Err(format!("unsupported property '{}'", property))
+                }
+            }
+        }
+    }
+
+    fn matches(&self, lookup: &dyn Fn(&str) -> Option<String>) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(lookup) && right.matches(lookup),
+            Expr::Or(left, right) => left.matches(lookup) || right.matches(lookup),
+            Expr::Exists { property, exists } => lookup(property).is_some() == *exists,
+            Expr::Compare {
+                property,
+                op,
+                value,
+            } => match lookup(property) {
+                Some(actual) => compare(&actual, *op, value),
+                // A missing property only satisfies the negative string test.
+                None => *op == Op::DoesNotContain,
+            },
+        }
+    }
+}
+
+/// String comparisons are case-insensitive; relational operators compare
+/// numerically when both sides parse as numbers.
+fn compare(actual: &str, op: Op, expected: &str) -> bool {
+    let actual_lower = actual.to_lowercase();
+    let expected_lower = expected.to_lowercase();
+
+    let ordering = || match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => actual_lower.cmp(&expected_lower),
+    };
+
+    match op {
+        Op::Eq => ordering() == Ordering::Equal,
+        Op::Ne => ordering() != Ordering::Equal,
+        Op::Lt => ordering() == Ordering::Less,
+        Op::Le => ordering() != Ordering::Greater,
+        Op::Gt => ordering() == Ordering::Greater,
+        Op::Ge => ordering() != Ordering::Less,
+        Op::Contains => actual_lower.contains(&expected_lower),
+        Op::DoesNotContain => !actual_lower.contains(&expected_lower),
+        Op::DerivedFrom => {
+            actual_lower == expected_lower
+                || actual_lower.starts_with(&format!("{}.", expected_lower))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(property: &str, op: Op, value: &str) -> Expr {
+        Expr::Compare {
+            property: property.to_string(),
+            op,
+            value: value.to_string(),
+        }
+    }
+
+    fn matches(criteria: &str, properties: &[(&str, &str)]) -> bool {
+        parse(criteria).unwrap().matches(&|name| {
+            properties
+                .iter()
+                .find(|(property, _)| *property == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn empty_and_star_match_everything() {
+        assert_eq!(parse("").unwrap(), Criteria::All);
+        assert_eq!(parse(" * ").unwrap(), Criteria::All);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let a = compare("dc:title", Op::Eq, "a");
+        let b = compare("dc:title", Op::Eq, "b");
+        let c = compare("dc:title", Op::Eq, "c");
+        assert_eq!(
+            parse(r#"dc:title = "a" or dc:title = "b" and dc:title = "c""#).unwrap(),
+            Criteria::Expr(Expr::Or(
+                Box::new(a.clone()),
+                Box::new(Expr::And(Box::new(b.clone()), Box::new(c.clone())))
+            ))
+        );
+        assert_eq!(
+            parse(r#"(dc:title = "a" or dc:title = "b") and dc:title = "c""#).unwrap(),
+            Criteria::Expr(Expr::And(
+                Box::new(Expr::Or(Box::new(a), Box::new(b))),
+                Box::new(c)
+            ))
+        );
+    }
+
+    #[test]
+    fn precedence_decides_the_result() {
+        let props = [("dc:title", "a")];
+        assert!(matches(
+            r#"dc:title = "a" or dc:title = "b" and dc:title = "c""#,
+            &props
+        ));
+        assert!(!matches(
+            r#"(dc:title = "a" or dc:title = "b") and dc:title = "c""#,
+            &props
+        ));
+    }
+
+    #[test]
+    fn derivedfrom_matches_the_class_and_its_subclasses() {
+        let video = [("upnp:class", "object.item.videoItem")];
+        assert!(matches(r#"upnp:class derivedfrom "object.item""#, &video));
+        assert!(matches(
+            r#"upnp:class derivedfrom "object.item.videoItem""#,
+            &video
+        ));
+        assert!(!matches(
+            r#"upnp:class derivedfrom "object.item.video""#,
+            &video
+        ));
+        assert!(!matches(
+            r#"upnp:class derivedfrom "object.container""#,
+            &video
+        ));
+    }
+
+    #[test]
+    fn contains_ignores_case() {
+        let props = [("dc:title", "Holiday Video")];
+        assert!(matches(r#"dc:title contains "holiday""#, &props));
+        assert!(!matches(r#"dc:title doesNotContain "VIDEO""#, &props));
+        // A missing property satisfies only the negative test
+        assert!(!matches(r#"upnp:artist contains "x""#, &props));
+        assert!(matches(r#"upnp:artist doesNotContain "x""#, &props));
+    }
+
+    #[test]
+    fn exists_checks_presence() {
+        let props = [("upnp:artist", "Band")];
+        assert!(matches("upnp:artist exists true", &props));
+        assert!(!matches("upnp:artist exists false", &props));
+        assert!(matches("dc:creator exists false", &props));
+        assert!(parse("upnp:artist exists maybe").is_err());
+    }
+
+    #[test]
+    fn relational_operators_compare_numbers_numerically() {
+        let props = [("@id", "10")];
+        assert!(matches(r#"@id > "9""#, &props));
+        assert!(matches(r#"@id <= "10""#, &props));
+        assert!(matches(r#"@id != "1""#, &props));
+    }
+
+    #[test]
+    fn quoted_values_unescape() {
+        assert_eq!(
+            parse(r#"dc:title = "say \"hi\" \\ bye""#).unwrap(),
+            Criteria::Expr(compare("dc:title", Op::Eq, r#"say "hi" \ bye"#))
+        );
+        assert!(parse(r#"dc:title = "open"#).is_err());
+        assert!(parse(r#"dc:title = "trailing\"#).is_err());
+    }
+
+    #[test]
+    fn malformed_criteria_are_rejected() {
+        assert!(parse(r#"dc:title "a""#).is_err());
+        assert!(parse(r#"dc:title like "a""#).is_err());
+        assert!(parse(r#"(dc:title = "a""#).is_err());
+        assert!(parse(r#"dc:title = "a" dc:title"#).is_err());
+        assert!(parse(r#"dc:title = a"#).is_err());
+    }
+
+    #[test]
+    fn unknown_properties_are_rejected() {
+        let searchable = ["dc:title", "upnp:class"];
+        let known = parse(r#"dc:title = "a" and upnp:class exists true"#).unwrap();
+        assert!(known.check_properties(&searchable).is_ok());
+        let unknown = parse(r#"dc:title = "a" or upnp:album = "b""#).unwrap();
+        assert!(unknown.check_properties(&searchable).is_err());
+        assert!(Criteria::All.check_properties(&searchable).is_ok());
+    }
+}
@@ -1,4 +1,5 @@
 use std::path::Path;
+use std::sync::Arc;
 
 mod config;
 mod discovery;
@@ -9,20 +10,51 @@ mod soap;
 mod tui;
 
 use config::Config;
-use discovery::advertise::{send_notify_byebye, start_ssdp_advertiser};
-use media::manager::list_media_files;
+use discovery::advertise::{send_notify_byebye, start_ssdp_advertiser, start_ssdp_responder};
+use media::library::Library;
 use server::http_server::start_http_server;
+use server::state::{start_library_events, ServerState};
 use soap::new_soap_client;
 use tui::TerminalGuard;
 
+// ── command line ──────────────────────────────────────────────────────────────
+
+const USAGE: &str = "Usage: rustcast [--cast-url <URL>]";
+
+/// Reads `--cast-url <URL>` (or `--cast-url=<URL>`): a remote URL to cast
+/// through the relay as soon as a device is connected in the TUI.
+fn parse_args() -> Result<Option<String>, String> {
+    let mut cast_url = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.split_once('=') {
+            Some(("--cast-url", url)) => cast_url = Some(url.to_string()),
+            _ if arg == "--cast-url" => {
+                cast_url = Some(args.next().ok_or("--cast-url needs a URL")?);
+            }
+            _ if arg == "-h" || arg == "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+    Ok(cast_url)
+}
+
 // ── main ──────────────────────────────────────────────────────────────────────
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::from_env().unwrap_or_else(|e| {
+    let cast_url = parse_args().unwrap_or_else(|e| {
+        eprintln!("Error: {}\n{}", e, USAGE);
+        std::process::exit(2);
+    });
+    let mut config = Config::from_env().unwrap_or_else(|e| {
         eprintln!("Configuration error: {}", e);
         std::process::exit(1);
     });
+    config.cast_url = cast_url;
 
     if !Path::new(&config.media_directory).exists() {
         eprintln!(
@@ -32,12 +64,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Err("Invalid media directory".into());
     }
 
-    let server_config = config.clone();
+    let library = Arc::new(Library::new(
+        &config.media_directory,
+        &config.state_directory,
+        &config.formats,
+    ));
+    // Without a watcher the library is simply never refreshed; not fatal.
+    let _watcher = library
+        .watch()
+        .map_err(|e| eprintln!("Warning: live library updates disabled: {}", e))
+        .ok();
+    library.reconcile_in_background();
+    library.probe_in_background();
+
+    let server_state = Arc::new(ServerState::new(config.clone(), Arc::clone(&library)));
+    let events_task = start_library_events(Arc::clone(&server_state));
+    let throttle = Arc::clone(&server_state.throttle);
+    let sessions = Arc::clone(&server_state.sessions);
+    let relays = Arc::clone(&server_state.relays);
     let server_task = tokio::spawn(async move {
-        start_http_server(server_config.http_port, server_config).await;
+        start_http_server(server_state.config.http_port, server_state).await;
     });
 
     let advertiser_task = start_ssdp_advertiser(config.clone());
+    let responder_task = start_ssdp_responder(config.clone());
 
     let soap_client = new_soap_client();
 
@@ -49,15 +99,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         hook(info);
     }));
 
-    let media_files = list_media_files(&config.media_directory);
-
     let mut terminal = TerminalGuard::new()?;
-    tui::run_app(&mut terminal, media_files, config.clone(), soap_client).await?;
+    tui::run_app(
+        &mut terminal,
+        library,
+        throttle,
+        sessions,
+        relays,
+        config.clone(),
+        soap_client,
+    )
+    .await?;
     drop(terminal);
 
     // ── shutdown ──────────────────────────────────────────────────────────────
     send_notify_byebye(&config).await;
     advertiser_task.abort();
+    responder_task.abort();
+    events_task.abort();
     server_task.abort();
     Ok(())
 }
@@ -0,0 +1,37 @@
+//! Matroska: an attachment named `cover.*`, as mkvmerge and most taggers
+//! name it. Other attachments (fonts, small covers) are ignored.
+
+use std::path::Path;
+
+use crate::media::seek::mkv::{elements, Segment};
+
+const ATTACHMENTS: u32 = 0x1941_A469;
+const ATTACHED_FILE: u32 = 0x61A7;
+const FILE_NAME: u32 = 0x466E;
+const FILE_DATA: u32 = 0x465C;
+
+pub fn extract(path: &Path) -> Option<Vec<u8>> {
+    let segment = Segment::read(path, &[ATTACHMENTS])?;
+    let cover = elements(segment.get(ATTACHMENTS)?)
+        .filter(|(id, _)| *id == ATTACHED_FILE)
+        .find_map(|(_, attachment)| {
+            let mut name = None;
+            let mut data = None;
+            for (id, body) in elements(attachment) {
+                match id {
+                    FILE_NAME => name = std::str::from_utf8(body).ok(),
+                    FILE_DATA => data = Some(body),
+                    _ => {}
+                }
+            }
+            let is_cover = name
+                .and_then(|name| name.rsplit_once('.'))
+                .is_some_and(|(stem, _)| stem.eq_ignore_ascii_case("cover"));
+            if is_cover {
+                data.map(<[u8]>::to_vec)
+            } else {
+                None
+            }
+        });
+    cover
+}
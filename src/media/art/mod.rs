@@ -0,0 +1,264 @@
+//! Cover art for items and folders, served to renderers as thumbnails.
+//!
+//! A file's art is the picture embedded in it (an ID3 `APIC` frame, the MP4
+//! `covr` item or a Matroska `cover.*` attachment), or else an image next to
+//! it: `<name>.jpg`, `folder.jpg` or `poster.jpg`. A folder's art is its own
+//! `folder.jpg` or `poster.jpg`. Renderers get the picture scaled down to the
+//! DLNA thumbnail profiles JPEG_TN and PNG_TN.
+
+mod mkv;
+mod mp3;
+mod mp4;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+
+use crate::media::manager::mtime_secs;
+
+/// Sidecar image extensions, in order of preference.
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+/// Sidecar names that stand for a whole folder, in order of preference.
+const FOLDER_IMAGES: &[&str] = &["folder", "poster"];
+
+/// Largest width and height of a JPEG_TN or PNG_TN image.
+const THUMBNAIL_SIZE: u32 = 160;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Thumbnails kept in memory; the cache is emptied when it fills up.
+const MAX_CACHED_THUMBNAILS: usize = 512;
+
+/// DLNA thumbnail profile of a served image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Thumbnail {
+    Jpeg,
+    Png,
+}
+
+impl Thumbnail {
+    pub const ALL: [Thumbnail; 2] = [Thumbnail::Jpeg, Thumbnail::Png];
+
+    /// `DLNA.ORG_PN` name, also used in `/art` URLs.
+    pub fn profile_id(self) -> &'static str {
+        match self {
+            Thumbnail::Jpeg => "JPEG_TN",
+            Thumbnail::Png => "PNG_TN",
+        }
+    }
+
+    pub fn from_profile_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.profile_id() == id)
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Thumbnail::Jpeg => "image/jpeg",
+            Thumbnail::Png => "image/png",
+        }
+    }
+
+    fn encode(self, image: &DynamicImage) -> Option<Vec<u8>> {
+        let mut out = Cursor::new(Vec::new());
+        match self {
+            Thumbnail::Jpeg => image
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))
+                .ok()?,
+            Thumbnail::Png => image.write_to(&mut out, ImageFormat::Png).ok()?,
+        }
+        Some(out.into_inner())
+    }
+}
+
+/// Where a picture comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArtSource {
+    /// Embedded in the media file at this path.
+    Embedded(PathBuf),
+    /// An image file.
+    Image(PathBuf),
+}
+
+impl ArtSource {
+    fn path(&self) -> &Path {
+        match self {
+            ArtSource::Embedded(path) | ArtSource::Image(path) => path,
+        }
+    }
+
+    fn load(&self) -> Option<Vec<u8>> {
+        match self {
+            ArtSource::Embedded(path) => embedded(path),
+            ArtSource::Image(path) => std::fs::read(path).ok(),
+        }
+    }
+}
+
+/// Art for the media file at `path`: its embedded picture, or else a
+/// sidecar image named after it or after the folder. `embedded` says whether
+/// it has an embedded picture, as recorded by the probe (see `has_embedded`),
+/// so the picture itself is not read here.
+pub fn file_art(path: &Path, embedded: bool) -> Option<ArtSource> {
+    if embedded {
+        return Some(ArtSource::Embedded(path.to_path_buf()));
+    }
+    let stem = path.file_stem()?.to_str()?;
+    let names: Vec<&str> = [stem]
+        .into_iter()
+        .chain(FOLDER_IMAGES.iter().copied())
+        .collect();
+    find_image(path.parent()?, &names)
+}
+
+/// Art for the directory at `dir`.
+pub fn folder_art(dir: &Path) -> Option<ArtSource> {
+    find_image(dir, FOLDER_IMAGES)
+}
+
+/// Whether the media file at `path` has an embedded picture. Reads the
+/// picture, so it is done once by the background probe and stored in the
+/// index (`MediaInfo::embedded_art`).
+pub fn has_embedded(path: &Path) -> bool {
+    embedded(path).is_some()
+}
+
+/// The picture embedded in a media file, in whatever format it was stored.
+fn embedded(path: &Path) -> Option<Vec<u8>> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match ext.as_str() {
+        "mp3" => mp3::extract(path),
+        "mp4" | "m4v" | "m4a" | "mov" => mp4::extract(path),
+        "mkv" | "webm" | "mka" => mkv::extract(path),
+        _ => None,
+    }
+}
+
+/// First image in `dir` named `<name>.<ext>` for a name in `names` and an
+/// extension in IMAGE_EXTENSIONS, compared case-insensitively.
+fn find_image(dir: &Path, names: &[&str]) -> Option<ArtSource> {
+    let entries: Vec<(String, PathBuf)> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            (name, entry.path())
+        })
+        .collect();
+    names
+        .iter()
+        .flat_map(|name| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .map(move |ext| format!("{}.{}", name.to_lowercase(), ext))
+        })
+        .find_map(|wanted| {
+            entries
+                .iter()
+                .find(|(name, path)| *name == wanted && path.is_file())
+        })
+        .map(|(_, path)| ArtSource::Image(path.clone()))
+}
+
+/// Decodes the picture and scales it to fit the thumbnail profile.
+pub fn thumbnail(source: &ArtSource, profile: Thumbnail) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(&source.load()?).ok()?;
+    let image = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image
+    };
+    profile.encode(&image)
+}
+
+/// Size and mtime of a path plus the mtime of its directory, which changes
+/// when a sidecar image is added or removed.
+type Stamp = (u64, u64, u64);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = path.metadata().ok()?;
+    let dir_modified = path
+        .parent()
+        .and_then(|dir| dir.metadata().ok())
+        .map(|dir| mtime_secs(&dir))
+        .unwrap_or(0);
+    Some((metadata.len(), mtime_secs(&metadata), dir_modified))
+}
+
+/// Keyed by path; the flag is the `embedded` the lookup was made with.
+type Sources = HashMap<PathBuf, (Stamp, bool, Option<ArtSource>)>;
+type Thumbnails = HashMap<(ArtSource, Thumbnail), (Stamp, Arc<Vec<u8>>)>;
+
+/// Art lookups and thumbnails shared by ContentDirectory, the `/art`
+/// endpoint and casts from the TUI. Entries are redone when the file or its directory changes.
+#[derive(Clone, Default)]
+pub struct ArtCache {
+    sources: Arc<Mutex<Sources>>,
+    thumbnails: Arc<Mutex<Thumbnails>>,
+}
+
+impl ArtCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Art for the media file at `path`; see `file_art`. Lists the file's
+    /// directory on a cache miss but never reads the picture.
+    pub fn file_source(&self, path: &Path, embedded: bool) -> Option<ArtSource> {
+        self.source(path, embedded, || file_art(path, embedded))
+    }
+
+    /// Art for the directory at `dir`. Lists it on a cache miss.
+    pub fn folder_source(&self, dir: &Path) -> Option<ArtSource> {
+        self.source(dir, false, || folder_art(dir))
+    }
+
+    fn source(
+        &self,
+        path: &Path,
+        embedded: bool,
+        lookup: impl FnOnce() -> Option<ArtSource>,
+    ) -> Option<ArtSource> {
+        let stamp = stamp(path)?;
+        if let Some((cached, cached_embedded, source)) = self.sources.lock().unwrap().get(path) {
+            if *cached == stamp && *cached_embedded == embedded {
+                return source.clone();
+            }
+        }
+
+        let source = lookup();
+        self.sources
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), (stamp, embedded, source.clone()));
+        source
+    }
+
+    /// The picture from `source` in the given profile; see `thumbnail`.
+    pub fn thumbnail(&self, source: &ArtSource, profile: Thumbnail) -> Option<Arc<Vec<u8>>> {
+        let stamp = stamp(source.path())?;
+        let key = (source.clone(), profile);
+        if let Some((cached, data)) = self.thumbnails.lock().unwrap().get(&key) {
+            if *cached == stamp {
+                return Some(Arc::clone(data));
+            }
+        }
+
+        let data = Arc::new(thumbnail(source, profile)?);
+        let mut thumbnails = self.thumbnails.lock().unwrap();
+        if thumbnails.len() >= MAX_CACHED_THUMBNAILS {
+            thumbnails.clear();
+        }
+        thumbnails.insert(key, (stamp, Arc::clone(&data)));
+        Some(data)
+    }
+}
@@ -0,0 +1,58 @@
+//! MP3: the `APIC` frame of an ID3v2.3/2.4 tag, preferring the front cover.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::media::seek::mp3::{id3_frames, id3v2_size};
+
+/// Largest tag read in full; covers are rarely over a few megabytes.
+const MAX_TAG_SIZE: usize = 16 * 1024 * 1024;
+
+/// APIC picture type of the front cover.
+const FRONT_COVER: u8 = 3;
+
+pub fn extract(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    let size = id3v2_size(&header)?;
+    if size > MAX_TAG_SIZE {
+        return None;
+    }
+    let mut tag = header.to_vec();
+    file.take((size - header.len()) as u64)
+        .read_to_end(&mut tag)
+        .ok()?;
+
+    let pictures: Vec<(u8, &[u8])> = id3_frames(&tag)
+        .filter(|(id, _)| *id == b"APIC")
+        .filter_map(|(_, body)| picture(body))
+        .collect();
+    let (_, data) = pictures
+        .iter()
+        .find(|(kind, _)| *kind == FRONT_COVER)
+        .or(pictures.first())?;
+    Some(data.to_vec())
+}
+
+/// Picture type and image data of an APIC body: text encoding, MIME type,
+/// picture type, description, then the image.
+fn picture(body: &[u8]) -> Option<(u8, &[u8])> {
+    let encoding = *body.first()?;
+    let mime_end = 1 + body.get(1..)?.iter().position(|&b| b == 0)?;
+    let kind = *body.get(mime_end + 1)?;
+    let description = body.get(mime_end + 2..)?;
+    // UTF-16 descriptions end in a double null on an even offset.
+    let data_start = if encoding == 1 || encoding == 2 {
+        description
+            .chunks_exact(2)
+            .position(|pair| pair == [0, 0])?
+            * 2
+            + 2
+    } else {
+        description.iter().position(|&b| b == 0)? + 1
+    };
+    let data = description.get(data_start..)?;
+    (!data.is_empty()).then_some((kind, data))
+}
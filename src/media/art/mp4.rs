@@ -0,0 +1,22 @@
+//! MP4: the iTunes-style `covr` item in `moov/udta/meta/ilst`.
+
+use std::fs::File;
+use std::path::Path;
+
+use crate::media::seek::mp4::{find_box, find_path, read_top_level_box};
+
+pub fn extract(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let moov = read_top_level_box(&mut file, b"moov")?;
+    let meta = find_path(&moov, &[b"udta", b"meta"])?;
+    // ISO `meta` is a full box with version and flags before its children;
+    // QuickTime writes it without them.
+    let children = match meta.get(..4) {
+        Some([0, 0, 0, 0]) => &meta[4..],
+        _ => meta,
+    };
+    let data = find_path(children, &[b"ilst", b"covr"]).and_then(|covr| find_box(covr, b"data"))?;
+    // Type indicator and locale precede the image.
+    let image = data.get(8..)?;
+    (!image.is_empty()).then(|| image.to_vec())
+}
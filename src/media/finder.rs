@@ -1,31 +1,185 @@
+use crate::config::Config;
 use crate::media::manager::MediaFile;
+use crate::media::subtitle::SubtitleFormat;
 use std::path::Path;
 
-/// Looks for a `.srt` subtitle alongside the media file (same base name).
-/// Returns the HTTP URL the renderer uses to fetch it, or None if not found.
-pub fn find_subtitle(media_file: &MediaFile, http_address: &str, http_port: u16) -> Option<String> {
-    let path = Path::new(&media_file.path);
-    let stem = path.file_stem()?.to_str()?;
-    let dir = path.parent()?;
-
-    for ext in &["srt", "SRT"] {
-        let candidate = dir.join(format!("{}.{}", stem, ext));
-        if candidate.exists() {
-            let parent_rel = Path::new(&media_file.relative_path)
-                .parent()
-                .map(|p| p.to_string_lossy().replace('\\', "/"))
-                .unwrap_or_default();
-            let filename = candidate.file_name()?.to_string_lossy().to_string();
-            let rel = if parent_rel.is_empty() {
-                filename
-            } else {
-                format!("{}/{}", parent_rel, filename)
-            };
-            return Some(format!(
-                "http://{}:{}/media/{}",
-                http_address, http_port, rel
-            ));
+/// File name tags that are short enough to pass for a language but are not.
+const NON_LANGUAGE_TAGS: &[&str] = &["sdh", "cc"];
+
+/// A subtitle file found next to a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subtitle {
+    /// HTTP URL the renderer fetches it from, on the `/subtitle` endpoint.
+    pub url: String,
+    /// Path relative to the media root.
+    pub relative_path: String,
+    /// Language tag from the file name, e.g. `en` or `pt-BR`.
+    pub language: Option<String>,
+    /// Format of the file on disk.
+    pub source: SubtitleFormat,
+    /// Format `url` serves it in.
+    pub format: SubtitleFormat,
+    /// Milliseconds `url` shifts every cue by.
+    pub offset_ms: i64,
+}
+
+impl Subtitle {
+    /// MIME type announced in DIDL.
+    pub fn mime_type(&self) -> &'static str {
+        self.format.mime_type()
+    }
+
+    /// The same track served in another format.
+    pub fn with_format(self, format: SubtitleFormat, config: &Config) -> Subtitle {
+        Subtitle { format, ..self }.with_url(config)
+    }
+
+    /// The same track with every cue shifted by `offset_ms`.
+    pub fn with_offset(self, offset_ms: i64, config: &Config) -> Subtitle {
+        Subtitle { offset_ms, ..self }.with_url(config)
+    }
+
+    fn with_url(self, config: &Config) -> Subtitle {
+        Subtitle {
+            url: config.subtitle_url(&self.relative_path, self.format.extension(), self.offset_ms),
+            ..self
+        }
+    }
+
+    /// Short description for the TUI, e.g. `"pt-BR (srt)"`, or
+    /// `"ja (ass → srt)"` when it is converted.
+    pub fn label(&self) -> String {
+        let format = if self.source == self.format {
+            self.source.extension().to_string()
+        } else {
+            format!("{} → {}", self.source.extension(), self.format.extension())
+        };
+        format!(
+            "{} ({})",
+            self.language.as_deref().unwrap_or("untagged"),
+            format
+        )
+    }
+}
+
+/// Every sidecar subtitle of the media file: `<stem>.<ext>`, or with tags in
+/// between such as `<stem>.en.srt` or `<stem>.pt-BR.forced.srt`. The first
+/// tag that looks like a language tag becomes the language. Untagged files
+/// come first, then by language, format and name. Each is served in its own
+/// format, re-encoded as UTF-8. The files are the ones the index matched
+/// when it listed the directory (see `is_subtitle_of`), so nothing is read
+/// from disk here.
+pub fn find_subtitles(media_file: &MediaFile, config: &Config) -> Vec<Subtitle> {
+    let Some(stem) = Path::new(&media_file.name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+    else {
+        return Vec::new();
+    };
+
+    let mut subtitles: Vec<Subtitle> = media_file
+        .subtitles
+        .iter()
+        .filter_map(|relative_path| {
+            let filename = relative_path.rsplit('/').next()?;
+            let (tags, source) = sidecar_parts(stem, filename)?;
+            let language = tags
+                .and_then(|tags| tags.split('.').find(|tag| is_language_tag(tag)))
+                .map(str::to_string);
+            Some(Subtitle {
+                url: config.subtitle_url(relative_path, source.extension(), 0),
+                relative_path: relative_path.clone(),
+                language,
+                source,
+                format: source,
+                offset_ms: 0,
+            })
+        })
+        .collect();
+
+    // Shorter names first, so `movie.srt` wins over `movie.sdh.srt`.
+    subtitles.sort_by_key(|s| {
+        let name = (s.relative_path.len(), s.relative_path.clone());
+        (s.language.clone(), s.source, name)
+    });
+    subtitles
+}
+
+/// Whether `filename`, in the same directory as the media file called
+/// `media_name`, is a sidecar subtitle of it.
+pub fn is_subtitle_of(media_name: &str, filename: &str) -> bool {
+    Path::new(media_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| sidecar_parts(stem, filename).is_some())
+}
+
+/// Tags and format of a sidecar subtitle file name for the media file stem
+/// `stem`, or None when it is not one. The stem is matched ignoring ASCII
+/// case, as `Movie.en.srt` next to `movie.mkv` is meant for it.
+fn sidecar_parts<'a>(stem: &str, filename: &'a str) -> Option<(Option<&'a str>, SubtitleFormat)> {
+    let rest = filename
+        .get(..stem.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(stem))
+        .and_then(|_| filename[stem.len()..].strip_prefix('.'))?;
+    let (tags, ext) = match rest.rsplit_once('.') {
+        Some((tags, ext)) => (Some(tags), ext),
+        None => (None, rest),
+    };
+    Some((tags, SubtitleFormat::from_extension(ext)?))
+}
+
+/// Whether a file name tag looks like a language: two or three letters with
+/// an optional region or script, as in `en`, `por`, `pt-BR` or `zh-Hant`.
+fn is_language_tag(tag: &str) -> bool {
+    let (language, region) = match tag.split_once(['-', '_']) {
+        Some((language, region)) => (language, Some(region)),
+        None => (tag, None),
+    };
+    !NON_LANGUAGE_TAGS.contains(&language.to_lowercase().as_str())
+        && (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && region.is_none_or(|region| {
+            (2..=4).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_names() {
+        assert_eq!(
+            sidecar_parts("movie", "movie.srt"),
+            Some((None, SubtitleFormat::Srt))
+        );
+        assert_eq!(
+            sidecar_parts("movie", "movie.pt-BR.forced.ass"),
+            Some((Some("pt-BR.forced"), SubtitleFormat::Ass))
+        );
+        assert_eq!(sidecar_parts("movie", "movie.nfo"), None);
+        assert_eq!(sidecar_parts("movie", "movies.srt"), None);
+        assert_eq!(sidecar_parts("movie", "movie"), None);
+        assert_eq!(sidecar_parts("filmé", "film.srt"), None);
+    }
+
+    #[test]
+    fn stems_match_ignoring_case() {
+        assert!(is_subtitle_of("movie.mkv", "Movie.EN.srt"));
+        assert!(is_subtitle_of("MOVIE.mkv", "movie.vtt"));
+        assert!(is_subtitle_of("Été.mkv", "Été.srt"));
+        assert!(!is_subtitle_of("Été.mkv", "éTÉ.srt"));
+        assert!(!is_subtitle_of("movie.mkv", "other.srt"));
+    }
+
+    #[test]
+    fn language_tags() {
+        for tag in ["en", "por", "pt-BR", "zh_Hant", "EN"] {
+            assert!(is_language_tag(tag), "{}", tag);
+        }
+        for tag in ["forced", "sdh", "e", "english", "pt-", "en-toolong", "1a"] {
+            assert!(!is_language_tag(tag), "{}", tag);
         }
     }
-    None
 }
@@ -0,0 +1,199 @@
+//! Registry of the file types RustCast knows: each extension's MIME type,
+//! media class and default DLNA profile.
+//!
+//! The built-in table can be extended or overridden with the `MEDIA_FORMATS`
+//! setting. Files whose class is video, audio or image are listed in the
+//! library; the rest (subtitles) are only served.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::media::manager::MediaClass;
+
+/// One built-in entry: extension, MIME type, class and default profile.
+type Builtin = (&'static str, &'static str, MediaClass, Option<&'static str>);
+
+/// Built-in formats. Profiles that depend on the codecs are derived by
+/// `dlna::profile` from the probed file and left out here.
+const BUILTIN: &[Builtin] = &[
+    ("mp4", "video/mp4", MediaClass::Video, None),
+    ("m4v", "video/mp4", MediaClass::Video, None),
+    ("mov", "video/quicktime", MediaClass::Video, None),
+    ("mkv", "video/x-matroska", MediaClass::Video, None),
+    ("webm", "video/webm", MediaClass::Video, None),
+    ("avi", "video/x-msvideo", MediaClass::Video, None),
+    ("ts", "video/mp2t", MediaClass::Video, None),
+    ("m2ts", "video/vnd.dlna.mpeg-tts", MediaClass::Video, None),
+    ("mts", "video/vnd.dlna.mpeg-tts", MediaClass::Video, None),
+    ("mpg", "video/mpeg", MediaClass::Video, None),
+    ("mpeg", "video/mpeg", MediaClass::Video, None),
+    ("mp3", "audio/mpeg", MediaClass::Audio, Some("MP3")),
+    ("flac", "audio/flac", MediaClass::Audio, None),
+    ("m4a", "audio/mp4", MediaClass::Audio, None),
+    ("aac", "audio/aac", MediaClass::Audio, Some("AAC_ADTS")),
+    ("ogg", "audio/ogg", MediaClass::Audio, None),
+    ("opus", "audio/ogg", MediaClass::Audio, None),
+    ("wav", "audio/wav", MediaClass::Audio, None),
+    ("jpg", "image/jpeg", MediaClass::Image, Some("JPEG_LRG")),
+    ("jpeg", "image/jpeg", MediaClass::Image, Some("JPEG_LRG")),
+    ("png", "image/png", MediaClass::Image, Some("PNG_LRG")),
+    ("srt", "application/x-subrip", MediaClass::Other, None),
+    ("vtt", "text/vtt", MediaClass::Other, None),
+    ("ass", "text/x-ssa", MediaClass::Other, None),
+    ("ssa", "text/x-ssa", MediaClass::Other, None),
+    ("smi", "smi/caption", MediaClass::Other, None),
+];
+
+/// MIME type of files with an unknown extension.
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format {
+    pub mime_type: String,
+    pub class: MediaClass,
+    /// `DLNA.ORG_PN` used when none can be derived from the probed file.
+    pub profile: Option<String>,
+}
+
+/// What a file with an unknown extension is served as.
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            mime_type: DEFAULT_MIME_TYPE.to_string(),
+            class: MediaClass::Other,
+            profile: None,
+        }
+    }
+}
+
+/// Formats keyed by lower-case extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatRegistry {
+    formats: BTreeMap<String, Format>,
+}
+
+impl FormatRegistry {
+    pub fn builtin() -> Self {
+        let formats = BUILTIN
+            .iter()
+            .map(|&(ext, mime_type, class, profile)| {
+                let format = Format {
+                    mime_type: mime_type.to_string(),
+                    class,
+                    profile: profile.map(str::to_string),
+                };
+                (ext.to_string(), format)
+            })
+            .collect();
+        Self { formats }
+    }
+
+    /// The built-in table with a `MEDIA_FORMATS` spec applied, such as
+    /// `wmv=video/x-ms-wmv flac=audio/x-flac,FLAC avi=`. Each entry is
+    /// `ext=mime` optionally followed by a class (`video`, `audio`, `image`
+    /// or `other`) and a DLNA profile, comma separated; the class defaults
+    /// to the MIME type's top-level type. `ext=` removes a format. Entries
+    /// are separated by spaces or semicolons.
+    pub fn with_overrides(mut self, spec: &str) -> Result<Self, String> {
+        for entry in spec.split([' ', ';']).filter(|e| !e.is_empty()) {
+            let (ext, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected ext=mime, got '{}'", entry))?;
+            let ext = ext.trim_start_matches('.').to_lowercase();
+            if ext.is_empty() {
+                return Err(format!("missing extension in '{}'", entry));
+            }
+            if value.is_empty() {
+                self.formats.remove(&ext);
+                continue;
+            }
+
+            let mut fields = value.split(',');
+            let mime_type = fields.next().unwrap_or_default().to_lowercase();
+            if !mime_type.contains('/') {
+                return Err(format!("'{}' is not a MIME type", mime_type));
+            }
+            let mut class = class_of_mime(&mime_type);
+            let mut profile = None;
+            for field in fields.filter(|f| !f.is_empty()) {
+                match parse_class(field) {
+                    Some(explicit) if profile.is_none() => class = explicit,
+                    _ if profile.is_none() => profile = Some(field.to_string()),
+                    _ => return Err(format!("too many fields in '{}'", entry)),
+                }
+            }
+            self.formats.insert(
+                ext,
+                Format {
+                    mime_type,
+                    class,
+                    profile,
+                },
+            );
+        }
+        Ok(self)
+    }
+
+    pub fn get(&self, ext: &str) -> Option<&Format> {
+        self.formats.get(&ext.to_lowercase())
+    }
+
+    /// The format of a file, by its extension.
+    pub fn format_of(&self, path: &str) -> Option<&Format> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        self.get(ext)
+    }
+
+    /// MIME type of a file, by its extension.
+    pub fn mime_type(&self, path: &str) -> &str {
+        self.format_of(path)
+            .map(|format| format.mime_type.as_str())
+            .unwrap_or(DEFAULT_MIME_TYPE)
+    }
+
+    /// Whether files with this extension belong in the library.
+    pub fn is_media(&self, ext: &str) -> bool {
+        self.get(ext).is_some_and(|f| f.class != MediaClass::Other)
+    }
+
+    /// Extensions listed in the library, sorted.
+    pub fn media_extensions(&self) -> Vec<String> {
+        self.formats
+            .iter()
+            .filter(|(_, format)| format.class != MediaClass::Other)
+            .map(|(ext, _)| ext.clone())
+            .collect()
+    }
+
+    /// Every MIME type served, sorted and without duplicates.
+    pub fn mime_types(&self) -> Vec<&str> {
+        let mut mimes: Vec<&str> = self
+            .formats
+            .values()
+            .map(|format| format.mime_type.as_str())
+            .collect();
+        mimes.sort_unstable();
+        mimes.dedup();
+        mimes
+    }
+}
+
+fn parse_class(name: &str) -> Option<MediaClass> {
+    match name.to_lowercase().as_str() {
+        "video" => Some(MediaClass::Video),
+        "audio" => Some(MediaClass::Audio),
+        "image" => Some(MediaClass::Image),
+        "other" => Some(MediaClass::Other),
+        _ => None,
+    }
+}
+
+/// Media class implied by a MIME type's top-level type.
+pub fn class_of_mime(mime_type: &str) -> MediaClass {
+    match mime_type.split('/').next() {
+        Some("video") => MediaClass::Video,
+        Some("audio") => MediaClass::Audio,
+        Some("image") => MediaClass::Image,
+        _ => MediaClass::Other,
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::media::finder::is_subtitle_of;
+use crate::media::formats::FormatRegistry;
+use crate::media::manager::{media_file, MediaFile};
+use crate::media::playlist::{is_playlist, Playlist, PLAYLIST_EXTENSIONS};
+use crate::media::probe::MediaInfo;
+use crate::media::subtitle::SubtitleFormat;
+
+/// File name of the index inside the state directory.
+const INDEX_FILE: &str = "library.json";
+
+/// Bumped whenever the on-disk layout, or what is recorded for a file,
+/// changes; older indexes are discarded.
+const INDEX_VERSION: u32 = 7;
+
+/// Snapshot of the media directory as of the last scan, persisted between
+/// runs so startup does not have to walk the whole tree.
+///
+/// Besides the files it records the mtime of every directory. A directory's
+/// mtime changes when entries are added, removed or renamed in it, so on the
+/// next start only directories whose mtime moved need to be listed again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaIndex {
+    version: u32,
+    root: PathBuf,
+    /// Extensions the index was built with: the format registry's media
+    /// extensions followed by the playlist ones.
+    #[serde(default)]
+    extensions: Vec<String>,
+    /// Directory mtime in nanoseconds, keyed by relative path (`""` is the root).
+    directories: BTreeMap<String, u64>,
+    /// Sorted by relative path.
+    files: Vec<MediaFile>,
+    /// Parsed playlist files, sorted by relative path. Unreadable ones are
+    /// left out.
+    #[serde(default)]
+    playlists: Vec<Playlist>,
+    /// The registry files are matched against, from the configuration.
+    #[serde(skip, default = "FormatRegistry::builtin")]
+    formats: FormatRegistry,
+}
+
+impl MediaIndex {
+    /// An index with nothing scanned yet; the first `reconcile` walks the
+    /// whole tree.
+    pub fn empty(root: &Path, formats: &FormatRegistry) -> Self {
+        Self {
+            version: INDEX_VERSION,
+            root: root.to_path_buf(),
+            extensions: indexed_extensions(formats),
+            directories: BTreeMap::new(),
+            files: Vec::new(),
+            playlists: Vec::new(),
+            formats: formats.clone(),
+        }
+    }
+
+    /// Loads the index saved for `root`. Returns None when there is none, it
+    /// cannot be parsed, or it was built for another media directory. When
+    /// the indexed extensions changed since, every directory is
+    /// marked for listing again; probe results are kept. Each file's format
+    /// is looked up again in `formats`.
+    pub fn load(state_directory: &Path, root: &Path, formats: &FormatRegistry) -> Option<Self> {
+        let data = fs::read(state_directory.join(INDEX_FILE)).ok()?;
+        let mut index: MediaIndex = serde_json::from_slice(&data).ok()?;
+        if index.version != INDEX_VERSION || index.root != root {
+            return None;
+        }
+        let extensions = indexed_extensions(formats);
+        if index.extensions != extensions {
+            index.extensions = extensions;
+            index.directories.clear();
+        }
+        index.files.retain_mut(|file| {
+            let ext = file.extension();
+            match formats.get(&ext) {
+                Some(format) if formats.is_media(&ext) => {
+                    file.format = format.clone();
+                    true
+                }
+                _ => false,
+            }
+        });
+        index.formats = formats.clone();
+        Some(index)
+    }
+
+    /// Writes the index atomically (temp file + rename), creating the state
+    /// directory if needed.
+    pub fn save(&self, state_directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(state_directory)?;
+        let target = state_directory.join(INDEX_FILE);
+        let temp = target.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec(self)?)?;
+        fs::rename(temp, target)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn files(&self) -> &[MediaFile] {
+        &self.files
+    }
+
+    pub fn playlists(&self) -> &[Playlist] {
+        &self.playlists
+    }
+
+    /// Brings the index up to date with the disk, listing only directories
+    /// that are new or whose mtime changed since they were recorded.
+    /// Playlists are all read again, since editing one leaves its
+    /// directory's mtime alone.
+    pub fn reconcile(&mut self) {
+        if !self.directories.contains_key("") {
+            self.scan_subtree("");
+            return;
+        }
+
+        let playlists: Vec<String> = self
+            .playlists
+            .iter()
+            .map(|playlist| playlist.relative_path.clone())
+            .collect();
+        for relative in playlists {
+            self.insert_playlist(relative);
+        }
+
+        let recorded: Vec<String> = self.directories.keys().cloned().collect();
+        for relative in recorded {
+            // Dropped while handling an ancestor earlier in this pass.
+            let Some(&mtime) = self.directories.get(&relative) else {
+                continue;
+            };
+            match fs::metadata(self.absolute(&relative)) {
+                Ok(metadata) if metadata.is_dir() => {
+                    if mtime_nanos(&metadata) != mtime {
+                        self.refresh_directory(&relative);
+                    }
+                }
+                _ => self.remove_subtree(&relative),
+            }
+        }
+    }
+
+    /// Re-examines one path reported by the filesystem watcher.
+    pub fn refresh(&mut self, path: &Path) {
+        let Some(relative) = self.relative(path) else {
+            return;
+        };
+
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => self.scan_subtree(&relative),
+            Ok(metadata) if metadata.is_file() && is_playlist(path) => {
+                self.insert_playlist(relative.clone())
+            }
+            Ok(metadata) if metadata.is_file() => {
+                match media_file(&self.root, path, &metadata, &self.formats) {
+                    Some(file) => self.upsert(file),
+                    None => self.remove_subtree(&relative),
+                }
+            }
+            _ => self.remove_subtree(&relative),
+        }
+
+        // Keep the parent's mtime current so the next startup does not list
+        // it again for a change that is already in the index.
+        if !relative.is_empty() {
+            let parent = parent_dir(&relative).to_string();
+            if let Ok(metadata) = fs::metadata(self.absolute(&parent)) {
+                if self.directories.contains_key(&parent) {
+                    self.directories
+                        .insert(parent.clone(), mtime_nanos(&metadata));
+                    // The path may be a subtitle that came or went.
+                    self.match_subtitles(&parent);
+                }
+            }
+        }
+    }
+
+    /// Stores the probe result for `file`, unless the entry has changed on
+    /// disk since it was probed. Returns whether it was stored.
+    pub fn set_info(&mut self, file: &MediaFile, info: MediaInfo) -> bool {
+        let Ok(pos) = self
+            .files
+            .binary_search_by(|f| f.relative_path.cmp(&file.relative_path))
+        else {
+            return false;
+        };
+        let entry = &mut self.files[pos];
+        if entry.size != file.size || entry.modified != file.modified {
+            return false;
+        }
+        entry.info = Some(info);
+        true
+    }
+
+    /// Drops everything under `relative` and walks it again from scratch.
+    /// Files that are unchanged on disk keep what was probed for them.
+    fn scan_subtree(&mut self, relative: &str) {
+        let previous: Vec<MediaFile> = self
+            .files
+            .iter()
+            .filter(|file| file.info.is_some() && is_under(&file.relative_path, relative))
+            .cloned()
+            .collect();
+        self.remove_subtree(relative);
+        self.walk(relative);
+        for file in previous {
+            if let Some(info) = file.info.clone() {
+                self.set_info(&file, info);
+            }
+        }
+    }
+
+    /// Records a directory and, recursively, everything below it. Does not
+    /// follow symlinks (avoids infinite loops).
+    fn walk(&mut self, relative: &str) {
+        let directory = self.absolute(relative);
+        let (Ok(metadata), Ok(entries)) = (fs::metadata(&directory), fs::read_dir(&directory))
+        else {
+            return;
+        };
+        self.directories
+            .insert(relative.to_string(), mtime_nanos(&metadata));
+
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                if let Some(child) = self.relative(&path) {
+                    self.walk(&child);
+                }
+            } else if file_type.is_file() && is_playlist(&path) {
+                if let Some(child) = self.relative(&path) {
+                    self.insert_playlist(child);
+                }
+            } else if file_type.is_file() {
+                if let Some(file) = entry
+                    .metadata()
+                    .ok()
+                    .and_then(|metadata| media_file(&self.root, &path, &metadata, &self.formats))
+                {
+                    self.upsert(file);
+                }
+            }
+        }
+        self.match_subtitles(relative);
+    }
+
+    /// Lists one directory again without descending into sub-directories
+    /// that are already known; new sub-directories are walked in full.
+    fn refresh_directory(&mut self, relative: &str) {
+        let directory = self.absolute(relative);
+        let (Ok(metadata), Ok(entries)) = (fs::metadata(&directory), fs::read_dir(&directory))
+        else {
+            self.remove_subtree(relative);
+            return;
+        };
+        self.directories
+            .insert(relative.to_string(), mtime_nanos(&metadata));
+
+        let mut present_files = HashSet::new();
+        let mut present_dirs = HashSet::new();
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let Some(child) = self.relative(&path) else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !self.directories.contains_key(&child) {
+                    self.walk(&child);
+                }
+                present_dirs.insert(child);
+            } else if file_type.is_file() && is_playlist(&path) {
+                self.insert_playlist(child.clone());
+                present_files.insert(child);
+            } else if file_type.is_file() {
+                if let Some(file) = entry
+                    .metadata()
+                    .ok()
+                    .and_then(|metadata| media_file(&self.root, &path, &metadata, &self.formats))
+                {
+                    self.upsert(file);
+                }
+                present_files.insert(child);
+            }
+        }
+
+        self.files.retain(|file| {
+            parent_dir(&file.relative_path) != relative
+                || present_files.contains(&file.relative_path)
+        });
+        self.playlists.retain(|playlist| {
+            parent_dir(&playlist.relative_path) != relative
+                || present_files.contains(&playlist.relative_path)
+        });
+        let vanished: Vec<String> = self
+            .directories
+            .keys()
+            .filter(|dir| {
+                !dir.is_empty() && parent_dir(dir) == relative && !present_dirs.contains(*dir)
+            })
+            .cloned()
+            .collect();
+        for dir in vanished {
+            self.remove_subtree(&dir);
+        }
+        self.match_subtitles(relative);
+    }
+
+    /// Records the sidecar subtitles of the files directly in the directory
+    /// `relative`, so listings do not have to look for them.
+    fn match_subtitles(&mut self, relative: &str) {
+        let mut names: Vec<String> = fs::read_dir(self.absolute(relative))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| {
+                name.rsplit_once('.')
+                    .is_some_and(|(_, ext)| SubtitleFormat::from_extension(ext).is_some())
+            })
+            .collect();
+        names.sort();
+
+        let prefix = if relative.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", relative)
+        };
+        // Paths with the prefix are contiguous in the sorted list.
+        let start = self.files.partition_point(|f| f.relative_path < prefix);
+        for file in self.files[start..]
+            .iter_mut()
+            .take_while(|f| f.relative_path.starts_with(&prefix))
+            .filter(|f| parent_dir(&f.relative_path) == relative)
+        {
+            file.subtitles = names
+                .iter()
+                .filter(|name| is_subtitle_of(&file.name, name))
+                .map(|name| format!("{}{}", prefix, name))
+                .collect();
+        }
+    }
+
+    /// Inserts or replaces a file, keeping `files` sorted. An entry whose size
+    /// and mtime are unchanged is kept as is, along with anything probed for it.
+    fn upsert(&mut self, file: MediaFile) {
+        match self
+            .files
+            .binary_search_by(|f| f.relative_path.cmp(&file.relative_path))
+        {
+            Ok(pos) => {
+                let existing = &self.files[pos];
+                if existing.size != file.size || existing.modified != file.modified {
+                    self.files[pos] = file;
+                }
+            }
+            Err(pos) => self.files.insert(pos, file),
+        }
+    }
+
+    /// Reads a playlist file and records it, keeping `playlists` sorted.
+    /// One that can no longer be read is dropped.
+    fn insert_playlist(&mut self, relative: String) {
+        let position = self
+            .playlists
+            .binary_search_by(|playlist| playlist.relative_path.cmp(&relative));
+        match (position, Playlist::load(&self.root, &relative)) {
+            (Ok(pos), Some(playlist)) => self.playlists[pos] = playlist,
+            (Ok(pos), None) => {
+                self.playlists.remove(pos);
+            }
+            (Err(pos), Some(playlist)) => self.playlists.insert(pos, playlist),
+            (Err(_), None) => {}
+        }
+    }
+
+    /// Removes a file, or a directory and everything below it.
+    fn remove_subtree(&mut self, relative: &str) {
+        self.files
+            .retain(|file| !is_under(&file.relative_path, relative));
+        self.playlists
+            .retain(|playlist| !is_under(&playlist.relative_path, relative));
+        self.directories.retain(|dir, _| !is_under(dir, relative));
+    }
+
+    /// Path relative to the media root with forward slashes, matching
+    /// `MediaFile::relative_path`; `""` is the root itself.
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    fn absolute(&self, relative: &str) -> PathBuf {
+        if relative.is_empty() {
+            self.root.clone()
+        } else {
+            self.root.join(relative)
+        }
+    }
+}
+
+/// Extensions of the files the index records.
+fn indexed_extensions(formats: &FormatRegistry) -> Vec<String> {
+    let mut extensions = formats.media_extensions();
+    extensions.extend(PLAYLIST_EXTENSIONS.iter().map(|ext| ext.to_string()));
+    extensions
+}
+
+/// Whether `path` is `relative` itself or lies below it.
+fn is_under(path: &str, relative: &str) -> bool {
+    relative.is_empty()
+        || path == relative
+        || path
+            .strip_prefix(relative)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Directory part of a relative path, `""` for the media root.
+fn parent_dir(relative_path: &str) -> &str {
+    relative_path
+        .rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or("")
+}
+
+/// Directory mtimes are compared at full precision: whole seconds would miss
+/// a change made in the same second as the previous scan.
+fn mtime_nanos(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::watch;
+
+use crate::media::art::ArtCache;
+use crate::media::formats::FormatRegistry;
+use crate::media::index::MediaIndex;
+use crate::media::manager::MediaFile;
+use crate::media::playlist::Playlist;
+use crate::media::probe;
+
+/// Quiet period after a filesystem event before the batch is applied, so a
+/// burst (a copy, an unpacked archive) turns into one library update.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Upper bound on how long a batch may keep growing. A download in progress
+/// touches its file continuously; without this it would never settle.
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(2);
+
+/// How often probe results are published while probing. Publishing saves
+/// the whole index and bumps the update counter, which sends a GENA NOTIFY
+/// and reloads the TUI, so a large library must not do it for every file.
+const PROBE_PUBLISH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The in-memory media library shared by the HTTP server and the TUI.
+///
+/// It starts from the on-disk index (see `MediaIndex`) so startup does not
+/// wait for a walk of the media directory, is reconciled with the disk in
+/// the background, and is then kept current by a filesystem watcher (see
+/// `Library::watch`). Files are probed for their stream details in the
+/// background as they appear (see `Library::probe_in_background`). Every
+/// change bumps the update counter; components that care call `subscribe`
+/// and wait for it to move.
+pub struct Library {
+    state_directory: PathBuf,
+    index: Mutex<MediaIndex>,
+    files: RwLock<Arc<Vec<MediaFile>>>,
+    playlists: RwLock<Arc<Vec<Playlist>>>,
+    art: ArtCache,
+    updates: watch::Sender<u64>,
+}
+
+impl Library {
+    pub fn new(media_directory: &str, state_directory: &str, formats: &FormatRegistry) -> Self {
+        let root = Path::new(media_directory);
+        let state_directory = PathBuf::from(state_directory);
+        let index = MediaIndex::load(&state_directory, root, formats)
+            .unwrap_or_else(|| MediaIndex::empty(root, formats));
+        Self {
+            state_directory,
+            files: RwLock::new(Arc::new(index.files().to_vec())),
+            playlists: RwLock::new(Arc::new(index.playlists().to_vec())),
+            index: Mutex::new(index),
+            art: ArtCache::new(),
+            updates: watch::Sender::new(0),
+        }
+    }
+
+    /// Current file list in relative-path order. The snapshot is cheap to
+    /// take and never changes, so callers may hold it across await points.
+    pub fn files(&self) -> Arc<Vec<MediaFile>> {
+        self.files.read().unwrap().clone()
+    }
+
+    /// Playlist files in relative-path order, parsed when the index found
+    /// them or the watcher saw them change. Unreadable ones are left out.
+    pub fn playlists(&self) -> Arc<Vec<Playlist>> {
+        self.playlists.read().unwrap().clone()
+    }
+
+    /// Cover art lookups, shared by everything that lists art for the
+    /// library's files.
+    pub fn art(&self) -> &ArtCache {
+        &self.art
+    }
+
+    /// Number of changes applied since startup.
+    pub fn update_id(&self) -> u64 {
+        *self.updates.borrow()
+    }
+
+    /// Receiver that is notified with the new update counter on every change.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.updates.subscribe()
+    }
+
+    /// Checks the loaded index against the disk on a background thread.
+    /// Start the watcher first so nothing changed meanwhile is missed.
+    pub fn reconcile_in_background(self: &Arc<Self>) {
+        let library = Arc::clone(self);
+        std::thread::spawn(move || library.update(MediaIndex::reconcile));
+    }
+
+    /// Probes every file that has no stream details yet, and keeps doing so
+    /// as files are added. Must be called from within the Tokio runtime.
+    pub fn probe_in_background(self: &Arc<Self>) {
+        let library = Arc::clone(self);
+        let mut updates = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let pending = Arc::clone(&library);
+                // Probing reads file headers; keep it off the async workers.
+                tokio::task::spawn_blocking(move || pending.probe_pending())
+                    .await
+                    .ok();
+                if updates.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Probes the files without stream details. Results go into the index
+    /// as they come and are published every PROBE_PUBLISH_INTERVAL, so they
+    /// show up progressively on a large library without an update per file.
+    fn probe_pending(&self) {
+        let pending: Vec<MediaFile> = self
+            .files()
+            .iter()
+            .filter(|file| file.info.is_none())
+            .cloned()
+            .collect();
+        let mut unpublished = false;
+        let mut published = Instant::now();
+        for file in &pending {
+            let info = probe::probe(Path::new(&file.path));
+            unpublished |= self.index.lock().unwrap().set_info(file, info);
+            if unpublished && published.elapsed() >= PROBE_PUBLISH_INTERVAL {
+                self.publish_files();
+                unpublished = false;
+                published = Instant::now();
+            }
+        }
+        if unpublished {
+            self.publish_files();
+        }
+    }
+
+    /// Publishes the index's file list after probe results were stored in
+    /// it, then saves the index.
+    fn publish_files(&self) {
+        let index = self.index.lock().unwrap();
+        *self.files.write().unwrap() = Arc::new(index.files().to_vec());
+        self.updates.send_modify(|id| *id += 1);
+        self.save(&index);
+    }
+
+    /// Starts watching the media directory. The returned watcher must be
+    /// kept alive for as long as updates are wanted.
+    pub fn watch(self: &Arc<Self>) -> notify::Result<RecommendedWatcher> {
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                // Reads (ours included: scanning, probing, streaming) change
+                // nothing and would otherwise trigger endless rescans.
+                if let Some(event) = event.ok().filter(|event| !event.kind.is_access()) {
+                    for path in event.paths {
+                        tx.send(path).ok();
+                    }
+                }
+            })?;
+        let root = self.index.lock().unwrap().root().to_path_buf();
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        let library = Arc::clone(self);
+        std::thread::spawn(move || {
+            // Ends when the watcher (and with it the sender) is dropped.
+            while let Ok(first) = rx.recv() {
+                let mut paths = vec![first];
+                let started = Instant::now();
+                while started.elapsed() < MAX_BATCH_DELAY {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(path) => paths.push(path),
+                        Err(_) => break,
+                    }
+                }
+                paths.sort();
+                paths.dedup();
+                library.update(|index| {
+                    for path in &paths {
+                        index.refresh(path);
+                    }
+                });
+            }
+        });
+
+        Ok(watcher)
+    }
+
+    /// Applies a change to the index. A different file list, or playlists
+    /// that were added, removed or edited, are published and bump the
+    /// update counter; any change at all is saved to disk.
+    fn update(&self, change: impl FnOnce(&mut MediaIndex)) {
+        let mut index = self.index.lock().unwrap();
+        let before = index.clone();
+        change(&mut index);
+        if *index == before {
+            return;
+        }
+
+        let files_changed = index.files() != before.files();
+        let playlists_changed = index.playlists() != before.playlists();
+        if files_changed {
+            *self.files.write().unwrap() = Arc::new(index.files().to_vec());
+        }
+        if playlists_changed {
+            *self.playlists.write().unwrap() = Arc::new(index.playlists().to_vec());
+        }
+        if files_changed || playlists_changed {
+            self.updates.send_modify(|id| *id += 1);
+        }
+        self.save(&index);
+    }
+
+    fn save(&self, index: &MediaIndex) {
+        if let Err(e) = index.save(&self.state_directory) {
+            eprintln!("Warning: failed to save media index: {}", e);
+        }
+    }
+}
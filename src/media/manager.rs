@@ -1,85 +1,101 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize)]
+use crate::media::formats::{Format, FormatRegistry};
+use crate::media::probe::MediaInfo;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MediaFile {
-    pub name: String,          // bare filename: "movie.mkv"
-    pub path: String,          // absolute filesystem path
-    pub relative_path: String, // relative to media root: "Action/movie.mkv"
+    pub name: String,            // bare filename: "movie.mkv"
+    pub path: String,            // absolute filesystem path
+    pub relative_path: String,   // relative to media root: "Action/movie.mkv"
+    pub modified: u64,           // mtime, seconds since the Unix epoch
+    pub size: u64,               // bytes
+    pub info: Option<MediaInfo>, // probed stream details, None until probed
+    /// Sidecar subtitle files next to it, relative to the media root; see
+    /// `finder::find_subtitles`.
+    pub subtitles: Vec<String>,
+    /// Looked up in the registry by extension; not saved, since the registry
+    /// can change between runs.
+    #[serde(skip)]
+    pub format: Format,
 }
 
-pub fn list_media_files(directory: &str) -> Vec<MediaFile> {
-    let root = Path::new(directory);
-    let mut results = Vec::new();
-    collect_media(root, root, &mut results);
-    results.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
-    results
+/// Broad kind of media a file holds, as given by the format registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaClass {
+    Video,
+    Audio,
+    Image,
+    Other,
 }
 
-/// Recursively collects media files under `current`, computing `relative_path`
-/// relative to `root`. Does not follow symlinks (avoids infinite loops).
-fn collect_media(root: &Path, current: &Path, results: &mut Vec<MediaFile>) {
-    let entries = match std::fs::read_dir(current) {
-        Ok(e) => e,
-        Err(_) => return,
-    };
-
-    for entry in entries.flatten() {
-        let file_type = match entry.file_type() {
-            Ok(t) => t,
-            Err(_) => continue,
-        };
-
-        let path = entry.path();
-
-        if file_type.is_dir() {
-            collect_media(root, &path, results);
-        } else if file_type.is_file() {
-            let ext = path
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or_default();
-
-            if is_supported_format(ext) {
-                let name = path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-
-                let relative_path = path
-                    .strip_prefix(root)
-                    .unwrap_or(&path)
-                    .to_string_lossy()
-                    // Normalise to forward slashes for URL construction on all platforms
-                    .replace('\\', "/");
+impl MediaFile {
+    /// Lower-cased file extension, e.g. `"mkv"`.
+    pub fn extension(&self) -> String {
+        Path::new(&self.name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase()
+    }
 
-                results.push(MediaFile {
-                    name,
-                    path: path.to_string_lossy().to_string(),
-                    relative_path,
-                });
-            }
-        }
+    pub fn class(&self) -> MediaClass {
+        self.format.class
     }
-}
 
-fn is_supported_format(ext: &str) -> bool {
-    matches!(ext.to_lowercase().as_str(), "mp4" | "mkv" | "avi" | "mp3")
+    pub fn mime_type(&self) -> &str {
+        &self.format.mime_type
+    }
 }
 
-pub fn get_mime_type(file_path: &str) -> &'static str {
-    let ext = std::path::Path::new(file_path)
+/// Builds the entry for a regular file under `root`, or None when its
+/// extension is not a media format in the registry.
+pub fn media_file(
+    root: &Path,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    formats: &FormatRegistry,
+) -> Option<MediaFile> {
+    let ext = path
         .extension()
         .and_then(|e| e.to_str())
-        .unwrap_or("");
-
-    match ext.to_lowercase().as_str() {
-        "mp4" => "video/mp4",
-        "mkv" => "video/x-matroska",
-        "avi" => "video/x-msvideo",
-        "mp3" => "audio/mpeg",
-        "srt" => "application/x-subrip",
-        _ => "application/octet-stream",
+        .unwrap_or_default();
+    if !formats.is_media(ext) {
+        return None;
     }
+
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let relative_path = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        // Normalise to forward slashes for URL construction on all platforms
+        .replace('\\', "/");
+
+    Some(MediaFile {
+        name,
+        path: path.to_string_lossy().to_string(),
+        relative_path,
+        modified: mtime_secs(metadata),
+        size: metadata.len(),
+        info: None,
+        subtitles: Vec::new(),
+        format: formats.get(ext)?.clone(),
+    })
+}
+
+/// Modification time in seconds since the Unix epoch, 0 when unavailable.
+pub fn mtime_secs(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
@@ -1,3 +1,13 @@
+pub mod art;
 pub mod finder;
+pub mod formats;
+pub mod index;
+pub mod library;
 pub mod manager;
+pub mod playlist;
+pub mod probe;
+pub mod seek;
 pub mod stream;
+pub mod subtitle;
+pub mod text;
+pub mod views;
@@ -0,0 +1,289 @@
+//! Playlist files kept next to the media: M3U (plain and extended, `.m3u`
+//! and `.m3u8`) and PLS. Entries are resolved against the playlist's own
+//! directory, so a playlist keeps working when its folder is moved or the
+//! media directory is mounted elsewhere.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::media::manager::MediaFile;
+use crate::media::text::decode;
+
+/// Extensions recognised as playlists, lower case.
+pub const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls"];
+
+/// Playlists larger than this are not read; real ones are a few kilobytes.
+const MAX_PLAYLIST_SIZE: u64 = 1024 * 1024;
+
+/// Whether a file is a playlist, by its extension.
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PLAYLIST_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Playlist {
+    /// Path of the playlist file relative to the media root.
+    pub relative_path: String,
+    /// The `#PLAYLIST:` name of an extended M3U, else the file name without
+    /// its extension.
+    pub title: String,
+    /// Referenced files as paths relative to the media root, in play order.
+    /// Entries may name files that are missing or not media; callers match
+    /// them against the library.
+    pub entries: Vec<String>,
+}
+
+impl Playlist {
+    /// Reads and parses a playlist. Returns None when the file cannot be
+    /// read or is too large.
+    pub fn load(root: &Path, relative_path: &str) -> Option<Self> {
+        let path = root.join(relative_path);
+        if fs::metadata(&path).ok()?.len() > MAX_PLAYLIST_SIZE {
+            return None;
+        }
+        let text = decode(&fs::read(&path).ok()?);
+        Some(Self::parse(root, relative_path, &text))
+    }
+
+    fn parse(root: &Path, relative_path: &str, text: &str) -> Self {
+        let is_pls = relative_path.to_lowercase().ends_with(".pls");
+        let (title, locations) = if is_pls {
+            (None, parse_pls(text))
+        } else {
+            parse_m3u(text)
+        };
+        let directory = parent_dir(relative_path);
+        let title = title.unwrap_or_else(|| {
+            let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+            name.rsplit_once('.')
+                .map(|(stem, _)| stem)
+                .unwrap_or(name)
+                .to_string()
+        });
+        Self {
+            relative_path: relative_path.to_string(),
+            title,
+            entries: locations
+                .iter()
+                .filter_map(|location| resolve(root, directory, location))
+                .collect(),
+        }
+    }
+}
+
+/// Writes `files` as a UTF-8 extended M3U named `title`. Entries are paths
+/// relative to the media root, so `path` must be directly in it. Never
+/// replaces an existing file: that fails with `ErrorKind::AlreadyExists`.
+pub fn write_m3u(path: &Path, title: &str, files: &[&MediaFile]) -> io::Result<()> {
+    let mut text = format!("#EXTM3U\n#PLAYLIST:{}\n", title);
+    for file in files {
+        // -1 is the extended M3U way of saying "unknown length"
+        let seconds = file
+            .info
+            .as_ref()
+            .and_then(|info| info.duration_secs())
+            .map(|secs| secs.round() as i64)
+            .unwrap_or(-1);
+        text.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            seconds, file.name, file.relative_path
+        ));
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(text.as_bytes())
+}
+
+/// Locations of an M3U, and the extended M3U `#PLAYLIST:` name if any.
+/// Other `#` lines (`#EXTM3U`, `#EXTINF`, ...) describe the entries and are
+/// skipped.
+fn parse_m3u(text: &str) -> (Option<String>, Vec<String>) {
+    let mut title = None;
+    let mut locations = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("#PLAYLIST:") {
+            title = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            locations.push(line.to_string());
+        }
+    }
+    (title, locations)
+}
+
+/// Locations of a PLS, ordered by their `FileN` number rather than by line.
+fn parse_pls(text: &str) -> Vec<String> {
+    let mut numbered: Vec<(u32, String)> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let number = key
+                .get(..4)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("file"))
+                .and_then(|_| key[4..].trim().parse().ok())?;
+            Some((number, value.trim().to_string()))
+        })
+        .collect();
+    numbered.sort_by_key(|(number, _)| *number);
+    numbered.into_iter().map(|(_, location)| location).collect()
+}
+
+/// Turns a playlist entry into a path relative to the media root. Relative
+/// entries are taken from the playlist's directory; absolute paths and
+/// `file://` URLs must point inside the media root. Remote URLs and entries
+/// that leave the root are dropped.
+fn resolve(root: &Path, directory: &str, location: &str) -> Option<String> {
+    let location = match location.strip_prefix("file://") {
+        Some(path) => percent_decode(path.strip_prefix("localhost").unwrap_or(path)),
+        None if location.contains("://") => return None,
+        None => location.to_string(),
+    };
+    let location = location.replace('\\', "/");
+
+    let (base, rest) = if Path::new(&location).is_absolute() {
+        // The media directory may be configured as a relative path or
+        // through a symlink; compare against its canonical form too.
+        let absolute = Path::new(&location);
+        let relative = absolute
+            .strip_prefix(root)
+            .map(Path::to_path_buf)
+            .ok()
+            .or_else(|| {
+                let canonical = fs::canonicalize(root).ok()?;
+                absolute.strip_prefix(canonical).map(Path::to_path_buf).ok()
+            })?;
+        ("", relative.to_string_lossy().replace('\\', "/"))
+    } else {
+        (directory, location)
+    };
+
+    let mut parts: Vec<&str> = base.split('/').filter(|part| !part.is_empty()).collect();
+    for part in rest.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Decodes `%XX` escapes in a `file://` URL path. Malformed escapes are
+/// kept as they are.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Directory part of a relative path, `""` for the media root.
+fn parent_dir(relative_path: &str) -> &str {
+    relative_path
+        .rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/srv/media";
+
+    fn entries(relative_path: &str, text: &str) -> Vec<String> {
+        Playlist::parse(Path::new(ROOT), relative_path, text).entries
+    }
+
+    #[test]
+    fn extended_m3u() {
+        let text =
+            "#EXTM3U\r\n#PLAYLIST: Road trip \r\n#EXTINF:215,Artist - One\r\none.mp3\r\n\r\n\
+                    #EXTINF:-1,Two\r\n  sub/two.flac  \r\n";
+        let playlist = Playlist::parse(Path::new(ROOT), "music/trip.m3u8", text);
+        assert_eq!(playlist.title, "Road trip");
+        assert_eq!(
+            playlist.entries,
+            vec!["music/one.mp3", "music/sub/two.flac"]
+        );
+    }
+
+    #[test]
+    fn title_falls_back_to_the_file_name() {
+        let playlist = Playlist::parse(Path::new(ROOT), "a/Best.of.m3u", "#PLAYLIST:\nx.mp3");
+        assert_eq!(playlist.title, "Best.of");
+    }
+
+    #[test]
+    fn pls_entries_follow_their_numbers() {
+        let text = "[playlist]\nNumberOfEntries=3\nFile2=b.mp3\nTitle2=B\nfile1 = a.mp3\n\
+                    FILE10=c.mp3\nFileX=bad.mp3\nLength1=-1\nVersion=2\n";
+        assert_eq!(entries("list.PLS", text), vec!["a.mp3", "b.mp3", "c.mp3"]);
+    }
+
+    #[test]
+    fn relative_entries_with_dot_segments() {
+        let text = "../other/a.mp3\n./b.mp3\nc//d.mp3\nsub\\win.mp3\n../../../escape.mp3\n..\n";
+        assert_eq!(
+            entries("music/lists/x.m3u", text),
+            vec![
+                "music/other/a.mp3",
+                "music/lists/b.mp3",
+                "music/lists/c/d.mp3",
+                "music/lists/sub/win.mp3",
+                "music"
+            ]
+        );
+        // Up to the root itself is not an entry
+        assert!(entries("x.m3u", "sub/..\n").is_empty());
+    }
+
+    #[test]
+    fn absolute_entries_must_be_inside_the_root() {
+        let text = "/srv/media/music/a.mp3\n/srv/other/b.mp3\n/srv/media/../c.mp3\n";
+        assert_eq!(entries("lists/x.m3u", text), vec!["music/a.mp3"]);
+    }
+
+    #[test]
+    fn urls() {
+        let text = "file:///srv/media/My%20Music/a%2Bb.mp3\nfile://localhost/srv/media/c.mp3\n\
+                    file:///srv/media/bad%zz.mp3\nhttp://radio.example/stream\nhttps://x/y.mp3\n\
+                    file:///elsewhere/d.mp3\n";
+        assert_eq!(
+            entries("x.m3u", text),
+            vec!["My Music/a+b.mp3", "c.mp3", "bad%zz.mp3"]
+        );
+    }
+
+    #[test]
+    fn playlist_extensions() {
+        assert!(is_playlist(Path::new("a/b.M3U8")));
+        assert!(is_playlist(Path::new("b.pls")));
+        assert!(!is_playlist(Path::new("b.mp3")));
+        assert!(!is_playlist(Path::new("m3u")));
+    }
+}
@@ -0,0 +1,132 @@
+//! AVI: the `hdrl` list at the start of the RIFF file holds the main header
+//! (`avih`: frame duration, frame count, size) and one `strl` list per
+//! stream with its header (`strh`) and format (`strf`).
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::{millis, MediaInfo};
+
+/// Largest `hdrl` list we are willing to load into memory.
+const MAX_HDRL_SIZE: u32 = 1024 * 1024;
+
+/// WAVEFORMATEX format tags.
+const WAVE_PCM: u16 = 0x0001;
+const WAVE_MP2: u16 = 0x0050;
+const WAVE_MP3: u16 = 0x0055;
+const WAVE_AAC: u16 = 0x00FF;
+const WAVE_AC3: u16 = 0x2000;
+const WAVE_DTS: u16 = 0x2001;
+
+pub fn probe(path: &Path) -> Option<MediaInfo> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 24];
+    file.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"AVI " {
+        return None;
+    }
+    if &header[12..16] != b"LIST" || &header[20..24] != b"hdrl" {
+        return None;
+    }
+    let size = le_u32(&header, 16)?;
+    if !(4..=MAX_HDRL_SIZE).contains(&size) {
+        return None;
+    }
+    let mut hdrl = vec![0u8; size as usize - 4];
+    file.read_exact(&mut hdrl).ok()?;
+
+    let mut info = MediaInfo::default();
+    for (id, body) in chunks(&hdrl) {
+        match &id {
+            b"avih" => {
+                let micros_per_frame = le_u32(body, 0)? as f64;
+                let frames = le_u32(body, 16)? as f64;
+                info.duration_ms = millis(micros_per_frame * frames / 1e6);
+                let width = le_u32(body, 32)?;
+                let height = le_u32(body, 36)?;
+                if width > 0 && height > 0 {
+                    info.resolution = Some((width, height));
+                }
+            }
+            b"LIST" if body.starts_with(b"strl") => stream(&body[4..], &mut info),
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+/// Reads one `strl` list into `info`, keeping the first video and the first
+/// audio stream.
+fn stream(strl: &[u8], info: &mut MediaInfo) {
+    let mut kind = None;
+    let mut handler = None;
+    for (id, body) in chunks(strl) {
+        match &id {
+            b"strh" => {
+                kind = body.get(0..4);
+                handler = body.get(4..8);
+            }
+            b"strf" => match kind {
+                Some(b"vids") if info.video_codec.is_none() => {
+                    // BITMAPINFOHEADER biCompression, falling back to the
+                    // stream header's handler.
+                    let fourcc = body.get(16..20).filter(|c| c.iter().any(|&b| b != 0));
+                    info.video_codec = fourcc.or(handler).map(video_codec);
+                }
+                Some(b"auds") if info.audio_codec.is_none() => {
+                    // WAVEFORMATEX
+                    info.audio_codec = le_u16(body, 0).map(audio_codec);
+                    info.audio_channels = le_u16(body, 2).map(u32::from).filter(|&c| c > 0);
+                    info.sample_rate = le_u32(body, 4).filter(|&r| r > 0);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Iterates the RIFF chunks in `data` as `(id, body)`; bodies are padded to
+/// an even length.
+fn chunks(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let id: [u8; 4] = data.get(pos..pos + 4)?.try_into().ok()?;
+        let size = le_u32(data, pos + 4)? as usize;
+        let body = data.get(pos + 8..(pos + 8).checked_add(size)?)?;
+        pos += 8 + size + (size & 1);
+        Some((id, body))
+    })
+}
+
+fn video_codec(fourcc: &[u8]) -> String {
+    let fourcc = String::from_utf8_lossy(fourcc).trim().to_uppercase();
+    match fourcc.as_str() {
+        "H264" | "X264" | "AVC1" => "h264".to_string(),
+        "HEVC" | "H265" | "X265" | "HVC1" => "hevc".to_string(),
+        "XVID" | "DIVX" | "DX50" | "FMP4" | "MP4V" => "mpeg4".to_string(),
+        "MJPG" => "mjpeg".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
+fn audio_codec(format_tag: u16) -> String {
+    match format_tag {
+        WAVE_PCM => "pcm".to_string(),
+        WAVE_MP2 => "mp2".to_string(),
+        WAVE_MP3 => "mp3".to_string(),
+        WAVE_AAC => "aac".to_string(),
+        WAVE_AC3 => "ac3".to_string(),
+        WAVE_DTS => "dts".to_string(),
+        other => format!("0x{:04x}", other),
+    }
+}
+
+fn le_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
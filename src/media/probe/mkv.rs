@@ -0,0 +1,138 @@
+//! Matroska/WebM: duration from `Info`; codec IDs, resolution, channels and
+//! sampling frequency from the entries of `Tracks`; the artist from `Tags`.
+
+use std::path::Path;
+
+use super::{millis, MediaInfo};
+use crate::media::seek::mkv::{elements, float, parse_info, uint, Segment, INFO};
+
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const AUDIO: u32 = 0xE1;
+const SAMPLING_FREQUENCY: u32 = 0xB5;
+const CHANNELS: u32 = 0x9F;
+const TAGS: u32 = 0x1254_C367;
+const TAG: u32 = 0x7373;
+const SIMPLE_TAG: u32 = 0x67C8;
+const TAG_NAME: u32 = 0x45A3;
+const TAG_STRING: u32 = 0x4487;
+
+/// TrackType values.
+const TYPE_VIDEO: u64 = 1;
+const TYPE_AUDIO: u64 = 2;
+
+pub fn probe(path: &Path) -> Option<MediaInfo> {
+    let segment = Segment::read(path, &[INFO, TRACKS, TAGS])?;
+
+    let mut info = MediaInfo::default();
+    if let Some((scale, duration)) = segment.get(INFO).and_then(parse_info) {
+        info.duration_ms = millis(duration * scale as f64 / 1e9);
+    }
+
+    let entries = elements(segment.get(TRACKS).unwrap_or_default())
+        .filter(|(id, _)| *id == TRACK_ENTRY)
+        .map(|(_, entry)| entry);
+    for entry in entries {
+        let mut track_type = 0;
+        let mut codec = None;
+        let mut video = None;
+        let mut audio = None;
+        for (id, body) in elements(entry) {
+            match id {
+                TRACK_TYPE => track_type = uint(body),
+                CODEC_ID => codec = Some(codec_name(&String::from_utf8_lossy(body))),
+                VIDEO => video = Some(body),
+                AUDIO => audio = Some(body),
+                _ => {}
+            }
+        }
+
+        match track_type {
+            TYPE_VIDEO if info.video_codec.is_none() => {
+                info.video_codec = codec;
+                let (mut width, mut height) = (0, 0);
+                for (id, body) in elements(video.unwrap_or_default()) {
+                    match id {
+                        PIXEL_WIDTH => width = uint(body) as u32,
+                        PIXEL_HEIGHT => height = uint(body) as u32,
+                        _ => {}
+                    }
+                }
+                if width > 0 && height > 0 {
+                    info.resolution = Some((width, height));
+                }
+            }
+            TYPE_AUDIO if info.audio_codec.is_none() => {
+                info.audio_codec = codec;
+                // Both default to these values when absent.
+                info.sample_rate = Some(8000);
+                info.audio_channels = Some(1);
+                for (id, body) in elements(audio.unwrap_or_default()) {
+                    match id {
+                        SAMPLING_FREQUENCY => info.sample_rate = float(body).map(|f| f as u32),
+                        CHANNELS => info.audio_channels = Some(uint(body) as u32),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    info.artist = segment.get(TAGS).and_then(artist);
+    Some(info)
+}
+
+/// Value of the first `ARTIST` simple tag in `Tags`.
+fn artist(tags: &[u8]) -> Option<String> {
+    elements(tags)
+        .filter(|(id, _)| *id == TAG)
+        .flat_map(|(_, tag)| elements(tag))
+        .filter(|(id, _)| *id == SIMPLE_TAG)
+        .find_map(|(_, simple_tag)| {
+            let mut name = None;
+            let mut value = None;
+            for (id, body) in elements(simple_tag) {
+                match id {
+                    TAG_NAME => name = Some(String::from_utf8_lossy(body)),
+                    TAG_STRING => value = Some(String::from_utf8_lossy(body)),
+                    _ => {}
+                }
+            }
+            let value = value?.trim_end_matches('\0').trim().to_string();
+            (name?.eq_ignore_ascii_case("ARTIST") && !value.is_empty()).then_some(value)
+        })
+}
+
+/// Short name for a Matroska codec ID such as `V_MPEG4/ISO/AVC`.
+fn codec_name(codec_id: &str) -> String {
+    let codec_id = codec_id.trim_end_matches('\0');
+    let name = match codec_id {
+        "V_MPEG4/ISO/AVC" => "h264",
+        "V_MPEGH/ISO/HEVC" => "hevc",
+        "V_MPEG4/ISO/ASP" | "V_MPEG4/ISO/SP" | "V_MPEG4/ISO/AP" => "mpeg4",
+        "V_MPEG2" => "mpeg2",
+        "V_VP8" => "vp8",
+        "V_VP9" => "vp9",
+        "V_AV1" => "av1",
+        "A_AC3" => "ac3",
+        "A_EAC3" => "eac3",
+        "A_DTS" => "dts",
+        "A_OPUS" => "opus",
+        "A_VORBIS" => "vorbis",
+        "A_FLAC" => "flac",
+        "A_MPEG/L3" => "mp3",
+        id if id.starts_with("A_AAC") => "aac",
+        id => {
+            return id
+                .trim_start_matches("V_")
+                .trim_start_matches("A_")
+                .to_lowercase()
+        }
+    };
+    name.to_string()
+}
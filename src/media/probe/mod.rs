@@ -0,0 +1,129 @@
+//! Stream details read straight from the container, without external tools.
+//!
+//! Renderers show "--:--" and often refuse to seek when the DIDL `<res>` has
+//! no `duration`, so every file is probed once in the background and the
+//! result is stored with its entry in the media index. Each parser reads only
+//! the headers: MP4 `moov`, Matroska `Info`/`Tracks`/`Tags`, AVI `hdrl`,
+//! and the first MP3 frame plus its ID3v2 tag.
+
+mod avi;
+mod mkv;
+mod mp3;
+mod mp4;
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::media::{art, seek};
+
+/// What probing found out about a file. Fields the container does not
+/// declare stay None; a file that could not be parsed has no fields set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub duration_ms: Option<u64>,
+    /// Width and height in pixels of the first video track.
+    pub resolution: Option<(u32, u32)>,
+    /// Average bitrate of the whole file in bits per second.
+    pub bitrate: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_channels: Option<u32>,
+    /// Sample rate of the first audio track in Hz.
+    pub sample_rate: Option<u32>,
+    /// Performer from the file's tags: ID3 `TPE1`, MP4 `©ART` or the
+    /// Matroska `ARTIST` tag.
+    pub artist: Option<String>,
+    /// Whether the file has a seek index (see `seek::read_index`), so
+    /// time-based seeks can be offered without parsing it while browsing.
+    pub time_seek: bool,
+    /// Whether the file embeds a cover picture (see `art::has_embedded`),
+    /// so listings can offer art without reading it.
+    pub embedded_art: bool,
+}
+
+impl MediaInfo {
+    pub fn duration_secs(&self) -> Option<f64> {
+        self.duration_ms.map(|ms| ms as f64 / 1000.0)
+    }
+
+    /// One-line description for the TUI, e.g.
+    /// `"1:32:10  1920x1080  h264/aac  4.5 Mb/s"`. Empty when nothing is known.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ms) = self.duration_ms {
+            parts.push(format_duration(ms));
+        }
+        if let Some((width, height)) = self.resolution {
+            parts.push(format!("{}x{}", width, height));
+        }
+        let codecs: Vec<&str> = [&self.video_codec, &self.audio_codec]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !codecs.is_empty() {
+            parts.push(codecs.join("/"));
+        }
+        if let Some(bitrate) = self.bitrate {
+            parts.push(if bitrate >= 1_000_000 {
+                format!("{:.1} Mb/s", bitrate as f64 / 1e6)
+            } else {
+                format!("{} kb/s", bitrate / 1000)
+            });
+        }
+        parts.join("  ")
+    }
+
+    /// Fills in the overall bitrate from the file size when the container
+    /// does not declare one.
+    fn with_bitrate_from_size(mut self, size: u64) -> Self {
+        if self.bitrate.is_none() {
+            self.bitrate = self
+                .duration_ms
+                .filter(|&ms| ms > 0)
+                .and_then(|ms| u32::try_from(size * 8 * 1000 / ms).ok());
+        }
+        self
+    }
+}
+
+/// Probes the file at `path`, picking the parser by extension. Formats
+/// without a parser, and files that fail to parse, yield an empty
+/// `MediaInfo` so they are not probed again.
+pub fn probe(path: &Path) -> MediaInfo {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let info = match ext.as_str() {
+        "mp4" | "m4v" | "m4a" | "mov" => mp4::probe(path),
+        "mkv" | "webm" | "mka" => mkv::probe(path),
+        "avi" => avi::probe(path),
+        "mp3" => mp3::probe(path),
+        _ => None,
+    };
+    let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+    MediaInfo {
+        time_seek: seek::read_index(path).is_some(),
+        embedded_art: art::has_embedded(path),
+        ..info.unwrap_or_default()
+    }
+    .with_bitrate_from_size(size)
+}
+
+/// Formats a duration as `h:mm:ss`, or `m:ss` when under an hour.
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Seconds to whole milliseconds, None for values that make no sense.
+fn millis(seconds: f64) -> Option<u64> {
+    (seconds.is_finite() && seconds > 0.0).then(|| (seconds * 1000.0).round() as u64)
+}
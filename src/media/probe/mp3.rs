@@ -0,0 +1,78 @@
+//! MP3: channels and sample rate from the first frame header. The duration
+//! comes from the Xing/VBRI frame count when there is one, then from an
+//! ID3v2 `TLEN` frame, and otherwise from the bitrate of a CBR stream. The
+//! artist is the tag's `TPE1` frame.
+
+use std::path::Path;
+
+use super::MediaInfo;
+use crate::media::seek::mp3::{find_first_frame, id3_frames, read_head, vbr_frames, FrameHeader};
+
+pub fn probe(path: &Path) -> Option<MediaInfo> {
+    let (head, file_size) = read_head(path)?;
+    let start = find_first_frame(&head)?;
+    let frame = FrameHeader::parse(head.get(start..start + 4)?)?;
+    let audio_bytes = file_size.saturating_sub(start as u64);
+
+    let duration_ms = match vbr_frames(&head[start..], &frame) {
+        Some(frames) => Some((frame.duration(frames) * 1000.0).round() as u64),
+        None => id3_length(&head)
+            .or_else(|| (frame.bitrate > 0).then(|| audio_bytes * 8 / frame.bitrate as u64)),
+    };
+    let bitrate = duration_ms
+        .filter(|&ms| ms > 0)
+        .and_then(|ms| u32::try_from(audio_bytes * 8 * 1000 / ms).ok());
+
+    Some(MediaInfo {
+        duration_ms,
+        bitrate,
+        audio_codec: Some("mp3".to_string()),
+        audio_channels: Some(if frame.mono { 1 } else { 2 }),
+        sample_rate: Some(frame.sample_rate),
+        artist: id3_text(&head, b"TPE1"),
+        ..MediaInfo::default()
+    })
+}
+
+/// Length in milliseconds from the `TLEN` frame of an ID3v2.3/2.4 tag.
+fn id3_length(head: &[u8]) -> Option<u64> {
+    let (_, body) = id3_frames(head).find(|(id, _)| *id == b"TLEN")?;
+    // First byte is the text encoding; only Latin-1/UTF-8 digits count.
+    let text: String = body
+        .iter()
+        .skip(1)
+        .map(|&b| b as char)
+        .filter(char::is_ascii_digit)
+        .collect();
+    text.parse().ok().filter(|&ms| ms > 0)
+}
+
+/// Text of an ID3v2.3/2.4 text frame such as `TPE1`. Of several values
+/// (separated by nulls in 2.4) only the first is kept.
+fn id3_text(head: &[u8], frame_id: &[u8]) -> Option<String> {
+    let (_, body) = id3_frames(head).find(|(id, _)| *id == frame_id)?;
+    let (&encoding, text) = body.split_first()?;
+    let text = match encoding {
+        0 => text.iter().map(|&b| b as char).collect(),
+        1 | 2 => {
+            let big_endian = encoding == 2 || text.starts_with(&[0xFE, 0xFF]);
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    if big_endian {
+                        u16::from_be_bytes(pair)
+                    } else {
+                        u16::from_le_bytes(pair)
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => return None,
+    };
+    let text = text.trim_start_matches('\u{feff}');
+    let first = text.split('\0').next()?.trim();
+    (!first.is_empty()).then(|| first.to_string())
+}
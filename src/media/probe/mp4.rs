@@ -0,0 +1,94 @@
+//! MP4/QuickTime: duration from `mvhd`; codec, resolution, channels and
+//! sample rate from the first sample description (`stsd`) of each track;
+//! the artist from the iTunes-style `©ART` item.
+
+use std::fs::File;
+use std::path::Path;
+
+use super::{millis, MediaInfo};
+use crate::media::seek::mp4::{
+    be_u32, boxes, find_box, find_path, handler_type, read_top_level_box, timescale_and_duration,
+};
+
+pub fn probe(path: &Path) -> Option<MediaInfo> {
+    let mut file = File::open(path).ok()?;
+    let moov = read_top_level_box(&mut file, b"moov")?;
+
+    let mut info = MediaInfo::default();
+    if let Some((timescale, duration)) = find_box(&moov, b"mvhd").and_then(timescale_and_duration) {
+        if timescale > 0 {
+            info.duration_ms = millis(duration as f64 / timescale as f64);
+        }
+    }
+
+    for (_, track) in boxes(&moov).filter(|(kind, _)| kind == b"trak") {
+        let Some((format, entry)) = sample_entry(track) else {
+            continue;
+        };
+        match handler_type(track) {
+            Some("vide") if info.video_codec.is_none() => {
+                info.video_codec = Some(codec_name(&format));
+                let width = be_u16(entry, 24).unwrap_or(0) as u32;
+                let height = be_u16(entry, 26).unwrap_or(0) as u32;
+                if width > 0 && height > 0 {
+                    info.resolution = Some((width, height));
+                }
+            }
+            Some("soun") if info.audio_codec.is_none() => {
+                info.audio_codec = Some(codec_name(&format));
+                info.audio_channels = be_u16(entry, 16).map(u32::from).filter(|&c| c > 0);
+                // 16.16 fixed point
+                info.sample_rate = be_u32(entry, 24).map(|rate| rate >> 16).filter(|&r| r > 0);
+            }
+            _ => {}
+        }
+    }
+    info.artist = artist(&moov);
+    Some(info)
+}
+
+/// The `©ART` item in `moov/udta/meta/ilst`.
+fn artist(moov: &[u8]) -> Option<String> {
+    let meta = find_path(moov, &[b"udta", b"meta"])?;
+    // ISO `meta` is a full box with version and flags before its children;
+    // QuickTime writes it without them.
+    let children = match meta.get(..4) {
+        Some([0, 0, 0, 0]) => &meta[4..],
+        _ => meta,
+    };
+    let data =
+        find_path(children, &[b"ilst", b"\xA9ART"]).and_then(|item| find_box(item, b"data"))?;
+    // Type indicator and locale precede the text.
+    let text = String::from_utf8_lossy(data.get(8..)?);
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Format and body of the first entry in the track's `stsd`. Visual entries
+/// hold width and height at 24/26; audio entries hold the channel count at
+/// 16 and the sample rate at 24.
+fn sample_entry(track: &[u8]) -> Option<([u8; 4], &[u8])> {
+    let stsd = find_path(track, &[b"mdia", b"minf", b"stbl", b"stsd"])?;
+    boxes(stsd.get(8..)?).next()
+}
+
+fn codec_name(format: &[u8; 4]) -> String {
+    match format {
+        b"avc1" | b"avc3" => "h264".to_string(),
+        b"hvc1" | b"hev1" => "hevc".to_string(),
+        b"av01" => "av1".to_string(),
+        b"vp09" => "vp9".to_string(),
+        b"mp4v" => "mpeg4".to_string(),
+        b"mp4a" => "aac".to_string(),
+        b"ac-3" => "ac3".to_string(),
+        b"ec-3" => "eac3".to_string(),
+        b"Opus" => "opus".to_string(),
+        b"fLaC" => "flac".to_string(),
+        b".mp3" => "mp3".to_string(),
+        other => String::from_utf8_lossy(other).trim().to_lowercase(),
+    }
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
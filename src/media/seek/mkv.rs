@@ -0,0 +1,435 @@
+//! Matroska/WebM: seek points come from the `Cues` element, whose cue
+//! points map a timestamp to the position of the cluster holding it.
+//! `Cues` usually sits after the clusters and is found through `SeekHead`.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const EBML_HEADER: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+pub(crate) const INFO: u32 = 0x1549_A966;
+const TIMECODE_SCALE: u32 = 0x2AD7B1;
+const DURATION: u32 = 0x4489;
+const CUES: u32 = 0x1C53_BB6B;
+const CUE_POINT: u32 = 0xBB;
+const CUE_TIME: u32 = 0xB3;
+const CUE_TRACK_POSITIONS: u32 = 0xB7;
+const CUE_CLUSTER_POSITION: u32 = 0xF1;
+const CLUSTER: u32 = 0x1F43_B675;
+
+/// Default TimecodeScale: timestamps are in milliseconds.
+const DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+/// Largest Info/SeekHead/Cues element we are willing to load into memory.
+const MAX_ELEMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+pub fn read(path: &Path) -> Option<(f64, Vec<(f64, u64)>)> {
+    let segment = Segment::read(path, &[INFO, CUES])?;
+    let (scale, duration) = parse_info(segment.get(INFO)?)?;
+    let to_seconds = |ticks: f64| ticks * scale as f64 / 1e9;
+    // Positions come straight from the file; a cue whose position does not
+    // fit is skipped.
+    let points = parse_cues(segment.get(CUES)?)
+        .into_iter()
+        .filter_map(|(time, position)| {
+            Some((
+                to_seconds(time as f64),
+                segment.start.checked_add(position)?,
+            ))
+        })
+        .collect();
+    Some((to_seconds(duration), points))
+}
+
+/// Selected level-1 elements of the first segment of a Matroska file.
+pub(crate) struct Segment {
+    /// File offset of the segment body; element positions are relative to it.
+    pub start: u64,
+    elements: Vec<(u32, Vec<u8>)>,
+}
+
+impl Segment {
+    /// Loads the bodies of the level-1 elements listed in `wanted`.
+    pub fn read(path: &Path, wanted: &[u32]) -> Option<Self> {
+        let mut file = File::open(path).ok()?;
+
+        let (id, size) = read_header(&mut file)?;
+        if id != EBML_HEADER {
+            return None;
+        }
+        file.seek(SeekFrom::Current(i64::try_from(size?).ok()?))
+            .ok()?;
+
+        let (id, segment_size) = read_header(&mut file)?;
+        if id != SEGMENT {
+            return None;
+        }
+        let start = file.stream_position().ok()?;
+        let end = segment_size.map(|size| start.saturating_add(size));
+
+        let mut segment = Segment {
+            start,
+            elements: Vec::new(),
+        };
+        let mut seek_head: Vec<u8> = Vec::new();
+
+        // Level-1 elements before the first cluster; anything after the
+        // clusters (usually Cues) is reached through the SeekHead instead of
+        // walking every cluster.
+        loop {
+            let pos = file.stream_position().ok()?;
+            if end.is_some_and(|end| pos >= end) {
+                break;
+            }
+            let Some((id, Some(size))) = read_header(&mut file) else {
+                break;
+            };
+            match id {
+                SEEK_HEAD => seek_head.extend(read_body(&mut file, size).into_iter().flatten()),
+                CLUSTER => break,
+                _ if wanted.contains(&id) && segment.get(id).is_none() => {
+                    if let Some(body) = read_body(&mut file, size) {
+                        segment.elements.push((id, body));
+                    }
+                }
+                _ => {
+                    file.seek(SeekFrom::Current(i64::try_from(size).ok()?))
+                        .ok()?;
+                }
+            }
+            if wanted.iter().all(|&id| segment.get(id).is_some()) {
+                return Some(segment);
+            }
+        }
+
+        for (id, position) in seek_entries(&seek_head) {
+            if wanted.contains(&id) && segment.get(id).is_none() {
+                let Some(position) = start.checked_add(position) else {
+                    continue;
+                };
+                if let Some(body) = read_element_at(&mut file, position, id) {
+                    segment.elements.push((id, body));
+                }
+            }
+        }
+        Some(segment)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        self.elements
+            .iter()
+            .find(|(element, _)| *element == id)
+            .map(|(_, body)| body.as_slice())
+    }
+}
+
+/// Reads an element ID and size at the current position. The size is None
+/// for the "unknown size" marker used by live streams.
+fn read_header(file: &mut File) -> Option<(u32, Option<u64>)> {
+    let (id, _) = read_vint(file, true)?;
+    let (size, len) = read_vint(file, false)?;
+    let unknown = size == (1u64 << (7 * len)) - 1;
+    Some((id as u32, (!unknown).then_some(size)))
+}
+
+/// Reads an EBML variable-length integer at the current position; see
+/// `slice_vint`.
+fn read_vint(file: &mut File, keep_marker: bool) -> Option<(u64, usize)> {
+    let mut buf = [0u8; 8];
+    file.read_exact(&mut buf[..1]).ok()?;
+    let len = buf[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    file.read_exact(&mut buf[1..len]).ok()?;
+    slice_vint(&buf[..len], keep_marker)
+}
+
+fn read_body(file: &mut File, size: u64) -> Option<Vec<u8>> {
+    if size > MAX_ELEMENT_SIZE {
+        file.seek(SeekFrom::Current(i64::try_from(size).ok()?))
+            .ok()?;
+        return None;
+    }
+    let mut body = vec![0u8; size as usize];
+    file.read_exact(&mut body).ok()?;
+    Some(body)
+}
+
+fn read_element_at(file: &mut File, position: u64, expected: u32) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(position)).ok()?;
+    match read_header(file)? {
+        (id, Some(size)) if id == expected => read_body(file, size),
+        _ => None,
+    }
+}
+
+/// Iterates the child elements in `data` as `(id, body)`.
+pub(crate) fn elements(data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let (id, id_len) = slice_vint(data.get(pos..)?, true)?;
+        let (size, size_len) = slice_vint(data.get(pos + id_len..)?, false)?;
+        let start = pos + id_len + size_len;
+        let body = data.get(start..start.checked_add(usize::try_from(size).ok()?)?)?;
+        pos = start + body.len();
+        Some((id as u32, body))
+    })
+}
+
+/// Decodes an EBML variable-length integer, returning it and its length in
+/// bytes. IDs keep their length marker bit; sizes do not.
+fn slice_vint(data: &[u8], keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut value = if keep_marker {
+        first as u64
+    } else {
+        first as u64 & (0xFF >> len)
+    };
+    for &byte in data.get(1..len)? {
+        value = (value << 8) | byte as u64;
+    }
+    Some((value, len))
+}
+
+pub(crate) fn uint(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0, |value, &byte| (value << 8) | byte as u64)
+}
+
+pub(crate) fn float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// `(element ID, position relative to the segment)` for each SeekHead entry.
+fn seek_entries(seek_head: &[u8]) -> Vec<(u32, u64)> {
+    elements(seek_head)
+        .filter(|(id, _)| *id == SEEK)
+        .filter_map(|(_, seek)| {
+            let mut target = None;
+            let mut position = None;
+            for (id, body) in elements(seek) {
+                match id {
+                    SEEK_ID => target = Some(uint(body) as u32),
+                    SEEK_POSITION => position = Some(uint(body)),
+                    _ => {}
+                }
+            }
+            Some((target?, position?))
+        })
+        .collect()
+}
+
+/// TimecodeScale (ns per tick) and Duration (in ticks) from Info.
+pub(crate) fn parse_info(info: &[u8]) -> Option<(u64, f64)> {
+    let mut scale = DEFAULT_TIMECODE_SCALE;
+    let mut duration = None;
+    for (id, body) in elements(info) {
+        match id {
+            TIMECODE_SCALE => scale = uint(body),
+            DURATION => duration = float(body),
+            _ => {}
+        }
+    }
+    Some((scale, duration?))
+}
+
+/// `(time in ticks, cluster position relative to the segment)` per cue point.
+fn parse_cues(cues: &[u8]) -> Vec<(u64, u64)> {
+    elements(cues)
+        .filter(|(id, _)| *id == CUE_POINT)
+        .filter_map(|(_, point)| {
+            let mut time = None;
+            let mut position = None;
+            for (id, body) in elements(point) {
+                match id {
+                    CUE_TIME => time = Some(uint(body)),
+                    CUE_TRACK_POSITIONS if position.is_none() => {
+                        position = elements(body)
+                            .find(|(id, _)| *id == CUE_CLUSTER_POSITION)
+                            .map(|(_, body)| uint(body));
+                    }
+                    _ => {}
+                }
+            }
+            Some((time?, position?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::seek::tests::temp_file;
+
+    /// An element with an 8-byte size, as muxers that patch sizes in
+    /// afterwards write them.
+    fn el(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        data.push(0x01);
+        data.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn cue(time: u8, position: &[u8]) -> Vec<u8> {
+        let positions = [el(0xF7, &[1]), el(CUE_CLUSTER_POSITION, position)].concat();
+        el(
+            CUE_POINT,
+            &[el(CUE_TIME, &[time]), el(CUE_TRACK_POSITIONS, &positions)].concat(),
+        )
+    }
+
+    fn info(duration: f64) -> Vec<u8> {
+        el(
+            INFO,
+            &[
+                el(TIMECODE_SCALE, &1_000_000u32.to_be_bytes()),
+                el(DURATION, &duration.to_be_bytes()),
+            ]
+            .concat(),
+        )
+    }
+
+    fn seek(id: u32, position: u64) -> Vec<u8> {
+        el(
+            SEEK,
+            &[
+                el(SEEK_ID, &id.to_be_bytes()),
+                el(SEEK_POSITION, &position.to_be_bytes()),
+            ]
+            .concat(),
+        )
+    }
+
+    fn file(segment: &[u8]) -> Vec<u8> {
+        [el(EBML_HEADER, &el(0x4282, b"webm")), el(SEGMENT, segment)].concat()
+    }
+
+    #[test]
+    fn vints_keep_or_drop_the_marker() {
+        assert_eq!(slice_vint(&[0x81], false), Some((1, 1)));
+        assert_eq!(slice_vint(&[0x81], true), Some((0x81, 1)));
+        assert_eq!(slice_vint(&[0x40, 0x02], false), Some((2, 2)));
+        assert_eq!(
+            slice_vint(&[0x1A, 0x45, 0xDF, 0xA3], true),
+            Some((0x1A45_DFA3, 4))
+        );
+        // No marker bit in the first byte
+        assert_eq!(slice_vint(&[0x00, 1, 2, 3, 4, 5, 6, 7, 8], false), None);
+        // Shorter than the marker says
+        assert_eq!(slice_vint(&[0x40], false), None);
+        assert_eq!(slice_vint(&[], false), None);
+    }
+
+    #[test]
+    fn elements_stop_at_a_truncated_body() {
+        let mut data = [el(CUE_TIME, &[5]), el(CUE_TIME, &[6, 7])].concat();
+        assert_eq!(
+            elements(&data).collect::<Vec<_>>(),
+            vec![(CUE_TIME, &[5][..]), (CUE_TIME, &[6, 7][..])]
+        );
+        data.pop();
+        assert_eq!(elements(&data).count(), 1);
+        // A size too large for the address space
+        let mut huge = vec![0xB3, 0x01];
+        huge.extend_from_slice(&[0xFF; 7]);
+        assert_eq!(elements(&huge).count(), 0);
+    }
+
+    #[test]
+    fn cue_points_need_a_time_and_a_position() {
+        let mut no_position = el(CUE_POINT, &el(CUE_TIME, &[9]));
+        no_position.extend(cue(0, &[0x10]));
+        no_position.extend(cue(3, &[0x01, 0x00]));
+        assert_eq!(parse_cues(&no_position), vec![(0, 0x10), (3, 0x100)]);
+    }
+
+    #[test]
+    fn info_needs_a_duration() {
+        assert_eq!(parse_info(&[]), None);
+        let body = &info(1500.0)[12..];
+        assert_eq!(parse_info(body), Some((1_000_000, 1500.0)));
+        let float32 = el(DURATION, &2500f32.to_be_bytes());
+        assert_eq!(parse_info(&float32), Some((DEFAULT_TIMECODE_SCALE, 2500.0)));
+        assert_eq!(float(&[0; 3]), None);
+        assert_eq!(uint(&[0x01, 0x00]), 256);
+    }
+
+    #[test]
+    fn seek_entries_skip_incomplete_seeks() {
+        let head = [
+            seek(CUES, 0x400),
+            el(SEEK, &el(SEEK_ID, &INFO.to_be_bytes())),
+        ]
+        .concat();
+        assert_eq!(seek_entries(&head), vec![(CUES, 0x400)]);
+    }
+
+    #[test]
+    fn reads_cues_before_the_clusters() {
+        let cues = el(CUES, &[cue(0, &[0x00]), cue(4, &[0x20])].concat());
+        let segment = [info(8000.0), cues, el(CLUSTER, &[0; 4])].concat();
+        let data = file(&segment);
+        let start = (data.len() - segment.len()) as u64;
+        let path = temp_file("front.mkv", &data);
+        let (duration, points) = read(&path).unwrap();
+        assert_eq!(duration, 8.0);
+        assert_eq!(points, vec![(0.0, start), (0.004, start + 0x20)]);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn reads_cues_through_the_seek_head() {
+        let head_len = el(SEEK_HEAD, &seek(CUES, 0)).len();
+        let front = [info(8000.0), el(CLUSTER, &[0; 16])].concat();
+        let cues_at = (head_len + front.len()) as u64;
+        let points = [cue(0, &[0x00]), cue(2, &[0xFF; 8])].concat();
+        let segment = [
+            el(SEEK_HEAD, &seek(CUES, cues_at)),
+            front,
+            el(CUES, &points),
+        ]
+        .concat();
+        let path = temp_file("back.mkv", &file(&segment));
+        // The cue whose position overflows is skipped
+        let (_, points) = read(&path).unwrap();
+        assert_eq!(points.len(), 1);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn oversized_elements_are_not_loaded() {
+        let mut segment = info(8000.0);
+        segment.extend_from_slice(&CUES.to_be_bytes());
+        segment.push(0x01);
+        segment.extend_from_slice(&(MAX_ELEMENT_SIZE + 1).to_be_bytes()[1..]);
+        let path = temp_file("oversized.mkv", &file(&segment));
+        assert!(read(&path).is_none());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let path = temp_file("not.mkv", &el(SEGMENT, &info(8000.0)));
+        assert!(Segment::read(&path, &[INFO]).is_none());
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,205 @@
+//! Time → byte-offset indexes for DLNA time-based seeking.
+//!
+//! Each container keeps its own table of seekable positions: MP4 sync
+//! samples (`stss` + `stco`/`co64`), Matroska `Cues`, and the Xing/VBRI TOC
+//! of VBR MP3s. The parsers turn those into a common `SeekIndex`, which the
+//! HTTP server uses to answer `TimeSeekRange.dlna.org` requests.
+
+pub(crate) mod mkv;
+pub(crate) mod mp3;
+pub(crate) mod mp4;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Seekable positions of one file, sorted by time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeekIndex {
+    /// Total play time in seconds.
+    pub duration: f64,
+    /// `(seconds, byte offset)` pairs; the first is always at time 0.
+    points: Vec<(f64, u64)>,
+}
+
+impl SeekIndex {
+    /// Builds an index, or None when there is nothing usable to seek with.
+    pub(crate) fn new(duration: f64, mut points: Vec<(f64, u64)>) -> Option<Self> {
+        points.retain(|(time, _)| time.is_finite() && *time >= 0.0);
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|later, earlier| later.0 == earlier.0);
+        if !duration.is_finite() || duration <= 0.0 || points.len() < 2 {
+            return None;
+        }
+        if points[0].0 > 0.0 {
+            let first_offset = points[0].1;
+            points.insert(0, (0.0, first_offset));
+        }
+        Some(Self { duration, points })
+    }
+
+    /// The last seek point at or before `time`, as `(seconds, byte offset)`.
+    pub fn seek_point(&self, time: f64) -> (f64, u64) {
+        let pos = self.points.partition_point(|(t, _)| *t <= time);
+        self.points[pos.saturating_sub(1)]
+    }
+
+    /// The first seek point after `time`, if any; playback up to `time`
+    /// ends before its byte offset.
+    pub fn next_point(&self, time: f64) -> Option<(f64, u64)> {
+        let pos = self.points.partition_point(|(t, _)| *t <= time);
+        self.points.get(pos).copied()
+    }
+}
+
+/// Reads the seek index of a file, picking the parser by extension.
+/// Returns None for other formats and for files without an index.
+pub fn read_index(path: &Path) -> Option<SeekIndex> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let (duration, points) = match ext.as_str() {
+        "mp4" | "m4v" | "m4a" | "mov" => mp4::read(path)?,
+        "mkv" | "webm" | "mka" => mkv::read(path)?,
+        "mp3" => mp3::read(path)?,
+        _ => return None,
+    };
+    SeekIndex::new(duration, points)
+}
+
+/// Parsed indexes keyed by file path. An entry stays valid while the file's
+/// size and mtime are unchanged; a file without an index is cached too, so
+/// it is not parsed again on every request.
+#[derive(Clone, Default)]
+pub struct SeekIndexes {
+    cache: Arc<Mutex<HashMap<String, CachedIndex>>>,
+}
+
+struct CachedIndex {
+    size: u64,
+    modified: u64,
+    index: Option<Arc<SeekIndex>>,
+}
+
+impl SeekIndexes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of the file at `path` with the given size and mtime. Parses the
+    /// file on a cache miss, so call it off the async runtime.
+    pub fn get(&self, path: &str, size: u64, modified: u64) -> Option<Arc<SeekIndex>> {
+        if let Some(cached) = self.cache.lock().unwrap().get(path) {
+            if cached.size == size && cached.modified == modified {
+                return cached.index.clone();
+            }
+        }
+
+        let index = read_index(Path::new(path)).map(Arc::new);
+        self.cache.lock().unwrap().insert(
+            path.to_string(),
+            CachedIndex {
+                size,
+                modified,
+                index: index.clone(),
+            },
+        );
+        index
+    }
+}
+
+/// Parses a DLNA normal play time: `ss.sss` or `h:mm:ss.sss`.
+pub fn parse_npt(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let seconds = match value.split(':').collect::<Vec<_>>().as_slice() {
+        [secs] => secs.parse::<f64>().ok()?,
+        [hours, minutes, secs] => {
+            hours.parse::<u64>().ok()? as f64 * 3600.0
+                + minutes.parse::<u64>().ok()? as f64 * 60.0
+                + secs.parse::<f64>().ok()?
+        }
+        _ => return None,
+    };
+    (seconds.is_finite() && seconds >= 0.0).then_some(seconds)
+}
+
+/// Formats seconds as an npt value in `h:mm:ss.sss` form.
+pub fn format_npt(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Writes `bytes` to a file of its own in the temp directory.
+    pub(crate) fn temp_file(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("rustcast-{}-{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn npt_in_seconds_and_clock_form() {
+        assert_eq!(parse_npt("12.5"), Some(12.5));
+        assert_eq!(parse_npt(" 0 "), Some(0.0));
+        assert_eq!(parse_npt("1:02:03.250"), Some(3723.25));
+        assert_eq!(parse_npt("0:00:07"), Some(7.0));
+    }
+
+    #[test]
+    fn malformed_npt_is_rejected() {
+        assert_eq!(parse_npt(""), None);
+        assert_eq!(parse_npt("-1"), None);
+        assert_eq!(parse_npt("1:30"), None);
+        assert_eq!(parse_npt("1:2:3:4"), None);
+        assert_eq!(parse_npt("-1:00:00"), None);
+        assert_eq!(parse_npt("inf"), None);
+        assert_eq!(parse_npt("NaN"), None);
+        assert_eq!(parse_npt("a:b:c"), None);
+    }
+
+    #[test]
+    fn npt_formatting() {
+        assert_eq!(format_npt(0.0), "0:00:00.000");
+        assert_eq!(format_npt(3723.25), "1:02:03.250");
+        assert_eq!(format_npt(-5.0), "0:00:00.000");
+        assert_eq!(parse_npt(&format_npt(59.9995)), Some(60.0));
+    }
+
+    #[test]
+    fn index_needs_a_duration_and_two_points() {
+        assert!(SeekIndex::new(10.0, vec![(0.0, 0)]).is_none());
+        assert!(SeekIndex::new(0.0, vec![(0.0, 0), (5.0, 500)]).is_none());
+        assert!(SeekIndex::new(f64::NAN, vec![(0.0, 0), (5.0, 500)]).is_none());
+        // Bad times are dropped before counting
+        assert!(SeekIndex::new(10.0, vec![(f64::NAN, 0), (-1.0, 10), (5.0, 500)]).is_none());
+    }
+
+    #[test]
+    fn index_is_sorted_and_starts_at_zero() {
+        let index = SeekIndex::new(10.0, vec![(6.0, 600), (2.0, 200), (2.0, 250)]).unwrap();
+        assert_eq!(index.points, vec![(0.0, 200), (2.0, 200), (6.0, 600)]);
+        assert_eq!(index.seek_point(1.0), (0.0, 200));
+        assert_eq!(index.seek_point(2.0), (2.0, 200));
+        assert_eq!(index.seek_point(100.0), (6.0, 600));
+        assert_eq!(index.next_point(2.0), Some((6.0, 600)));
+        assert_eq!(index.next_point(6.0), None);
+    }
+
+    #[test]
+    fn unknown_formats_have_no_index() {
+        let path = temp_file("index.avi", b"RIFF");
+        assert!(read_index(&path).is_none());
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,405 @@
+//! MP3: VBR files carry a seek table in their first frame, either a Xing
+//! (`Xing`/`Info`) header with a 100-entry TOC of byte percentages, or a
+//! Fraunhofer VBRI header with a table of per-segment byte counts.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes read from the start of the file; enough for an ID3v2 tag of
+/// typical size plus the first frame.
+const HEAD_SIZE: usize = 256 * 1024;
+
+/// Xing header flags.
+const XING_FRAMES: u32 = 0x1;
+const XING_BYTES: u32 = 0x2;
+const XING_TOC: u32 = 0x4;
+
+/// Offset of the VBRI header from the frame start; it is always here,
+/// whatever the channel mode.
+const VBRI_OFFSET: usize = 36;
+
+/// Layer III bitrates in kbit/s by bitrate index.
+const MPEG1_BITRATES: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+pub fn read(path: &Path) -> Option<(f64, Vec<(f64, u64)>)> {
+    let (head, file_size) = read_head(path)?;
+    let start = find_first_frame(&head)?;
+    let frame = FrameHeader::parse(head.get(start..start + 4)?)?;
+    let data = head.get(start..)?;
+
+    xing(data, &frame, start as u64, file_size).or_else(|| vbri(data, &frame, start as u64))
+}
+
+/// The first `HEAD_SIZE` bytes of the file, and the file size.
+pub(crate) fn read_head(path: &Path) -> Option<(Vec<u8>, u64)> {
+    let mut file = File::open(path).ok()?;
+    let file_size = file.metadata().ok()?.len();
+    let mut head = Vec::with_capacity(HEAD_SIZE);
+    file.by_ref()
+        .take(HEAD_SIZE as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some((head, file_size))
+}
+
+/// Offset of the first MPEG audio frame, after any ID3v2 tag.
+pub(crate) fn find_first_frame(head: &[u8]) -> Option<usize> {
+    let pos = id3v2_size(head).unwrap_or(0);
+    (pos..head.len().saturating_sub(4)).find(|&i| FrameHeader::parse(&head[i..i + 4]).is_some())
+}
+
+/// Total size of the ID3v2 tag at the start of `head`, if there is one.
+pub(crate) fn id3v2_size(head: &[u8]) -> Option<usize> {
+    if !head.starts_with(b"ID3") {
+        return None;
+    }
+    let size = syncsafe(head.get(6..10)?);
+    let footer = if head.get(5)? & 0x10 != 0 { 10 } else { 0 };
+    Some(10 + size + footer)
+}
+
+/// Iterates the frames of an ID3v2.3/2.4 tag as `(id, body)`, stopping at
+/// the padding or at the end of `tag`, which may be cut short.
+pub(crate) fn id3_frames(tag: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let version = tag.get(3).copied().filter(|v| (3..=4).contains(v));
+    let tag_end = id3v2_size(tag).unwrap_or(0).min(tag.len());
+    let mut pos = 10;
+    std::iter::from_fn(move || {
+        let version = version?;
+        if pos + 10 > tag_end || tag[pos] == 0 {
+            return None; // end of tag or padding
+        }
+        let id = &tag[pos..pos + 4];
+        let size_bytes = &tag[pos + 4..pos + 8];
+        let size = if version == 4 {
+            syncsafe(size_bytes)
+        } else {
+            u32::from_be_bytes(size_bytes.try_into().ok()?) as usize
+        };
+        let body = tag.get(pos + 10..(pos + 10).checked_add(size)?)?;
+        pos += 10 + size;
+        Some((id, body))
+    })
+}
+
+/// Decodes a syncsafe integer (7 bits per byte), as used by ID3v2.
+pub(crate) fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0usize, |size, &byte| (size << 7) | (byte & 0x7F) as usize)
+}
+
+pub(crate) struct FrameHeader {
+    pub mpeg1: bool,
+    pub mono: bool,
+    pub sample_rate: u32,
+    /// Kilobits per second; 0 for the "free" bitrate.
+    pub bitrate: u32,
+}
+
+impl FrameHeader {
+    /// Parses a Layer III frame header; other layers are not indexed.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let header = u32::from_be_bytes(bytes.try_into().ok()?);
+        if header & 0xFFE0_0000 != 0xFFE0_0000 {
+            return None;
+        }
+        let version = (header >> 19) & 0x3; // 0: 2.5, 2: 2, 3: 1
+        let layer = (header >> 17) & 0x3; // 1: Layer III
+        let bitrate = (header >> 12) & 0xF;
+        let rate_index = (header >> 10) & 0x3;
+        if version == 1 || layer != 1 || bitrate == 0xF || rate_index == 3 {
+            return None;
+        }
+        let base_rate = [44100, 48000, 32000][rate_index as usize];
+        let sample_rate = match version {
+            3 => base_rate,
+            2 => base_rate / 2,
+            _ => base_rate / 4,
+        };
+        let bitrates = if version == 3 {
+            &MPEG1_BITRATES
+        } else {
+            &MPEG2_BITRATES
+        };
+        Some(Self {
+            mpeg1: version == 3,
+            mono: (header >> 6) & 0x3 == 3,
+            sample_rate,
+            bitrate: bitrates[bitrate as usize],
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        if self.mpeg1 {
+            1152
+        } else {
+            576
+        }
+    }
+
+    /// Offset of the Xing header from the frame start: after the 4-byte
+    /// header and the side information.
+    fn xing_offset(&self) -> usize {
+        match (self.mpeg1, self.mono) {
+            (true, false) => 36,
+            (true, true) | (false, false) => 21,
+            (false, true) => 13,
+        }
+    }
+
+    pub fn duration(&self, frames: u32) -> f64 {
+        frames as f64 * self.samples_per_frame() as f64 / self.sample_rate as f64
+    }
+}
+
+/// Frame count from the Xing or VBRI header of the first frame, present in
+/// VBR files (and in CBR files written by LAME, as `Info`).
+pub(crate) fn vbr_frames(data: &[u8], frame: &FrameHeader) -> Option<u32> {
+    let pos = frame.xing_offset();
+    match data.get(pos..pos + 4)? {
+        b"Xing" | b"Info" if be_u32(data, pos + 4)? & XING_FRAMES != 0 => be_u32(data, pos + 8),
+        _ if data.get(VBRI_OFFSET..VBRI_OFFSET + 4)? == b"VBRI" => be_u32(data, VBRI_OFFSET + 14),
+        _ => None,
+    }
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Xing TOC entry `i` is the byte position, in 1/256ths of the stream, of
+/// `i` percent of the play time.
+fn xing(
+    data: &[u8],
+    frame: &FrameHeader,
+    start: u64,
+    file_size: u64,
+) -> Option<(f64, Vec<(f64, u64)>)> {
+    let pos = frame.xing_offset();
+    let tag = data.get(pos..pos + 4)?;
+    if tag != b"Xing" && tag != b"Info" {
+        return None;
+    }
+    let flags = be_u32(data, pos + 4)?;
+    if flags & XING_FRAMES == 0 || flags & XING_TOC == 0 {
+        return None;
+    }
+
+    let mut field = pos + 8;
+    let frames = be_u32(data, field)?;
+    field += 4;
+    let bytes = if flags & XING_BYTES != 0 {
+        let bytes = be_u32(data, field)? as u64;
+        field += 4;
+        bytes
+    } else {
+        file_size - start
+    };
+    let toc = data.get(field..field + 100)?;
+
+    let duration = frame.duration(frames);
+    let points = toc
+        .iter()
+        .enumerate()
+        .map(|(percent, &entry)| {
+            (
+                duration * percent as f64 / 100.0,
+                start + bytes * entry as u64 / 256,
+            )
+        })
+        .collect();
+    Some((duration, points))
+}
+
+/// VBRI lists the byte size of each of `entries` equal-length segments.
+fn vbri(data: &[u8], frame: &FrameHeader, start: u64) -> Option<(f64, Vec<(f64, u64)>)> {
+    if data.get(VBRI_OFFSET..VBRI_OFFSET + 4)? != b"VBRI" {
+        return None;
+    }
+    let frames = be_u32(data, VBRI_OFFSET + 14)?;
+    let entries = be_u16(data, VBRI_OFFSET + 18)? as usize;
+    let scale = be_u16(data, VBRI_OFFSET + 20)? as u64;
+    let entry_size = be_u16(data, VBRI_OFFSET + 22)? as usize;
+    if entries == 0 || !(1..=4).contains(&entry_size) {
+        return None;
+    }
+    let table = data.get(VBRI_OFFSET + 26..VBRI_OFFSET + 26 + entries * entry_size)?;
+
+    let duration = frame.duration(frames);
+    let mut offset = start;
+    let mut points = vec![(0.0, start)];
+    for (i, entry) in table.chunks_exact(entry_size).enumerate() {
+        let size = entry.iter().fold(0u64, |v, &b| (v << 8) | b as u64);
+        offset += size * scale;
+        points.push((duration * (i + 1) as f64 / entries as f64, offset));
+    }
+    points.pop(); // the last entry ends at the end of the stream
+    Some((duration, points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::seek::tests::temp_file;
+
+    /// MPEG-1 Layer III, 128 kbit/s, 44.1 kHz, stereo.
+    const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+
+    fn frame_with(offset: usize, tag: &[u8]) -> Vec<u8> {
+        let mut data = HEADER.to_vec();
+        data.resize(offset, 0);
+        data.extend_from_slice(tag);
+        data.resize(data.len().max(417), 0);
+        data
+    }
+
+    fn xing_frame(toc_len: usize) -> Vec<u8> {
+        let mut tag = b"Xing".to_vec();
+        tag.extend_from_slice(&(XING_FRAMES | XING_BYTES | XING_TOC).to_be_bytes());
+        tag.extend_from_slice(&1000u32.to_be_bytes());
+        tag.extend_from_slice(&25600u32.to_be_bytes());
+        tag.extend((0..toc_len).map(|i| (i * 2) as u8));
+        let mut data = HEADER.to_vec();
+        data.resize(36, 0);
+        data.extend_from_slice(&tag);
+        data
+    }
+
+    fn vbri_frame(entry_size: u16, table: &[u8]) -> Vec<u8> {
+        let mut tag = b"VBRI".to_vec();
+        tag.extend_from_slice(&[0; 10]);
+        tag.extend_from_slice(&1000u32.to_be_bytes());
+        tag.extend_from_slice(&4u16.to_be_bytes());
+        tag.extend_from_slice(&2u16.to_be_bytes());
+        tag.extend_from_slice(&entry_size.to_be_bytes());
+        tag.extend_from_slice(&[0; 2]);
+        tag.extend_from_slice(table);
+        frame_with(VBRI_OFFSET, &tag)
+    }
+
+    fn id3_tag(frames: &[u8], padding: usize) -> Vec<u8> {
+        let size = frames.len() + padding;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend((0..4).rev().map(|i| (size >> (7 * i)) as u8 & 0x7F));
+        tag.extend_from_slice(frames);
+        tag.resize(10 + size, 0);
+        tag
+    }
+
+    #[test]
+    fn frame_headers() {
+        let frame = FrameHeader::parse(&HEADER).unwrap();
+        assert!(frame.mpeg1 && !frame.mono);
+        assert_eq!((frame.sample_rate, frame.bitrate), (44100, 128));
+        assert_eq!(frame.xing_offset(), 36);
+        assert_eq!(frame.duration(44100), 1152.0);
+
+        // MPEG-2, mono
+        let frame = FrameHeader::parse(&[0xFF, 0xF3, 0x90, 0xC0]).unwrap();
+        assert!(!frame.mpeg1 && frame.mono);
+        assert_eq!((frame.sample_rate, frame.bitrate), (22050, 80));
+        assert_eq!(frame.xing_offset(), 13);
+
+        // Layer II, bad bitrate, reserved sample rate, no sync
+        assert!(FrameHeader::parse(&[0xFF, 0xFD, 0x90, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0x9C, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0x49, 0x44, 0x33, 0x03]).is_none());
+        assert!(FrameHeader::parse(&HEADER[..3]).is_none());
+    }
+
+    #[test]
+    fn xing_toc_maps_percentages_to_bytes() {
+        let data = xing_frame(100);
+        let frame = FrameHeader::parse(&HEADER).unwrap();
+        assert_eq!(vbr_frames(&data, &frame), Some(1000));
+        let (duration, points) = xing(&data, &frame, 30, 100_000).unwrap();
+        assert_eq!(duration, frame.duration(1000));
+        assert_eq!(points.len(), 100);
+        assert_eq!(points[0], (0.0, 30));
+        assert_eq!(points[50], (duration / 2.0, 30 + 10_000));
+    }
+
+    #[test]
+    fn truncated_xing_toc_is_ignored() {
+        let frame = FrameHeader::parse(&HEADER).unwrap();
+        assert!(xing(&xing_frame(99), &frame, 0, 100_000).is_none());
+        // Without the TOC flag there is nothing to seek with
+        let mut data = xing_frame(100);
+        data[43] = (XING_FRAMES | XING_BYTES) as u8;
+        assert!(xing(&data, &frame, 0, 100_000).is_none());
+    }
+
+    #[test]
+    fn vbri_table_sums_segment_sizes() {
+        let frame = FrameHeader::parse(&HEADER).unwrap();
+        let data = vbri_frame(2, &[0, 100, 0, 200, 1, 44, 1, 144]);
+        assert_eq!(vbr_frames(&data, &frame), Some(1000));
+        let (duration, points) = vbri(&data, &frame, 10).unwrap();
+        assert_eq!(
+            points,
+            vec![
+                (0.0, 10),
+                (duration / 4.0, 210),
+                (duration / 2.0, 610),
+                (duration * 3.0 / 4.0, 1210)
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_vbri_tables_are_rejected() {
+        let frame = FrameHeader::parse(&HEADER).unwrap();
+        assert!(vbri(&vbri_frame(5, &[0; 20]), &frame, 0).is_none());
+        assert!(vbri(&vbri_frame(0, &[]), &frame, 0).is_none());
+        let mut short = vbri_frame(2, &[0, 100, 0, 200]);
+        short.truncate(VBRI_OFFSET + 26 + 4);
+        assert!(vbri(&short, &frame, 0).is_none());
+    }
+
+    #[test]
+    fn id3_tags_are_skipped_and_read() {
+        let mut frames = b"TIT2\x00\x00\x00\x03\x00\x00\x03Hi".to_vec();
+        frames.extend_from_slice(b"TPE1\x00\x00\x00\x02\x00\x00\x03A");
+        let tag = id3_tag(&frames, 20);
+        assert_eq!(id3v2_size(&tag), Some(tag.len()));
+        assert_eq!(
+            id3_frames(&tag).collect::<Vec<_>>(),
+            vec![
+                (&b"TIT2"[..], &b"\x03Hi"[..]),
+                (&b"TPE1"[..], &b"\x03A"[..])
+            ]
+        );
+        // Cut short inside the second frame
+        assert_eq!(id3_frames(&tag[..34]).count(), 1);
+        assert_eq!(syncsafe(&[0x00, 0x00, 0x02, 0x01]), 257);
+        assert_eq!(id3v2_size(b"RIFF"), None);
+
+        let mut head = tag.clone();
+        head.extend_from_slice(&HEADER);
+        head.extend_from_slice(&[0; 8]);
+        assert_eq!(find_first_frame(&head), Some(tag.len()));
+    }
+
+    #[test]
+    fn reads_the_index_after_an_id3_tag() {
+        let tag = id3_tag(&[], 20);
+        let mut data = tag.clone();
+        data.extend_from_slice(&xing_frame(100));
+        data.resize(30_000, 0);
+        let path = temp_file("index.mp3", &data);
+        let (duration, points) = read(&path).unwrap();
+        assert!((duration - 26.122).abs() < 0.001);
+        assert_eq!(points[1], (duration / 100.0, 30 + 200));
+        std::fs::remove_file(path).ok();
+    }
+}
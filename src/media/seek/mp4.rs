@@ -0,0 +1,446 @@
+//! MP4/QuickTime: seek points are the sync samples (`stss`) of the video
+//! track, located through the sample-to-chunk (`stsc`), sample size (`stsz`)
+//! and chunk offset (`stco`/`co64`) tables. Audio-only files have no `stss`;
+//! every sample is a sync sample there, so they are thinned out.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest `moov` box we are willing to load into memory.
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Minimum spacing between seek points taken from a track without `stss`.
+const MIN_POINT_SPACING: f64 = 1.0;
+
+/// Most samples a track may have to be indexed: ten hours at 60 frames per
+/// second fit several times over. Larger tables are taken as corrupt.
+const MAX_SAMPLES: usize = 8 * 1024 * 1024;
+
+pub fn read(path: &Path) -> Option<(f64, Vec<(f64, u64)>)> {
+    let mut file = File::open(path).ok()?;
+    let moov = read_top_level_box(&mut file, b"moov")?;
+
+    let mvhd = find_box(&moov, b"mvhd")?;
+    let (movie_timescale, movie_duration) = timescale_and_duration(mvhd)?;
+
+    let tracks: Vec<&[u8]> = boxes(&moov)
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, body)| body)
+        .collect();
+    let track = ["vide", "soun"]
+        .iter()
+        .find_map(|handler| tracks.iter().find(|t| handler_type(t) == Some(handler)))?;
+    let table = SampleTable::parse(track)?;
+
+    let duration = if movie_timescale > 0 && movie_duration > 0 {
+        movie_duration as f64 / movie_timescale as f64
+    } else {
+        table.total_ticks() as f64 / table.timescale as f64
+    };
+    Some((duration, table.seek_points()))
+}
+
+/// Walks the top-level boxes of the file and loads the body of `kind`.
+pub(crate) fn read_top_level_box(file: &mut File, kind: &[u8; 4]) -> Option<Vec<u8>> {
+    let file_size = file.metadata().ok()?.len();
+    let mut pos = 0u64;
+    while pos + 8 <= file_size {
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).ok()?;
+        let mut size = u32::from_be_bytes(header[0..4].try_into().ok()?) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large).ok()?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = file_size - pos;
+        }
+        if size < header_len {
+            return None;
+        }
+
+        if &header[4..8] == kind {
+            let body_len = size - header_len;
+            if body_len > MAX_MOOV_SIZE {
+                return None;
+            }
+            let mut body = vec![0u8; body_len as usize];
+            file.read_exact(&mut body).ok()?;
+            return Some(body);
+        }
+        pos = pos.checked_add(size)?;
+    }
+    None
+}
+
+/// Iterates the child boxes in `data` as `(type, body)`.
+pub(crate) fn boxes(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let header = data.get(pos..pos + 8)?;
+        let kind: [u8; 4] = header[4..8].try_into().ok()?;
+        let (size, header_len) = match u32::from_be_bytes(header[0..4].try_into().ok()?) {
+            0 => (data.len() - pos, 8),
+            1 => (usize::try_from(be_u64(data, pos + 8)?).ok()?, 16),
+            size => (size as usize, 8),
+        };
+        let body = data.get(pos + header_len..pos.checked_add(size)?)?;
+        pos += size;
+        Some((kind, body))
+    })
+}
+
+pub(crate) fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data).find(|(k, _)| k == kind).map(|(_, body)| body)
+}
+
+/// Follows a path of nested boxes, e.g. `["mdia", "minf", "stbl"]`.
+pub(crate) fn find_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter()
+        .try_fold(data, |data, kind| find_box(data, kind))
+}
+
+/// Timescale and duration from an `mvhd` or `mdhd` body (version 0 or 1).
+pub(crate) fn timescale_and_duration(body: &[u8]) -> Option<(u32, u64)> {
+    match body.first()? {
+        0 => Some((be_u32(body, 12)?, be_u32(body, 16)? as u64)),
+        1 => Some((be_u32(body, 20)?, be_u64(body, 24)?)),
+        _ => None,
+    }
+}
+
+pub(crate) fn handler_type(track: &[u8]) -> Option<&str> {
+    let hdlr = find_path(track, &[b"mdia", b"hdlr"])?;
+    std::str::from_utf8(hdlr.get(8..12)?).ok()
+}
+
+pub(crate) fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+/// Reads the `count` field at offset 4 of a full box and the `count`
+/// fixed-size entries that follow it.
+fn entries(body: &[u8], first: usize, entry_size: usize) -> Option<impl Iterator<Item = &[u8]>> {
+    let count = be_u32(body, first - 4)? as usize;
+    let table = body.get(first..first.checked_add(count.checked_mul(entry_size)?)?)?;
+    Some(table.chunks_exact(entry_size))
+}
+
+struct SampleTable {
+    timescale: u32,
+    /// `stts`: (sample count, duration in ticks).
+    deltas: Vec<(u32, u32)>,
+    /// `stss`: 1-based sample numbers; None when every sample is a sync sample.
+    sync_samples: Option<Vec<u32>>,
+    /// `stsc`: (first chunk, 1-based; samples per chunk).
+    chunk_runs: Vec<(u32, u32)>,
+    /// `stsz`: uniform size, or 0 when `sizes` holds one size per sample.
+    sample_size: u32,
+    sizes: Vec<u32>,
+    /// Samples described by every table, at most MAX_SAMPLES.
+    sample_count: usize,
+    chunk_offsets: Vec<u64>,
+}
+
+impl SampleTable {
+    fn parse(track: &[u8]) -> Option<Self> {
+        let mdhd = find_path(track, &[b"mdia", b"mdhd"])?;
+        let (timescale, _) = timescale_and_duration(mdhd)?;
+        if timescale == 0 {
+            return None;
+        }
+        let stbl = find_path(track, &[b"mdia", b"minf", b"stbl"])?;
+        let u32_at = |entry: &[u8], pos| be_u32(entry, pos).unwrap_or(0);
+
+        let deltas: Vec<(u32, u32)> = entries(find_box(stbl, b"stts")?, 8, 8)?
+            .map(|e| (u32_at(e, 0), u32_at(e, 4)))
+            .collect();
+        let sync_samples = match find_box(stbl, b"stss") {
+            Some(stss) => Some(entries(stss, 8, 4)?.map(|e| u32_at(e, 0)).collect()),
+            None => None,
+        };
+        let chunk_runs = entries(find_box(stbl, b"stsc")?, 8, 12)?
+            .map(|e| (u32_at(e, 0), u32_at(e, 4)))
+            .collect();
+
+        let stsz = find_box(stbl, b"stsz")?;
+        let sample_size = be_u32(stsz, 4)?;
+        let sizes: Vec<u32> = if sample_size == 0 {
+            entries(stsz, 12, 4)?.map(|e| u32_at(e, 0)).collect()
+        } else {
+            Vec::new()
+        };
+        // The declared count is only trusted as far as the size and time
+        // tables actually cover it.
+        let timed: u64 = deltas.iter().map(|&(count, _)| count as u64).sum();
+        let sample_count = (be_u32(stsz, 8)? as u64).min(timed);
+        let sample_count = if sample_size == 0 {
+            sample_count.min(sizes.len() as u64)
+        } else {
+            sample_count
+        };
+        let sample_count = usize::try_from(sample_count)
+            .ok()
+            .filter(|&count| count <= MAX_SAMPLES)?;
+
+        let chunk_offsets = if let Some(stco) = find_box(stbl, b"stco") {
+            entries(stco, 8, 4)?.map(|e| u32_at(e, 0) as u64).collect()
+        } else {
+            entries(find_box(stbl, b"co64")?, 8, 8)?
+                .map(|e| be_u64(e, 0).unwrap_or(0))
+                .collect()
+        };
+
+        Some(Self {
+            timescale,
+            deltas,
+            sync_samples,
+            chunk_runs,
+            sample_size,
+            sizes,
+            sample_count,
+            chunk_offsets,
+        })
+    }
+
+    fn total_ticks(&self) -> u64 {
+        self.deltas
+            .iter()
+            .map(|&(count, delta)| count as u64 * delta as u64)
+            .sum()
+    }
+
+    fn size_of(&self, sample: usize) -> u64 {
+        if self.sample_size != 0 {
+            self.sample_size as u64
+        } else {
+            self.sizes.get(sample).copied().unwrap_or(0) as u64
+        }
+    }
+
+    /// Byte offset of every sample, walking chunks in order.
+    fn sample_offsets(&self) -> Vec<u64> {
+        let total = self.sample_count;
+        let mut offsets = Vec::with_capacity(total);
+        let mut run = 0;
+        for (chunk, &chunk_offset) in self.chunk_offsets.iter().enumerate() {
+            let chunk_number = chunk as u32 + 1;
+            while run + 1 < self.chunk_runs.len() && self.chunk_runs[run + 1].0 <= chunk_number {
+                run += 1;
+            }
+            let Some(&(_, per_chunk)) = self.chunk_runs.get(run) else {
+                break;
+            };
+            let mut offset = chunk_offset;
+            for _ in 0..per_chunk {
+                if offsets.len() == total {
+                    return offsets;
+                }
+                offsets.push(offset);
+                offset = offset.saturating_add(self.size_of(offsets.len() - 1));
+            }
+        }
+        offsets
+    }
+
+    /// Decode time in ticks of every sample.
+    fn sample_times(&self) -> Vec<u64> {
+        let mut times = Vec::with_capacity(self.sample_count);
+        let mut tick = 0u64;
+        for &(count, delta) in &self.deltas {
+            for _ in 0..count {
+                if times.len() == self.sample_count {
+                    return times;
+                }
+                times.push(tick);
+                tick += delta as u64;
+            }
+        }
+        times
+    }
+
+    fn seek_points(&self) -> Vec<(f64, u64)> {
+        let offsets = self.sample_offsets();
+        let times = self.sample_times();
+        let point = |sample: usize| {
+            let time = *times.get(sample)? as f64 / self.timescale as f64;
+            Some((time, *offsets.get(sample)?))
+        };
+
+        match &self.sync_samples {
+            Some(sync) => sync
+                .iter()
+                .filter_map(|&number| point(number.checked_sub(1)? as usize))
+                .collect(),
+            None => {
+                let mut points: Vec<(f64, u64)> = Vec::new();
+                for sample in 0..offsets.len().min(times.len()) {
+                    let Some((time, offset)) = point(sample) else {
+                        break;
+                    };
+                    if points
+                        .last()
+                        .is_none_or(|(last, _)| time - last >= MIN_POINT_SPACING)
+                    {
+                        points.push((time, offset));
+                    }
+                }
+                points
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::seek::tests::temp_file;
+
+    fn bx(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    /// A full box body: version and flags, then the given words.
+    fn words(values: &[u32]) -> Vec<u8> {
+        let mut body = vec![0; 4];
+        for value in values {
+            body.extend_from_slice(&value.to_be_bytes());
+        }
+        body
+    }
+
+    fn track(handler: &[u8; 4], stbl: &[Vec<u8>]) -> Vec<u8> {
+        let mdhd = words(&[0, 0, 1000, 3000]);
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(handler);
+        let minf = bx(b"minf", &bx(b"stbl", &stbl.concat()));
+        let mdia = [bx(b"mdhd", &mdhd), bx(b"hdlr", &hdlr), minf].concat();
+        bx(b"mdia", &mdia)
+    }
+
+    /// Six 0.5 s samples of 100 bytes, three per chunk, in chunks at 1000
+    /// and 2000; samples 1 and 4 are sync samples.
+    fn video_tables() -> Vec<Vec<u8>> {
+        vec![
+            bx(b"stts", &words(&[1, 6, 500])),
+            bx(b"stss", &words(&[2, 1, 4])),
+            bx(b"stsc", &words(&[1, 1, 3, 1])),
+            bx(b"stsz", &words(&[100, 6])),
+            bx(b"stco", &words(&[2, 1000, 2000])),
+        ]
+    }
+
+    #[test]
+    fn sync_samples_become_seek_points() {
+        let table = SampleTable::parse(&track(b"vide", &video_tables())).unwrap();
+        assert_eq!(
+            table.sample_offsets(),
+            vec![1000, 1100, 1200, 2000, 2100, 2200]
+        );
+        assert_eq!(table.seek_points(), vec![(0.0, 1000), (1.5, 2000)]);
+        assert_eq!(table.total_ticks(), 3000);
+    }
+
+    #[test]
+    fn tracks_without_stss_are_thinned_out() {
+        let mut tables = video_tables();
+        tables.remove(1);
+        // Variable sizes, 64-bit chunk offsets
+        tables[2] = bx(b"stsz", &words(&[0, 6, 10, 20, 30, 40, 50, 60]));
+        tables[3] = bx(b"co64", &words(&[2, 0, 1000, 1, 0]));
+        let table = SampleTable::parse(&track(b"soun", &tables)).unwrap();
+        assert_eq!(
+            table.sample_offsets(),
+            vec![1000, 1010, 1030, 1 << 32, (1 << 32) + 40, (1 << 32) + 90]
+        );
+        assert_eq!(
+            table.seek_points(),
+            vec![(0.0, 1000), (1.0, 1030), (2.0, (1 << 32) + 40)]
+        );
+    }
+
+    #[test]
+    fn tables_shorter_than_their_count_are_rejected() {
+        let mut tables = video_tables();
+        tables[0] = bx(b"stts", &words(&[1000, 6, 500]));
+        assert!(SampleTable::parse(&track(b"vide", &tables)).is_none());
+
+        let mut tables = video_tables();
+        tables[4] = bx(b"stco", &words(&[u32::MAX, 1000]));
+        assert!(SampleTable::parse(&track(b"vide", &tables)).is_none());
+    }
+
+    #[test]
+    fn sample_count_is_capped_by_the_tables() {
+        // stsz claims far more samples than stts times
+        let mut tables = video_tables();
+        tables[3] = bx(b"stsz", &words(&[100, u32::MAX]));
+        let table = SampleTable::parse(&track(b"vide", &tables)).unwrap();
+        assert_eq!(table.sample_count, 6);
+
+        // Both claim more than any real file has
+        tables[0] = bx(b"stts", &words(&[1, u32::MAX, 1]));
+        assert!(SampleTable::parse(&track(b"vide", &tables)).is_none());
+    }
+
+    #[test]
+    fn box_sizes_must_fit_the_data() {
+        let mut data = bx(b"free", b"abcd");
+        data.extend_from_slice(&bx(b"moov", b"xyz"));
+        assert_eq!(boxes(&data).count(), 2);
+
+        // Declared larger than what is left
+        let mut truncated = bx(b"moov", b"xyz");
+        truncated[3] = 200;
+        assert_eq!(boxes(&truncated).count(), 0);
+        // Smaller than its own header
+        let mut tiny = bx(b"moov", b"xyz");
+        tiny[3] = 4;
+        assert!(find_box(&tiny, b"moov").is_none());
+        // 64-bit size past the end
+        let mut large = vec![0, 0, 0, 1];
+        large.extend_from_slice(b"moov");
+        large.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(boxes(&large).count(), 0);
+        // Size 0 runs to the end
+        let mut open = bx(b"mdat", b"12345");
+        open[..4].copy_from_slice(&[0; 4]);
+        assert_eq!(find_box(&open, b"mdat"), Some(&b"12345"[..]));
+    }
+
+    #[test]
+    fn reads_the_index_of_a_file() {
+        let mvhd = words(&[0, 0, 600, 1800]);
+        let moov = [
+            bx(b"mvhd", &mvhd),
+            bx(b"trak", &track(b"vide", &video_tables())),
+        ]
+        .concat();
+        let file = [bx(b"ftyp", b"isom"), bx(b"moov", &moov)].concat();
+        let path = temp_file("index.mp4", &file);
+        assert_eq!(read(&path), Some((3.0, vec![(0.0, 1000), (1.5, 2000)])));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn oversized_moov_is_not_loaded() {
+        let mut file = bx(b"ftyp", b"isom");
+        file.extend_from_slice(&[0, 0, 0, 1]);
+        file.extend_from_slice(b"moov");
+        file.extend_from_slice(&(MAX_MOOV_SIZE + 17).to_be_bytes());
+        let path = temp_file("oversized.mp4", &file);
+        assert!(read(&path).is_none());
+        std::fs::remove_file(path).ok();
+    }
+}
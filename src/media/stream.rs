@@ -1,11 +1,28 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::{header, Url};
+use tokio::time::timeout;
 
 use crate::config::Config;
 use crate::dlna::{av_transport, connection_manager, metadata};
-use crate::media::manager::{get_mime_type, MediaFile};
+use crate::media::art::{has_embedded, ArtCache};
+use crate::media::finder::Subtitle;
+use crate::media::manager::MediaFile;
+use crate::server::relay::Relays;
 use crate::soap::SoapClient;
 
+/// How long a remote URL gets to answer the HEAD request made before it
+/// is cast.
+const REMOTE_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// MIME type cast when neither the origin nor the URL's extension tells;
+/// the one renderers are most likely to accept.
+const REMOTE_FALLBACK_MIME: &str = "video/mp4";
+
 /// Configures and starts playback of a media file on the DLNA renderer.
+/// `subtitles` are offered in that order; renderers that take a single
+/// track show the first.
 ///
 /// Flow: PrepareForConnection (optional) → SetAVTransportURI → Play
 pub async fn stream_media(
@@ -14,26 +31,103 @@ pub async fn stream_media(
     av_control_url: &str,
     cm_control_url: &str,
     media_file: &MediaFile,
-    subtitle_url: Option<&str>,
+    subtitles: &[Subtitle],
+    art: &ArtCache,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !Path::new(&media_file.path).exists() {
         return Err(format!("File '{}' not found.", media_file.path).into());
     }
 
     // URL the renderer uses to fetch the media over HTTP
-    let media_url = format!(
-        "http://{}:{}/media/{}",
-        config.http_address, config.http_port, media_file.relative_path
-    );
+    let media_url = config.media_url(&media_file.relative_path);
 
     // PrepareForConnection is optional — silently ignore unsupported devices
     let _ = connection_manager::prepare_connection(client, cm_control_url).await;
 
-    let mime_type = get_mime_type(&media_file.path);
-    let metadata = metadata::build(&media_file.name, &media_url, mime_type, subtitle_url);
+    // Advertise time-based seek as Browse does, from the probe, and cover
+    // art when the `/art` endpoint has some.
+    let time_seek = media_file.info.as_ref().is_some_and(|info| info.time_seek);
+    let path = PathBuf::from(&media_file.path);
+    let probed = media_file.info.as_ref().map(|info| info.embedded_art);
+    let art = art.clone();
+    let has_art = tokio::task::spawn_blocking(move || {
+        let embedded = probed.unwrap_or_else(|| has_embedded(&path));
+        art.file_source(&path, embedded).is_some()
+    })
+    .await
+    .unwrap_or(false);
+    let art_url = has_art.then(|| config.art_url(&media_file.relative_path));
+    let metadata = metadata::build(
+        media_file,
+        &media_url,
+        subtitles,
+        time_seek,
+        art_url.as_deref(),
+    );
 
     av_transport::set_uri(client, av_control_url, &media_url, &metadata).await?;
     av_transport::play(client, av_control_url).await?;
 
     Ok(())
 }
+
+/// Casts a remote HTTP resource, relayed by the `/proxy` endpoint so the
+/// renderer gets the DLNA headers it expects. The origin is asked for its
+/// type with a HEAD request first; when it does not answer that (live feeds
+/// often do not), the type comes from the URL's extension. Returns the MIME
+/// type cast.
+pub async fn stream_url(
+    client: &SoapClient,
+    config: &Config,
+    relays: &Relays,
+    av_control_url: &str,
+    cm_control_url: &str,
+    url: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let parsed = Url::parse(url).map_err(|e| format!("'{}' is not a valid URL: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("only http:// and https:// URLs can be cast".into());
+    }
+
+    let head = timeout(REMOTE_PROBE_TIMEOUT, client.head(url).send())
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .filter(|response| response.status().is_success());
+    let header_value = |name: header::HeaderName| {
+        head.as_ref()
+            .and_then(|response| response.headers().get(name)?.to_str().ok())
+            .map(str::to_owned)
+    };
+    let mime_type = header_value(header::CONTENT_TYPE)
+        .and_then(|value| {
+            value
+                .split(';')
+                .next()
+                .map(|mime| mime.trim().to_lowercase())
+        })
+        .filter(|mime| mime.contains('/'))
+        .or_else(|| {
+            config
+                .formats
+                .format_of(parsed.path())
+                .map(|format| format.mime_type.clone())
+        })
+        .unwrap_or_else(|| REMOTE_FALLBACK_MIME.to_string());
+    let byte_seek = header_value(header::ACCEPT_RANGES).as_deref() == Some("bytes");
+
+    let proxy_url = config.proxy_url(relays.register(url, &mime_type));
+    let title = parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .or(parsed.host_str())
+        .unwrap_or(url);
+    let metadata = metadata::build_remote(title, &proxy_url, &mime_type, byte_seek);
+
+    let _ = connection_manager::prepare_connection(client, cm_control_url).await;
+    av_transport::set_uri(client, av_control_url, &proxy_url, &metadata).await?;
+    av_transport::play(client, av_control_url).await?;
+
+    Ok(mime_type)
+}
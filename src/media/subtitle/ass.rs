@@ -0,0 +1,142 @@
+//! Advanced SubStation Alpha (and SSA): `Dialogue:` lines in the `[Events]`
+//! section, laid out by its `Format:` line, with `{\...}` override blocks.
+
+use super::{parse_timestamp, Cue};
+
+/// Header of written files: one plain style, sized for a 1080p script.
+const HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1920
+PlayResY: 1080
+WrapStyle: 0
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,64,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,3,1,2,60,60,50,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+/// Field layout used when a file has no `Format:` line in `[Events]`.
+const DEFAULT_FIELDS: &[&str] = &[
+    "layer", "start", "end", "style", "name", "marginl", "marginr", "marginv", "effect", "text",
+];
+
+pub fn parse(text: &str) -> Vec<Cue> {
+    let mut in_events = false;
+    let mut fields: Vec<String> = DEFAULT_FIELDS.iter().map(|f| f.to_string()).collect();
+    let mut cues = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key.eq_ignore_ascii_case("format") {
+            fields = value.split(',').map(|f| f.trim().to_lowercase()).collect();
+        } else if key.eq_ignore_ascii_case("dialogue") {
+            // Text is the last field and may itself contain commas
+            let values: Vec<&str> = value.trim_start().splitn(fields.len(), ',').collect();
+            let field = |name: &str| {
+                let index = fields.iter().position(|f| f == name)?;
+                values.get(index).copied()
+            };
+            let (Some(start), Some(end), Some(body)) = (
+                field("start").and_then(parse_timestamp),
+                field("end").and_then(parse_timestamp),
+                field("text"),
+            ) else {
+                continue;
+            };
+            cues.push(Cue {
+                start_ms: start,
+                end_ms: end,
+                text: to_tags(body),
+            });
+        }
+    }
+    cues
+}
+
+pub fn write(cues: &[Cue]) -> String {
+    let mut out = String::from(HEADER);
+    for cue in cues {
+        out.push_str(&format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            timestamp(cue.start_ms),
+            timestamp(cue.end_ms),
+            from_tags(&cue.text)
+        ));
+    }
+    out
+}
+
+/// Converts ASS text to cue text: `\N` breaks lines, `\i1`, `\b1` and `\u1`
+/// overrides become tags, and every other override is dropped.
+fn to_tags(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut open: Vec<char> = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        for code in rest[start + 1..start + end].split('\\') {
+            let mut chars = code.chars();
+            let (Some(tag @ ('i' | 'b' | 'u')), value) = (chars.next(), chars.as_str()) else {
+                continue;
+            };
+            match value {
+                "1" if !open.contains(&tag) => {
+                    out.push_str(&format!("<{}>", tag));
+                    open.push(tag);
+                }
+                "0" | "" if open.contains(&tag) => {
+                    out.push_str(&format!("</{}>", tag));
+                    open.retain(|t| *t != tag);
+                }
+                _ => {}
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    for tag in open.iter().rev() {
+        out.push_str(&format!("</{}>", tag));
+    }
+    out.replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ")
+}
+
+/// Converts cue text to ASS: tags become overrides and lines join with `\N`.
+fn from_tags(text: &str) -> String {
+    let mut out = text.replace('{', "(").replace('}', ")");
+    for tag in ["i", "b", "u"] {
+        out = out
+            .replace(&format!("<{}>", tag), &format!("{{\\{}1}}", tag))
+            .replace(&format!("</{}>", tag), &format!("{{\\{}0}}", tag));
+    }
+    out.replace('\n', "\\N")
+}
+
+/// `h:mm:ss.cc`, the centisecond timestamps ASS uses.
+fn timestamp(ms: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000 / 10
+    )
+}
@@ -0,0 +1,415 @@
+//! Subtitle decoding and conversion for the `/subtitle` endpoint.
+//!
+//! Sidecar subtitles come in whatever charset they were saved in, often
+//! Windows-1252 or a legacy Asian code page, and renderers show mojibake for
+//! anything but UTF-8. Files are decoded (BOM, then UTF-8, then a statistical
+//! guess) and served as UTF-8. When a renderer does not take the file's
+//! format, the cues are parsed and written out in one it does; styling that
+//! the target cannot express is dropped, but italics, bold and underline
+//! carry over.
+
+mod ass;
+mod offsets;
+mod smi;
+mod srt;
+mod vtt;
+
+use crate::media::text::decode;
+
+pub use offsets::SubtitleOffsets;
+
+/// Subtitle formats RustCast can read and write, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+    Smi,
+}
+
+impl SubtitleFormat {
+    pub const ALL: [SubtitleFormat; 4] = [
+        SubtitleFormat::Srt,
+        SubtitleFormat::Vtt,
+        SubtitleFormat::Ass,
+        SubtitleFormat::Smi,
+    ];
+
+    /// Format of a file with the given extension; SSA is read as ASS.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            "smi" | "sami" => Some(SubtitleFormat::Smi),
+            _ => None,
+        }
+    }
+
+    /// Name used in file extensions, `?format=` and `sec:CaptionInfoEx`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+            SubtitleFormat::Smi => "smi",
+        }
+    }
+
+    /// MIME type in DIDL and in responses. SRT is `text/srt`, which is what
+    /// Samsung and LG renderers look for.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "text/srt",
+            SubtitleFormat::Vtt => "text/vtt",
+            SubtitleFormat::Ass => "text/x-ssa",
+            SubtitleFormat::Smi => "smi/caption",
+        }
+    }
+
+    /// MIME types renderers use for this format in their protocolInfo.
+    fn mime_aliases(self) -> &'static [&'static str] {
+        match self {
+            SubtitleFormat::Srt => &["text/srt", "application/x-subrip", "text/x-subrip"],
+            SubtitleFormat::Vtt => &["text/vtt"],
+            SubtitleFormat::Ass => &["text/x-ssa", "text/x-ass"],
+            SubtitleFormat::Smi => &["smi/caption", "application/smil", "text/smi"],
+        }
+    }
+
+    /// Subtitle formats named in a renderer's sink protocolInfo list, in
+    /// order of preference. Empty when the renderer does not say.
+    pub fn accepted_by(sink_protocol_info: &str) -> Vec<SubtitleFormat> {
+        let mimes: Vec<String> = sink_protocol_info
+            .split(',')
+            .filter_map(|entry| entry.split(':').nth(2))
+            .map(|mime| mime.trim().to_lowercase())
+            .collect();
+        Self::ALL
+            .into_iter()
+            .filter(|format| {
+                format
+                    .mime_aliases()
+                    .iter()
+                    .any(|alias| mimes.iter().any(|mime| mime == alias))
+            })
+            .collect()
+    }
+
+    /// Format to serve a subtitle of this format in, given what the
+    /// renderer accepts. Renderers that do not list any get SRT, which is
+    /// the one nearly all of them read.
+    pub fn target_for(self, accepted: &[SubtitleFormat]) -> SubtitleFormat {
+        if accepted.contains(&self) {
+            self
+        } else {
+            accepted.first().copied().unwrap_or(SubtitleFormat::Srt)
+        }
+    }
+}
+
+/// One subtitle shown from `start_ms` to `end_ms`. Lines are separated by
+/// `\n`; `<i>`, `<b>` and `<u>` tags mark styling, as in SRT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// Decodes a subtitle file in format `from`, shifts every cue by
+/// `offset_ms` and writes it as `to`. A file already in the target format
+/// and not shifted is only re-encoded as UTF-8, so its styling is kept.
+pub fn convert(bytes: &[u8], from: SubtitleFormat, to: SubtitleFormat, offset_ms: i64) -> String {
+    let text = decode(bytes);
+    if from == to && offset_ms == 0 {
+        return text;
+    }
+    write(&shift(parse(&text, from), offset_ms), to)
+}
+
+/// Moves every cue `offset_ms` later (earlier when negative). Cues pushed
+/// before the start are cut at zero or dropped, as are cues pushed past the
+/// largest time there is. The offset comes from a URL; the sums are done
+/// in i128 so no value of it can overflow them.
+pub fn shift(cues: Vec<Cue>, offset_ms: i64) -> Vec<Cue> {
+    let shifted = |ms: u64| i128::from(ms) + i128::from(offset_ms);
+    cues.into_iter()
+        .filter_map(|cue| {
+            let end_ms = u64::try_from(shifted(cue.end_ms)).ok()?;
+            let start_ms = u64::try_from(shifted(cue.start_ms)).unwrap_or(0);
+            (end_ms > start_ms).then_some(Cue {
+                start_ms,
+                end_ms,
+                ..cue
+            })
+        })
+        .collect()
+}
+
+pub fn parse(text: &str, format: SubtitleFormat) -> Vec<Cue> {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut cues = match format {
+        SubtitleFormat::Srt => srt::parse(&text),
+        SubtitleFormat::Vtt => vtt::parse(&text),
+        SubtitleFormat::Ass => ass::parse(&text),
+        SubtitleFormat::Smi => smi::parse(&text),
+    };
+    cues.retain(|cue| cue.end_ms > cue.start_ms && !cue.text.trim().is_empty());
+    cues.sort_by_key(|cue| cue.start_ms);
+    cues
+}
+
+pub fn write(cues: &[Cue], format: SubtitleFormat) -> String {
+    match format {
+        SubtitleFormat::Srt => srt::write(cues),
+        SubtitleFormat::Vtt => vtt::write(cues),
+        SubtitleFormat::Ass => ass::write(cues),
+        SubtitleFormat::Smi => smi::write(cues),
+    }
+}
+
+/// Parses `[h:]mm:ss[.,]fff`, the timestamp form shared by SRT, WebVTT and
+/// (with centiseconds) ASS. Digits past the milliseconds are ignored; values
+/// too large to represent are rejected.
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (clock, fraction) = match value.rsplit_once(['.', ',']) {
+        Some((clock, fraction)) => (clock, fraction),
+        None => (value, ""),
+    };
+    let mut seconds = 0u64;
+    for part in clock.split(':') {
+        seconds = seconds
+            .checked_mul(60)?
+            .checked_add(part.trim().parse::<u64>().ok()?)?;
+    }
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // ".5" is half a second, ".05" fifty milliseconds, ".005" five
+    let millis = match fraction.get(..3).unwrap_or(fraction) {
+        "" => 0,
+        digits => digits.parse::<u64>().ok()? * 10u64.pow(3 - digits.len() as u32),
+    };
+    seconds.checked_mul(1000)?.checked_add(millis)
+}
+
+/// Formats milliseconds as `hh:mm:ss` followed by `separator` and the
+/// milliseconds, as SRT (`,`) and WebVTT (`.`) write them.
+fn format_timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// Removes markup tags other than `<i>`, `<b>` and `<u>`.
+fn keep_basic_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('>') else {
+            out.push_str(&rest[open..]);
+            return out;
+        };
+        let tag = &rest[open..open + close + 1];
+        let name = tag
+            .trim_start_matches(['<', '/'])
+            .trim_end_matches('>')
+            .to_lowercase();
+        if matches!(name.as_str(), "i" | "b" | "u") {
+            out.push_str(&tag.to_lowercase());
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decodes the character references that show up in WebVTT and SAMI text.
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "\u{0}lt")
+        .replace("&gt;", "\u{0}gt")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .replace("\u{0}lt", "<")
+        .replace("\u{0}gt", ">")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start_ms: u64, end_ms: u64, text: &str) -> Cue {
+        Cue {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+        }
+    }
+
+    fn sample() -> Vec<Cue> {
+        vec![
+            cue(1000, 2500, "<i>Fish & chips</i>\nsecond line"),
+            cue(2500, 4000, "<b>bold</b> and <u>under</u>"),
+            cue(3_723_450, 3_725_000, "a, b, c"),
+        ]
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        for format in SubtitleFormat::ALL {
+            let written = write(&sample(), format);
+            assert_eq!(parse(&written, format), sample(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn srt_with_crlf_and_stray_markup() {
+        let text = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<font color=red>Hello</font>\r\n<I>world</I>\r\n\r\n\
+                    \r\n2\r\n00:00:03,000 --> 00:00:02,000\r\nbackwards\r\n\r\n\
+                    00:00:05,000 --> 00:00:06,000\r\nno number\r\n";
+        assert_eq!(
+            parse(text, SubtitleFormat::Srt),
+            vec![
+                cue(1000, 2500, "Hello\n<i>world</i>"),
+                cue(5000, 6000, "no number")
+            ]
+        );
+    }
+
+    #[test]
+    fn vtt_skips_header_notes_and_settings() {
+        let text = "\u{feff}WEBVTT - title\n\nNOTE a comment\n\nSTYLE\n::cue { color: red }\n\n\
+                    intro\n00:01.000 --> 00:02.000 align:start line:0\n&lt;3 &amp; <c.red>more</c>\n";
+        assert_eq!(
+            parse(text, SubtitleFormat::Vtt),
+            vec![cue(1000, 2000, "<3 & more")]
+        );
+        assert!(write(&[cue(0, 1000, "<3 & <i>x</i>")], SubtitleFormat::Vtt)
+            .contains("&lt;3 &amp; <i>x</i>"));
+    }
+
+    #[test]
+    fn ass_overrides_become_tags() {
+        let text = "[Script Info]\nTitle: x\n\n[Events]\n\
+                    Format: Layer, Start, End, Style, Text\n\
+                    Dialogue: 0,0:00:01.00,0:00:02.50,Default,{\\i1}Hi{\\i0}, there\\Nnext\n\
+                    Dialogue: 0,0:00:03.00,0:00:04.00,Default,{\\pos(10,20)\\b1}bold\n\
+                    Comment: 0,0:00:05.00,0:00:06.00,Default,ignored\n";
+        assert_eq!(
+            parse(text, SubtitleFormat::Ass),
+            vec![
+                cue(1000, 2500, "<i>Hi</i>, there\nnext"),
+                cue(3000, 4000, "<b>bold</b>")
+            ]
+        );
+    }
+
+    #[test]
+    fn sami_syncs_run_to_the_next_one() {
+        let text = "<SAMI><BODY>\n<SYNC Start=1000><P Class=ENCC>Hello<BR/>  World\n\
+                    <SYNC Start=\"3000\"><P Class=ENCC>&nbsp;\n\
+                    <sync start=4000><p class=ENCC>last<p class=FRCC>dernier\n</BODY></SAMI>";
+        assert_eq!(
+            parse(text, SubtitleFormat::Smi),
+            vec![cue(1000, 3000, "Hello\nWorld"), cue(4000, 9000, "last")]
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("01:02:03,456"), Some(3_723_456));
+        assert_eq!(parse_timestamp("1:02.5"), Some(62_500));
+        assert_eq!(parse_timestamp("0:00:01.23456"), Some(1230 + 4));
+        assert_eq!(parse_timestamp("0:00:01.05"), Some(1050));
+        assert_eq!(parse_timestamp("12"), Some(12_000));
+        assert_eq!(parse_timestamp("0:00:01.x"), None);
+        assert_eq!(parse_timestamp("a:00"), None);
+        assert_eq!(parse_timestamp("99999999999999999:00:00"), None);
+        assert_eq!(format_timestamp(3_723_456, ','), "01:02:03,456");
+    }
+
+    #[test]
+    fn shift_moves_cues() {
+        let shifted = shift(sample(), 1500);
+        assert_eq!(
+            shifted[0],
+            cue(2500, 4000, "<i>Fish & chips</i>\nsecond line")
+        );
+        assert_eq!(shift(sample(), 0), sample());
+    }
+
+    #[test]
+    fn negative_shift_clamps_at_zero() {
+        let cues = vec![
+            cue(1000, 2000, "gone"),
+            cue(2000, 4000, "cut"),
+            cue(5000, 6000, "kept"),
+        ];
+        assert_eq!(
+            shift(cues, -2500),
+            vec![cue(0, 1500, "cut"), cue(2500, 3500, "kept")]
+        );
+    }
+
+    #[test]
+    fn extreme_shifts_do_not_overflow() {
+        assert!(shift(sample(), i64::MIN).is_empty());
+        let late = shift(vec![cue(0, u64::MAX - 10, "x")], i64::MAX);
+        assert!(late.is_empty());
+        let srt = convert(
+            b"1\n00:00:01,000 --> 00:00:02,000\nx\n",
+            SubtitleFormat::Srt,
+            SubtitleFormat::Vtt,
+            i64::MAX,
+        );
+        assert!(srt.contains("2562047788015:12:56.807 --> 2562047788015:12:57.807"));
+    }
+
+    #[test]
+    fn convert_keeps_unshifted_files_as_they_are() {
+        let text = "\u{feff}[Script Info]\n[Events]\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\an8}Top";
+        assert_eq!(
+            convert(text.as_bytes(), SubtitleFormat::Ass, SubtitleFormat::Ass, 0),
+            &text[3..]
+        );
+        assert_eq!(
+            convert(
+                text.as_bytes(),
+                SubtitleFormat::Ass,
+                SubtitleFormat::Srt,
+                500
+            ),
+            "1\n00:00:01,500 --> 00:00:02,500\nTop\n\n"
+        );
+    }
+
+    #[test]
+    fn formats_accepted_by_a_renderer() {
+        let sink =
+            "http-get:*:video/mp4:*,http-get:*:text/x-ass:*,http-get:*:application/x-subrip:*";
+        let accepted = SubtitleFormat::accepted_by(sink);
+        assert_eq!(accepted, vec![SubtitleFormat::Srt, SubtitleFormat::Ass]);
+        assert_eq!(
+            SubtitleFormat::Ass.target_for(&accepted),
+            SubtitleFormat::Ass
+        );
+        assert_eq!(
+            SubtitleFormat::Smi.target_for(&accepted),
+            SubtitleFormat::Srt
+        );
+        assert_eq!(SubtitleFormat::Vtt.target_for(&[]), SubtitleFormat::Srt);
+        assert_eq!(
+            SubtitleFormat::from_extension("SSA"),
+            Some(SubtitleFormat::Ass)
+        );
+    }
+}
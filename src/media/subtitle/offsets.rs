@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File name of the offsets inside the state directory.
+const OFFSETS_FILE: &str = "subtitle_offsets.json";
+
+/// Subtitle timing offsets set from the TUI, in milliseconds, keyed by the
+/// media file's path relative to the media root. Files without an entry
+/// play their subtitles as timed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtitleOffsets {
+    offsets: BTreeMap<String, i64>,
+}
+
+impl SubtitleOffsets {
+    /// Loads the saved offsets, or none when the file is missing or cannot
+    /// be parsed.
+    pub fn load(state_directory: &Path) -> Self {
+        let offsets = fs::read(state_directory.join(OFFSETS_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self { offsets }
+    }
+
+    /// Writes the offsets atomically (temp file + rename), creating the
+    /// state directory if needed.
+    pub fn save(&self, state_directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(state_directory)?;
+        let target = state_directory.join(OFFSETS_FILE);
+        let temp = target.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(&self.offsets)?)?;
+        fs::rename(temp, target)
+    }
+
+    pub fn get(&self, relative_path: &str) -> i64 {
+        self.offsets.get(relative_path).copied().unwrap_or(0)
+    }
+
+    /// Records the offset of a file; zero forgets it.
+    pub fn set(&mut self, relative_path: &str, offset_ms: i64) {
+        if offset_ms == 0 {
+            self.offsets.remove(relative_path);
+        } else {
+            self.offsets.insert(relative_path.to_string(), offset_ms);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! SAMI: HTML-like markup where each `<SYNC Start=ms>` shows its paragraph
+//! until the next one. A paragraph of `&nbsp;` clears the screen.
+
+use super::{decode_entities, keep_basic_tags, Cue};
+
+/// How long the last cue stays up when nothing follows it.
+const LAST_CUE_MS: u64 = 5000;
+
+pub fn parse(text: &str) -> Vec<Cue> {
+    let lower = text.to_ascii_lowercase();
+    let mut syncs: Vec<(u64, String)> = Vec::new();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find("<sync") {
+        let open = search + found;
+        let Some(close) = lower[open..].find('>').map(|c| open + c) else {
+            break;
+        };
+        let end = lower[close..]
+            .find("<sync")
+            .or_else(|| lower[close..].find("</body"))
+            .map(|e| close + e)
+            .unwrap_or(text.len());
+        if let Some(start) = attribute(&lower[open..close], "start") {
+            syncs.push((start, paragraph(&text[close + 1..end])));
+        }
+        search = end;
+    }
+
+    syncs
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, body))| !body.trim().is_empty())
+        .filter_map(|(index, (start, body))| {
+            let end_ms = match syncs.get(index + 1) {
+                Some((next, _)) => *next,
+                None => start.checked_add(LAST_CUE_MS)?,
+            };
+            Some(Cue {
+                start_ms: *start,
+                end_ms,
+                text: body.clone(),
+            })
+        })
+        .collect()
+}
+
+pub fn write(cues: &[Cue]) -> String {
+    let mut out = String::from(
+        "<SAMI>\n<HEAD>\n<TITLE></TITLE>\n<STYLE TYPE=\"text/css\">\n<!--\n\
+         P { font-family: Arial; font-weight: normal; color: white; text-align: center; }\n\
+         .UNKNOWNCC { Name: Unknown; lang: und; SAMIType: CC; }\n\
+         -->\n</STYLE>\n</HEAD>\n<BODY>\n",
+    );
+    for (index, cue) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "<SYNC Start={}><P Class=UNKNOWNCC>{}\n",
+            cue.start_ms,
+            escape(&cue.text).replace('\n', "<br>")
+        ));
+        // Clears the screen unless the next cue starts right away
+        if cues
+            .get(index + 1)
+            .is_none_or(|next| next.start_ms > cue.end_ms)
+        {
+            out.push_str(&format!(
+                "<SYNC Start={}><P Class=UNKNOWNCC>&nbsp;\n",
+                cue.end_ms
+            ));
+        }
+    }
+    out.push_str("</BODY>\n</SAMI>\n");
+    out
+}
+
+/// Numeric value of `name=` in a lower-cased tag, quoted or not.
+fn attribute(tag: &str, name: &str) -> Option<u64> {
+    let value = tag.split(&format!("{}=", name)).nth(1)?;
+    let digits: String = value
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Text of the first `<P>` in a SYNC block. Further paragraphs are other
+/// languages of the same cue.
+fn paragraph(block: &str) -> String {
+    let lower = block.to_ascii_lowercase();
+    let body = match lower.find("<p") {
+        Some(p) => {
+            let start = lower[p..]
+                .find('>')
+                .map(|c| p + c + 1)
+                .unwrap_or(block.len());
+            let end = lower[start..]
+                .find("<p")
+                .or_else(|| lower[start..].find("</p"))
+                .map(|e| start + e)
+                .unwrap_or(block.len());
+            &block[start..end]
+        }
+        None => block,
+    };
+    let mut lines = String::new();
+    for (index, part) in split_breaks(body).iter().enumerate() {
+        if index > 0 {
+            lines.push('\n');
+        }
+        let part = part.split_whitespace().collect::<Vec<_>>().join(" ");
+        lines.push_str(&part);
+    }
+    decode_entities(&keep_basic_tags(&lines)).trim().to_string()
+}
+
+/// Splits markup at `<br>` tags, in any case and with or without a slash.
+fn split_breaks(body: &str) -> Vec<&str> {
+    let lower = body.to_ascii_lowercase();
+    let mut parts = Vec::new();
+    let mut last = 0;
+    let mut search = 0;
+    while let Some(found) = lower[search..].find("<br") {
+        let open = search + found;
+        let Some(close) = lower[open..].find('>') else {
+            break;
+        };
+        parts.push(&body[last..open]);
+        last = open + close + 1;
+        search = last;
+    }
+    parts.push(&body[last..]);
+    parts
+}
+
+/// Escapes `&` and `<` outside of styling tags.
+fn escape(text: &str) -> String {
+    let mut out = text.replace('&', "&amp;").replace('<', "&lt;");
+    for tag in ["i", "b", "u"] {
+        out = out
+            .replace(&format!("&lt;{}>", tag), &format!("<{}>", tag))
+            .replace(&format!("&lt;/{}>", tag), &format!("</{}>", tag));
+    }
+    out
+}
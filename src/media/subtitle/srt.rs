@@ -0,0 +1,51 @@
+//! SubRip: numbered blocks of `hh:mm:ss,mmm --> hh:mm:ss,mmm` and text.
+
+use super::{format_timestamp, keep_basic_tags, parse_timestamp, Cue};
+
+pub fn parse(text: &str) -> Vec<Cue> {
+    blocks(text)
+        .filter_map(|block| {
+            let mut lines = block.lines();
+            let mut timing = lines.next()?;
+            if !timing.contains("-->") {
+                // The cue number
+                timing = lines.next()?;
+            }
+            let (start_ms, end_ms) = parse_timing(timing)?;
+            let body: Vec<&str> = lines.collect();
+            Some(Cue {
+                start_ms,
+                end_ms,
+                text: keep_basic_tags(&body.join("\n")),
+            })
+        })
+        .collect()
+}
+
+pub fn write(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_timestamp(cue.start_ms, ','),
+            format_timestamp(cue.end_ms, ','),
+            cue.text
+        ));
+    }
+    out
+}
+
+/// Blank-line separated blocks, without the empty ones.
+pub(super) fn blocks(text: &str) -> impl Iterator<Item = &str> {
+    text.split("\n\n")
+        .map(|block| block.trim_matches('\n'))
+        .filter(|block| !block.trim().is_empty())
+}
+
+/// Start and end of a `start --> end [settings]` line.
+pub(super) fn parse_timing(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
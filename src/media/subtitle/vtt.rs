@@ -0,0 +1,54 @@
+//! WebVTT: a `WEBVTT` header, then cues like SRT's with `.` before the
+//! milliseconds, optional identifiers and cue settings after the timing.
+
+use super::srt::{blocks, parse_timing};
+use super::{decode_entities, format_timestamp, keep_basic_tags, Cue};
+
+pub fn parse(text: &str) -> Vec<Cue> {
+    blocks(text.trim_start_matches('\u{feff}'))
+        .filter_map(|block| {
+            let mut lines = block.lines();
+            // Skips the header and any identifier line before the timing;
+            // NOTE, STYLE and REGION blocks have no timing line at all.
+            let timing = lines.by_ref().take(2).find(|line| line.contains("-->"))?;
+            let (start_ms, end_ms) = parse_timing(timing)?;
+            let body: Vec<&str> = lines.collect();
+            Some(Cue {
+                start_ms,
+                end_ms,
+                text: decode_entities(&keep_basic_tags(&body.join("\n"))),
+            })
+        })
+        .collect()
+}
+
+pub fn write(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(cue.start_ms, '.'),
+            format_timestamp(cue.end_ms, '.'),
+            escape(&cue.text)
+        ));
+    }
+    out
+}
+
+/// Escapes `&` and any `<` that does not open a styling tag.
+fn escape(text: &str) -> String {
+    let text = text.replace('&', "&amp;");
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        let tag = &rest[open..];
+        let styling = ["<i>", "</i>", "<b>", "</b>", "<u>", "</u>"]
+            .iter()
+            .any(|t| tag.starts_with(t));
+        out.push_str(if styling { "<" } else { "&lt;" });
+        rest = &rest[open + 1..];
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,58 @@
+//! Text files kept next to the media, such as subtitles and playlists, are
+//! saved in whatever charset the tool that wrote them used. They are read
+//! into UTF-8 here.
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+
+/// Decodes a text file: by its byte order mark, as UTF-8 when it is valid
+/// UTF-8, and otherwise in the charset that fits the bytes best.
+pub fn decode(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return encoding
+            .decode_without_bom_handling(&bytes[bom_len..])
+            .0
+            .into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector
+        .guess(None, true)
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_order_marks_win() {
+        assert_eq!(decode(b"\xEF\xBB\xBFcaf\xC3\xA9"), "café");
+        assert_eq!(decode(b"\xFF\xFEc\x00a\x00f\x00\xE9\x00"), "café");
+        assert_eq!(decode(b"\xFE\xFF\x00c\x00a\x00f\x00\xE9"), "café");
+    }
+
+    #[test]
+    fn valid_utf8_is_kept() {
+        assert_eq!(decode("Grüße, 東京".as_bytes()), "Grüße, 東京");
+        assert_eq!(decode(b""), "");
+    }
+
+    #[test]
+    fn legacy_charsets_are_guessed() {
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252
+            .encode("Le café était très bon, merci à vous. Où est la gare ? Déjà vu.");
+        assert_eq!(
+            decode(&latin1),
+            "Le café était très bon, merci à vous. Où est la gare ? Déjà vu."
+        );
+        let (sjis, _, _) =
+            encoding_rs::SHIFT_JIS.encode("こんにちは、世界。今日はいい天気ですね。");
+        assert_eq!(decode(&sjis), "こんにちは、世界。今日はいい天気ですね。");
+    }
+}
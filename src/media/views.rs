@@ -0,0 +1,106 @@
+use crate::media::manager::{MediaClass, MediaFile};
+
+/// Number of entries shown in the "Recently Added" view.
+const RECENT_LIMIT: usize = 100;
+
+/// A virtual arrangement of the media library. Every view is computed from
+/// the same file list, so switching views never rescans the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// The real directory hierarchy.
+    Folders,
+    Videos,
+    Music,
+    /// Newest files first, by modification time.
+    Recent,
+    /// One folder per file extension.
+    ByType,
+}
+
+impl View {
+    pub const ALL: [View; 5] = [
+        View::Folders,
+        View::Videos,
+        View::Music,
+        View::Recent,
+        View::ByType,
+    ];
+
+    /// Container ID of the view's top-level container in ContentDirectory.
+    pub fn id(self) -> &'static str {
+        match self {
+            View::Folders => "folders",
+            View::Videos => "videos",
+            View::Music => "music",
+            View::Recent => "recent",
+            View::ByType => "types",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            View::Folders => "By Folder",
+            View::Videos => "All Videos",
+            View::Music => "All Music",
+            View::Recent => "Recently Added",
+            View::ByType => "By Type",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<View> {
+        View::ALL.into_iter().find(|view| view.id() == id)
+    }
+
+    /// The view after this one, wrapping around; used by the TUI to cycle.
+    pub fn next(self) -> View {
+        let pos = View::ALL.iter().position(|&v| v == self).unwrap_or(0);
+        View::ALL[(pos + 1) % View::ALL.len()]
+    }
+}
+
+/// Returns indices into `files` for the entries of `view`, in display order.
+/// `files` is expected in relative-path order, as the library keeps it.
+pub fn view_files(view: View, files: &[MediaFile]) -> Vec<usize> {
+    let all = 0..files.len();
+    match view {
+        View::Folders => all.collect(),
+        View::Videos => all
+            .filter(|&i| files[i].class() == MediaClass::Video)
+            .collect(),
+        View::Music => all
+            .filter(|&i| files[i].class() == MediaClass::Audio)
+            .collect(),
+        View::Recent => {
+            let mut indices: Vec<usize> = all.collect();
+            // Stable sort keeps path order among files with the same mtime.
+            indices.sort_by(|&a, &b| files[b].modified.cmp(&files[a].modified));
+            indices.truncate(RECENT_LIMIT);
+            indices
+        }
+        View::ByType => {
+            let mut indices: Vec<usize> = all.collect();
+            indices.sort_by_key(|&i| files[i].extension());
+            indices
+        }
+    }
+}
+
+/// Number of entries of `view`; `view_files(view, files).len()` without
+/// building the list.
+pub fn view_len(view: View, files: &[MediaFile]) -> usize {
+    let count = |class| files.iter().filter(|f| f.class() == class).count();
+    match view {
+        View::Folders | View::ByType => files.len(),
+        View::Videos => count(MediaClass::Video),
+        View::Music => count(MediaClass::Audio),
+        View::Recent => files.len().min(RECENT_LIMIT),
+    }
+}
+
+/// Distinct extensions present in `files`, sorted; one "By Type" folder each.
+pub fn extensions(files: &[MediaFile]) -> Vec<String> {
+    let mut extensions: Vec<String> = files.iter().map(MediaFile::extension).collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
@@ -0,0 +1,116 @@
+use crate::media::formats::FormatRegistry;
+use crate::server::description::{Action, Argument, ServiceSpec, StateVariable};
+use crate::soap::{extract_tag, xml_escape, UpnpError};
+
+pub const CONNECTION_MANAGER: &str = "urn:schemas-upnp-org:service:ConnectionManager:1";
+
+pub const SERVICE: ServiceSpec = ServiceSpec {
+    service_type: CONNECTION_MANAGER,
+    service_id: "urn:upnp-org:serviceId:ConnectionManager",
+    scpd_path: "/upnp/ConnectionManager.xml",
+    control_path: "/upnp/control/ConnectionManager",
+    event_path: "/upnp/event/ConnectionManager",
+    actions: &[
+        Action {
+            name: "GetProtocolInfo",
+            arguments: &[
+                Argument::output("Source", "SourceProtocolInfo"),
+                Argument::output("Sink", "SinkProtocolInfo"),
+            ],
+        },
+        Action {
+            name: "GetCurrentConnectionIDs",
+            arguments: &[Argument::output("ConnectionIDs", "CurrentConnectionIDs")],
+        },
+        Action {
+            name: "GetCurrentConnectionInfo",
+            arguments: &[
+                Argument::input("ConnectionID", "A_ARG_TYPE_ConnectionID"),
+                Argument::output("RcsID", "A_ARG_TYPE_RcsID"),
+                Argument::output("AVTransportID", "A_ARG_TYPE_AVTransportID"),
+                Argument::output("ProtocolInfo", "A_ARG_TYPE_ProtocolInfo"),
+                Argument::output("PeerConnectionManager", "A_ARG_TYPE_ConnectionManager"),
+                Argument::output("PeerConnectionID", "A_ARG_TYPE_ConnectionID"),
+                Argument::output("Direction", "A_ARG_TYPE_Direction"),
+                Argument::output("Status", "A_ARG_TYPE_ConnectionStatus"),
+            ],
+        },
+    ],
+    state_variables: &[
+        StateVariable::new("SourceProtocolInfo", "string", true),
+        StateVariable::new("SinkProtocolInfo", "string", true),
+        StateVariable::new("CurrentConnectionIDs", "string", true),
+        StateVariable::new("A_ARG_TYPE_ConnectionStatus", "string", false).allowed(&[
+            "OK",
+            "ContentFormatMismatch",
+            "InsufficientBandwidth",
+            "UnreliableChannel",
+            "Unknown",
+        ]),
+        StateVariable::new("A_ARG_TYPE_ConnectionManager", "string", false),
+        StateVariable::new("A_ARG_TYPE_Direction", "string", false).allowed(&["Input", "Output"]),
+        StateVariable::new("A_ARG_TYPE_ProtocolInfo", "string", false),
+        StateVariable::new("A_ARG_TYPE_ConnectionID", "i4", false),
+        StateVariable::new("A_ARG_TYPE_AVTransportID", "i4", false),
+        StateVariable::new("A_ARG_TYPE_RcsID", "i4", false),
+    ],
+};
+
+/// RustCast does not implement PrepareForConnection, so only the default
+/// connection with ID 0 ever exists.
+const DEFAULT_CONNECTION_ID: &str = "0";
+
+/// Dispatches a ConnectionManager action and returns its output arguments.
+pub fn handle_action(
+    action: &str,
+    body: &str,
+    formats: &FormatRegistry,
+) -> Result<String, UpnpError> {
+    match action {
+        "GetProtocolInfo" => Ok(format!(
+            "<Source>{}</Source><Sink></Sink>",
+            xml_escape(&source_protocol_info(formats))
+        )),
+        "GetCurrentConnectionIDs" => Ok(format!(
+            "<ConnectionIDs>{}</ConnectionIDs>",
+            DEFAULT_CONNECTION_ID
+        )),
+        "GetCurrentConnectionInfo" => current_connection_info(body),
+        _ => Err(UpnpError::INVALID_ACTION),
+    }
+}
+
+/// Evented state variables and their current values.
+pub fn evented_state(formats: &FormatRegistry) -> Vec<(&'static str, String)> {
+    vec![
+        ("SourceProtocolInfo", source_protocol_info(formats)),
+        ("SinkProtocolInfo", String::new()),
+        ("CurrentConnectionIDs", DEFAULT_CONNECTION_ID.to_string()),
+    ]
+}
+
+/// Comma-separated protocolInfo list covering every MIME type we serve.
+pub fn source_protocol_info(formats: &FormatRegistry) -> String {
+    formats
+        .mime_types()
+        .iter()
+        .map(|mime| format!("http-get:*:{}:*", mime))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn current_connection_info(body: &str) -> Result<String, UpnpError> {
+    let connection_id = extract_tag(body, "ConnectionID").ok_or(UpnpError::INVALID_ARGS)?;
+    if connection_id.trim() != DEFAULT_CONNECTION_ID {
+        return Err(UpnpError::INVALID_CONNECTION_REFERENCE);
+    }
+
+    Ok("<RcsID>-1</RcsID>\
+<AVTransportID>-1</AVTransportID>\
+<ProtocolInfo></ProtocolInfo>\
+<PeerConnectionManager></PeerConnectionManager>\
+<PeerConnectionID>-1</PeerConnectionID>\
+<Direction>Output</Direction>\
+<Status>OK</Status>"
+        .to_string())
+}
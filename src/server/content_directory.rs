@@ -0,0 +1,704 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::dlna::metadata::{
+    container_element, item_element, upnp_class, wrap_didl, PLAYLIST_CONTAINER, STORAGE_FOLDER,
+};
+use crate::dlna::search;
+use crate::media::finder::find_subtitles;
+use crate::media::manager::{MediaClass, MediaFile};
+use crate::media::playlist::Playlist;
+use crate::media::views::{extensions, view_files, view_len, View};
+use crate::server::description::{Action, Argument, ServiceSpec, StateVariable};
+use crate::server::state::ServerState;
+use crate::soap::{extract_tag, xml_escape, UpnpError};
+
+pub const CONTENT_DIRECTORY: &str = "urn:schemas-upnp-org:service:ContentDirectory:1";
+
+pub const SERVICE: ServiceSpec = ServiceSpec {
+    service_type: CONTENT_DIRECTORY,
+    service_id: "urn:upnp-org:serviceId:ContentDirectory",
+    scpd_path: "/upnp/ContentDirectory.xml",
+    control_path: "/upnp/control/ContentDirectory",
+    event_path: "/upnp/event/ContentDirectory",
+    actions: &[
+        Action {
+            name: "Browse",
+            arguments: &[
+                Argument::input("ObjectID", "A_ARG_TYPE_ObjectID"),
+                Argument::input("BrowseFlag", "A_ARG_TYPE_BrowseFlag"),
+                Argument::input("Filter", "A_ARG_TYPE_Filter"),
+                Argument::input("StartingIndex", "A_ARG_TYPE_Index"),
+                Argument::input("RequestedCount", "A_ARG_TYPE_Count"),
+                Argument::input("SortCriteria", "A_ARG_TYPE_SortCriteria"),
+                Argument::output("Result", "A_ARG_TYPE_Result"),
+                Argument::output("NumberReturned", "A_ARG_TYPE_Count"),
+                Argument::output("TotalMatches", "A_ARG_TYPE_Count"),
+                Argument::output("UpdateID", "A_ARG_TYPE_UpdateID"),
+            ],
+        },
+        Action {
+            name: "Search",
+            arguments: &[
+                Argument::input("ContainerID", "A_ARG_TYPE_ObjectID"),
+                Argument::input("SearchCriteria", "A_ARG_TYPE_SearchCriteria"),
+                Argument::input("Filter", "A_ARG_TYPE_Filter"),
+                Argument::input("StartingIndex", "A_ARG_TYPE_Index"),
+                Argument::input("RequestedCount", "A_ARG_TYPE_Count"),
+                Argument::input("SortCriteria", "A_ARG_TYPE_SortCriteria"),
+                Argument::output("Result", "A_ARG_TYPE_Result"),
+                Argument::output("NumberReturned", "A_ARG_TYPE_Count"),
+                Argument::output("TotalMatches", "A_ARG_TYPE_Count"),
+                Argument::output("UpdateID", "A_ARG_TYPE_UpdateID"),
+            ],
+        },
+        Action {
+            name: "GetSearchCapabilities",
+            arguments: &[Argument::output("SearchCaps", "SearchCapabilities")],
+        },
+        Action {
+            name: "GetSortCapabilities",
+            arguments: &[Argument::output("SortCaps", "SortCapabilities")],
+        },
+        Action {
+            name: "GetSystemUpdateID",
+            arguments: &[Argument::output("Id", "SystemUpdateID")],
+        },
+    ],
+    state_variables: &[
+        StateVariable::new("SearchCapabilities", "string", false),
+        StateVariable::new("SortCapabilities", "string", false),
+        StateVariable::new("SystemUpdateID", "ui4", true),
+        StateVariable::new("ContainerUpdateIDs", "string", true),
+        StateVariable::new("A_ARG_TYPE_ObjectID", "string", false),
+        StateVariable::new("A_ARG_TYPE_Result", "string", false),
+        StateVariable::new("A_ARG_TYPE_BrowseFlag", "string", false)
+            .allowed(&["BrowseMetadata", "BrowseDirectChildren"]),
+        StateVariable::new("A_ARG_TYPE_SearchCriteria", "string", false),
+        StateVariable::new("A_ARG_TYPE_Filter", "string", false),
+        StateVariable::new("A_ARG_TYPE_SortCriteria", "string", false),
+        StateVariable::new("A_ARG_TYPE_Index", "ui4", false),
+        StateVariable::new("A_ARG_TYPE_Count", "ui4", false),
+        StateVariable::new("A_ARG_TYPE_UpdateID", "ui4", false),
+    ],
+};
+
+/// Object ID of the root container, fixed by the ContentDirectory spec.
+const ROOT_ID: &str = "0";
+
+/// Object ID of the container listing the playlist files.
+const PLAYLISTS_ID: &str = "playlists";
+
+/// Properties the Search action can evaluate, reported by GetSearchCapabilities.
+const SEARCH_CAPABILITIES: &str =
+    "@id,@parentID,dc:title,dc:creator,upnp:artist,upnp:class,res@protocolInfo";
+
+/// Dispatches a ContentDirectory action and returns its output arguments.
+pub fn handle_action(action: &str, body: &str, state: &ServerState) -> Result<String, UpnpError> {
+    match action {
+        "Browse" => browse(body, state),
+        "Search" => search(body, state),
+        "GetSearchCapabilities" => Ok(format!("<SearchCaps>{}</SearchCaps>", SEARCH_CAPABILITIES)),
+        "GetSortCapabilities" => Ok("<SortCaps></SortCaps>".to_string()),
+        "GetSystemUpdateID" => Ok(format!("<Id>{}</Id>", state.system_update_id())),
+        _ => Err(UpnpError::INVALID_ACTION),
+    }
+}
+
+/// Evented state variables for a given SystemUpdateID. Changes are not
+/// tracked per container, so every change is reported against the root.
+pub fn evented_state(update_id: u32) -> Vec<(&'static str, String)> {
+    vec![
+        ("SystemUpdateID", update_id.to_string()),
+        ("ContainerUpdateIDs", format!("{},{}", ROOT_ID, update_id)),
+    ]
+}
+
+/// A browsable object. IDs are built from view IDs and paths relative to the
+/// media root, so they stay stable across rescans:
+///
+///   0                              root
+///   folders, videos, music, ...    view containers (see `View::id`)
+///   folders/Action                 directory in the "By Folder" view
+///   types/mkv                      extension folder in the "By Type" view
+///   <container>/<relative path>    item (e.g. `videos/Action/movie.mkv`);
+///                                  in "By Folder" the prefix is `folders`
+///   playlists                      playlist files, shown when there are any
+///   playlists/<relative path>      one playlist (e.g. `playlists/Trip.m3u`)
+///   playlists/<relative path>/<n>  its n-th entry, counted from 0
+enum Object<'a> {
+    Root,
+    View(View),
+    Folder(&'a str),
+    Extension(String),
+    Playlists,
+    Playlist(&'a Playlist),
+    Item {
+        id: String,
+        parent_id: String,
+        file: &'a MediaFile,
+    },
+}
+
+impl Object<'_> {
+    fn id(&self) -> String {
+        match self {
+            Object::Root => ROOT_ID.to_string(),
+            Object::View(view) => view.id().to_string(),
+            Object::Folder(path) => folder_id(path),
+            Object::Extension(ext) => format!("{}/{}", View::ByType.id(), ext),
+            Object::Playlists => PLAYLISTS_ID.to_string(),
+            Object::Playlist(playlist) => playlist_id(playlist),
+            Object::Item { id, .. } => id.clone(),
+        }
+    }
+
+    fn parent_id(&self) -> String {
+        match self {
+            Object::Root => "-1".to_string(),
+            Object::View(_) => ROOT_ID.to_string(),
+            Object::Folder(path) => folder_id(parent_dir(path)),
+            Object::Extension(_) => View::ByType.id().to_string(),
+            Object::Playlists => ROOT_ID.to_string(),
+            Object::Playlist(_) => PLAYLISTS_ID.to_string(),
+            Object::Item { parent_id, .. } => parent_id.clone(),
+        }
+    }
+}
+
+/// ID of a directory in the "By Folder" view; `""` is the view itself.
+fn folder_id(path: &str) -> String {
+    if path.is_empty() {
+        View::Folders.id().to_string()
+    } else {
+        format!("{}/{}", View::Folders.id(), path)
+    }
+}
+
+fn playlist_id(playlist: &Playlist) -> String {
+    format!("{}/{}", PLAYLISTS_ID, playlist.relative_path)
+}
+
+/// Directory part of a relative path, `""` for the media root.
+fn parent_dir(relative_path: &str) -> &str {
+    relative_path
+        .rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or("")
+}
+
+/// All views over one scan of the media directory. Folders are derived from
+/// the files' relative paths, so directories without any media are never shown.
+struct Catalog<'a> {
+    files: &'a [MediaFile],
+    folders: BTreeSet<&'a str>,
+    extensions: Vec<String>,
+    playlists: &'a [Playlist],
+    /// Direct children of each directory in "By Folder", `""` being the
+    /// view itself; counted once so a listing does not walk every file for
+    /// each container in it.
+    folder_sizes: HashMap<&'a str, usize>,
+    /// Files in each "By Type" extension folder.
+    extension_sizes: HashMap<String, usize>,
+    /// Entries of the flat views (All Videos, All Music, Recently Added).
+    view_sizes: Vec<(View, usize)>,
+}
+
+impl<'a> Catalog<'a> {
+    fn new(files: &'a [MediaFile], playlists: &'a [Playlist]) -> Self {
+        let mut folders = BTreeSet::new();
+        let mut folder_sizes = HashMap::new();
+        let mut extension_sizes = HashMap::new();
+        for file in files {
+            let mut path = file.relative_path.as_str();
+            *folder_sizes.entry(parent_dir(path)).or_insert(0) += 1;
+            *extension_sizes.entry(file.extension()).or_insert(0) += 1;
+            while let Some((parent, _)) = path.rsplit_once('/') {
+                folders.insert(parent);
+                path = parent;
+            }
+        }
+        for folder in &folders {
+            *folder_sizes.entry(parent_dir(folder)).or_insert(0) += 1;
+        }
+        let view_sizes = [View::Videos, View::Music, View::Recent]
+            .into_iter()
+            .map(|view| (view, view_len(view, files)))
+            .collect();
+        Self {
+            files,
+            folders,
+            extensions: extensions(files),
+            playlists,
+            folder_sizes,
+            extension_sizes,
+            view_sizes,
+        }
+    }
+
+    fn item(container: &str, parent_id: String, file: &'a MediaFile) -> Object<'a> {
+        Object::Item {
+            id: format!("{}/{}", container, file.relative_path),
+            parent_id,
+            file,
+        }
+    }
+
+    fn find(&self, id: &str) -> Option<Object<'a>> {
+        if id == ROOT_ID {
+            return Some(Object::Root);
+        }
+        if let Some(view) = View::from_id(id) {
+            return Some(Object::View(view));
+        }
+        if id == PLAYLISTS_ID {
+            return Some(Object::Playlists);
+        }
+
+        let (view_id, rest) = id.split_once('/')?;
+        if view_id == PLAYLISTS_ID {
+            return self.find_in_playlists(rest);
+        }
+        match View::from_id(view_id)? {
+            View::Folders => {
+                if let Some(folder) = self.folders.get(rest) {
+                    return Some(Object::Folder(folder));
+                }
+                let file = self.file(rest)?;
+                Some(Self::item(
+                    view_id,
+                    folder_id(parent_dir(&file.relative_path)),
+                    file,
+                ))
+            }
+            View::ByType => {
+                let (ext, path) = match rest.split_once('/') {
+                    Some((ext, path)) => (ext, Some(path)),
+                    None => (rest, None),
+                };
+                let ext = self.extensions.iter().find(|e| *e == ext)?;
+                match path {
+                    None => Some(Object::Extension(ext.clone())),
+                    Some(path) => {
+                        let file = self.file(path).filter(|f| f.extension() == *ext)?;
+                        let container = format!("{}/{}", view_id, ext);
+                        Some(Self::item(&container, container.clone(), file))
+                    }
+                }
+            }
+            view => view_files(view, self.files)
+                .into_iter()
+                .map(|i| &self.files[i])
+                .find(|f| f.relative_path == rest)
+                .map(|file| Self::item(view_id, view_id.to_string(), file)),
+        }
+    }
+
+    /// A playlist, or one of its entries, by the ID part after `playlists/`.
+    fn find_in_playlists(&self, rest: &str) -> Option<Object<'a>> {
+        if let Some(playlist) = self.playlists.iter().find(|p| p.relative_path == rest) {
+            return Some(Object::Playlist(playlist));
+        }
+        let (path, position) = rest.rsplit_once('/')?;
+        let position: usize = position.parse().ok()?;
+        let playlist = self.playlists.iter().find(|p| p.relative_path == path)?;
+        let file = self.file(playlist.entries.get(position)?)?;
+        Some(Object::Item {
+            id: format!("{}/{}", playlist_id(playlist), position),
+            parent_id: playlist_id(playlist),
+            file,
+        })
+    }
+
+    /// Library file by relative path.
+    fn file(&self, relative_path: &str) -> Option<&'a MediaFile> {
+        self.files
+            .binary_search_by(|f| f.relative_path.as_str().cmp(relative_path))
+            .ok()
+            .map(|pos| &self.files[pos])
+    }
+
+    /// Entries of a playlist found in the library. Each keeps its position
+    /// in the playlist as its ID, so IDs stay put when other entries are
+    /// missing.
+    fn playlist_entries(&self, playlist: &Playlist) -> Vec<Object<'a>> {
+        let container = playlist_id(playlist);
+        playlist
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(position, entry)| {
+                Some(Object::Item {
+                    id: format!("{}/{}", container, position),
+                    parent_id: container.clone(),
+                    file: self.file(entry)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Direct children of a container, in display order. In "By Folder",
+    /// sub-folders come first, then files, both in path order.
+    fn children(&self, object: &Object<'a>) -> Vec<Object<'a>> {
+        match object {
+            Object::Root => {
+                let mut views: Vec<Object> = View::ALL.into_iter().map(Object::View).collect();
+                if !self.playlists.is_empty() {
+                    views.push(Object::Playlists);
+                }
+                views
+            }
+            Object::Playlists => self.playlists.iter().map(Object::Playlist).collect(),
+            Object::Playlist(playlist) => self.playlist_entries(playlist),
+            Object::View(View::Folders) => self.folder_children(""),
+            Object::Folder(path) => self.folder_children(path),
+            Object::View(View::ByType) => self
+                .extensions
+                .iter()
+                .map(|ext| Object::Extension(ext.clone()))
+                .collect(),
+            Object::Extension(ext) => {
+                let container = format!("{}/{}", View::ByType.id(), ext);
+                self.files
+                    .iter()
+                    .filter(|file| file.extension() == *ext)
+                    .map(|file| Self::item(&container, container.clone(), file))
+                    .collect()
+            }
+            Object::View(view) => view_files(*view, self.files)
+                .into_iter()
+                .map(|i| Self::item(view.id(), view.id().to_string(), &self.files[i]))
+                .collect(),
+            Object::Item { .. } => Vec::new(),
+        }
+    }
+
+    /// Number of direct children of a container, as `children` would list.
+    fn child_count(&self, object: &Object) -> usize {
+        match object {
+            Object::View(View::Folders) => self.folder_sizes.get("").copied().unwrap_or(0),
+            Object::Folder(path) => self.folder_sizes.get(path).copied().unwrap_or(0),
+            Object::View(View::ByType) => self.extensions.len(),
+            Object::Extension(ext) => self.extension_sizes.get(ext).copied().unwrap_or(0),
+            Object::View(view) => self
+                .view_sizes
+                .iter()
+                .find(|(sized, _)| sized == view)
+                .map_or(0, |&(_, size)| size),
+            Object::Root => View::ALL.len() + usize::from(!self.playlists.is_empty()),
+            Object::Playlists => self.playlists.len(),
+            Object::Playlist(playlist) => playlist
+                .entries
+                .iter()
+                .filter(|entry| self.file(entry).is_some())
+                .count(),
+            Object::Item { .. } => 0,
+        }
+    }
+
+    fn folder_children(&self, dir: &str) -> Vec<Object<'a>> {
+        let folders = self
+            .folders
+            .iter()
+            .filter(|folder| parent_dir(folder) == dir)
+            .map(|folder| Object::Folder(folder));
+        let files = self
+            .files
+            .iter()
+            .filter(|file| parent_dir(&file.relative_path) == dir)
+            .map(|file| Self::item(View::Folders.id(), folder_id(dir), file));
+        folders.chain(files).collect()
+    }
+
+    /// Every object below a container, at any depth, in display order.
+    /// Searching from the root walks "By Folder" only, so each file is
+    /// matched once rather than once per view.
+    fn descendants(&self, object: &Object<'a>) -> Vec<Object<'a>> {
+        let start = match object {
+            Object::Root => Object::View(View::Folders),
+            Object::View(view) => Object::View(*view),
+            Object::Folder(path) => Object::Folder(path),
+            Object::Extension(ext) => Object::Extension(ext.clone()),
+            Object::Playlists => Object::Playlists,
+            Object::Playlist(playlist) => Object::Playlist(playlist),
+            Object::Item { .. } => return Vec::new(),
+        };
+        let mut result = Vec::new();
+        for child in self.children(&start) {
+            let nested = self.descendants(&child);
+            result.push(child);
+            result.extend(nested);
+        }
+        result
+    }
+
+    /// Value of a searchable property for an object, see SEARCH_CAPABILITIES.
+    fn property(&self, object: &Object, name: &str) -> Option<String> {
+        let mime = match object {
+            Object::Item { file, .. } => Some(file.mime_type()),
+            _ => None,
+        };
+        match name {
+            "@id" => Some(object.id()),
+            "@parentID" => Some(object.parent_id()),
+            "dc:title" => Some(self.title(object)),
+            "upnp:class" => Some(
+                match object {
+                    Object::Root | Object::View(_) | Object::Extension(_) | Object::Playlists => {
+                        "object.container"
+                    }
+                    Object::Folder(_) => STORAGE_FOLDER,
+                    Object::Playlist(_) => PLAYLIST_CONTAINER,
+                    Object::Item { file, .. } => upnp_class(file.class()),
+                }
+                .to_string(),
+            ),
+            "upnp:artist" | "dc:creator" => match object {
+                Object::Item { file, .. } => file.info.as_ref()?.artist.clone(),
+                _ => None,
+            },
+            "res@protocolInfo" => mime.map(|m| format!("http-get:*:{}:*", m)),
+            _ => None,
+        }
+    }
+
+    fn title(&self, object: &Object) -> String {
+        match object {
+            Object::Root => String::new(),
+            Object::View(view) => view.title().to_string(),
+            Object::Folder(path) => path.rsplit('/').next().unwrap_or(path).to_string(),
+            Object::Extension(ext) => ext.to_uppercase(),
+            Object::Playlists => "Playlists".to_string(),
+            Object::Playlist(playlist) => playlist.title.clone(),
+            Object::Item { file, .. } => file.name.clone(),
+        }
+    }
+
+    fn render(&self, object: &Object, state: &ServerState) -> String {
+        match object {
+            Object::Item { file, .. } => item_element(
+                &object.id(),
+                &object.parent_id(),
+                file,
+                &state.config.media_url(&file.relative_path),
+                &match file.class() {
+                    MediaClass::Video => find_subtitles(file, &state.config),
+                    _ => Vec::new(),
+                },
+                file.info.as_ref().is_some_and(|info| info.time_seek),
+                state
+                    .library
+                    .art()
+                    .file_source(
+                        Path::new(&file.path),
+                        file.info.as_ref().is_some_and(|info| info.embedded_art),
+                    )
+                    .map(|_| state.config.art_url(&file.relative_path))
+                    .as_deref(),
+            ),
+            Object::Root => container_element(
+                ROOT_ID,
+                "-1",
+                &state.config.friendly_name,
+                STORAGE_FOLDER,
+                self.child_count(object),
+                None,
+            ),
+            Object::Folder(path) => container_element(
+                &object.id(),
+                &object.parent_id(),
+                &self.title(object),
+                STORAGE_FOLDER,
+                self.child_count(object),
+                state
+                    .library
+                    .art()
+                    .folder_source(&Path::new(&state.config.media_directory).join(path))
+                    .map(|_| state.config.art_url(path))
+                    .as_deref(),
+            ),
+            Object::Playlist(_) => container_element(
+                &object.id(),
+                &object.parent_id(),
+                &self.title(object),
+                PLAYLIST_CONTAINER,
+                self.child_count(object),
+                None,
+            ),
+            _ => container_element(
+                &object.id(),
+                &object.parent_id(),
+                &self.title(object),
+                STORAGE_FOLDER,
+                self.child_count(object),
+                None,
+            ),
+        }
+    }
+}
+
+/// Reads the StartingIndex/RequestedCount pair shared by Browse and Search.
+/// RequestedCount 0 means "everything from StartingIndex on".
+fn paging(body: &str) -> (usize, usize) {
+    let starting_index: usize = extract_tag(body, "StartingIndex")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0);
+    let requested_count: usize = extract_tag(body, "RequestedCount")
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&count| count > 0)
+        .unwrap_or(usize::MAX);
+    (starting_index, requested_count)
+}
+
+/// Renders one page of `objects` into Browse/Search output arguments.
+fn result_page(
+    catalog: &Catalog,
+    objects: &[Object],
+    (starting_index, requested_count): (usize, usize),
+    state: &ServerState,
+) -> String {
+    let page: Vec<String> = objects
+        .iter()
+        .skip(starting_index)
+        .take(requested_count)
+        .map(|object| catalog.render(object, state))
+        .collect();
+    result_arguments(&wrap_didl(&page.concat()), page.len(), objects.len(), state)
+}
+
+fn result_arguments(didl: &str, returned: usize, total: usize, state: &ServerState) -> String {
+    format!(
+        "<Result>{}</Result>\
+<NumberReturned>{}</NumberReturned>\
+<TotalMatches>{}</TotalMatches>\
+<UpdateID>{}</UpdateID>",
+        xml_escape(didl),
+        returned,
+        total,
+        state.system_update_id()
+    )
+}
+
+fn browse(body: &str, state: &ServerState) -> Result<String, UpnpError> {
+    let object_id = extract_tag(body, "ObjectID").ok_or(UpnpError::INVALID_ARGS)?;
+    let browse_flag = extract_tag(body, "BrowseFlag").ok_or(UpnpError::INVALID_ARGS)?;
+
+    let files = state.media_files();
+    let playlists = state.library.playlists();
+    let catalog = Catalog::new(&files, &playlists);
+    let object = catalog.find(&object_id).ok_or(UpnpError::NO_SUCH_OBJECT)?;
+
+    match browse_flag.as_str() {
+        "BrowseMetadata" => Ok(result_arguments(
+            &wrap_didl(&catalog.render(&object, state)),
+            1,
+            1,
+            state,
+        )),
+        "BrowseDirectChildren" => {
+            if matches!(object, Object::Item { .. }) {
+                return Err(UpnpError::NO_SUCH_OBJECT);
+            }
+            let children = catalog.children(&object);
+            Ok(result_page(&catalog, &children, paging(body), state))
+        }
+        _ => Err(UpnpError::INVALID_ARGS),
+    }
+}
+
+fn search(body: &str, state: &ServerState) -> Result<String, UpnpError> {
+    let container_id = extract_tag(body, "ContainerID").ok_or(UpnpError::INVALID_ARGS)?;
+    let criteria = extract_tag(body, "SearchCriteria").unwrap_or_default();
+    let criteria = search::parse(&criteria).map_err(|_| UpnpError::INVALID_SEARCH_CRITERIA)?;
+    let searchable: Vec<&str> = SEARCH_CAPABILITIES.split(',').collect();
+    criteria
+        .check_properties(&searchable)
+        .map_err(|_| UpnpError::INVALID_SEARCH_CRITERIA)?;
+
+    let files = state.media_files();
+    let playlists = state.library.playlists();
+    let catalog = Catalog::new(&files, &playlists);
+    let container = match catalog.find(&container_id) {
+        Some(Object::Item { .. }) | None => return Err(UpnpError::NO_SUCH_CONTAINER),
+        Some(container) => container,
+    };
+
+    let matches: Vec<Object> = catalog
+        .descendants(&container)
+        .into_iter()
+        .filter(|object| criteria.matches(&|name| catalog.property(object, name)))
+        .collect();
+    Ok(result_page(&catalog, &matches, paging(body), state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::formats::Format;
+
+    fn file(relative_path: &str, class: MediaClass) -> MediaFile {
+        MediaFile {
+            name: relative_path.rsplit('/').next().unwrap().to_string(),
+            path: format!("/media/{}", relative_path),
+            relative_path: relative_path.to_string(),
+            modified: 0,
+            size: 0,
+            info: None,
+            subtitles: Vec::new(),
+            format: Format {
+                class,
+                ..Format::default()
+            },
+        }
+    }
+
+    #[test]
+    fn child_counts_match_the_children() {
+        let files = vec![
+            file("a/b/one.mkv", MediaClass::Video),
+            file("a/two.mp3", MediaClass::Audio),
+            file("a/z/three.MP3", MediaClass::Audio),
+            file("top.jpg", MediaClass::Image),
+        ];
+        let playlists = vec![Playlist {
+            relative_path: "a/list.m3u".to_string(),
+            title: "list".to_string(),
+            entries: vec!["a/two.mp3".to_string(), "gone.mp3".to_string()],
+        }];
+        let catalog = Catalog::new(&files, &playlists);
+        let mut containers = vec![Object::Root];
+        while let Some(container) = containers.pop() {
+            let children = catalog.children(&container);
+            assert_eq!(
+                catalog.child_count(&container),
+                children.len(),
+                "{}",
+                container.id()
+            );
+            containers.extend(children);
+        }
+        assert_eq!(catalog.child_count(&Object::Folder("a")), 3);
+    }
+
+    #[test]
+    fn items_are_found_by_id() {
+        let files = vec![
+            file("a/one.mkv", MediaClass::Video),
+            file("b.mp3", MediaClass::Audio),
+        ];
+        let catalog = Catalog::new(&files, &[]);
+        let item = catalog.find("folders/a/one.mkv").unwrap();
+        assert_eq!(item.parent_id(), "folders/a");
+        assert!(matches!(
+            catalog.find("folders/a"),
+            Some(Object::Folder("a"))
+        ));
+        assert_eq!(
+            catalog.find("types/mp3/b.mp3").unwrap().id(),
+            "types/mp3/b.mp3"
+        );
+        assert!(catalog.find("types/mkv/b.mp3").is_none());
+        assert!(catalog.find("folders/missing.mkv").is_none());
+        assert!(catalog.find("music/a/one.mkv").is_none());
+    }
+}
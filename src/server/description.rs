@@ -0,0 +1,263 @@
+use crate::config::Config;
+use crate::server::{connection_manager, content_directory};
+use crate::soap::xml_escape;
+
+pub const DESCRIPTION_PATH: &str = "/description.xml";
+pub const MEDIA_SERVER: &str = "urn:schemas-upnp-org:device:MediaServer:1";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+pub struct Argument {
+    pub name: &'static str,
+    pub direction: Direction,
+    pub related_state_variable: &'static str,
+}
+
+impl Argument {
+    pub const fn input(name: &'static str, related_state_variable: &'static str) -> Self {
+        Self {
+            name,
+            direction: Direction::In,
+            related_state_variable,
+        }
+    }
+
+    pub const fn output(name: &'static str, related_state_variable: &'static str) -> Self {
+        Self {
+            name,
+            direction: Direction::Out,
+            related_state_variable,
+        }
+    }
+}
+
+pub struct Action {
+    pub name: &'static str,
+    pub arguments: &'static [Argument],
+}
+
+pub struct StateVariable {
+    pub name: &'static str,
+    pub data_type: &'static str,
+    pub send_events: bool,
+    pub allowed_values: &'static [&'static str],
+}
+
+impl StateVariable {
+    pub const fn new(name: &'static str, data_type: &'static str, send_events: bool) -> Self {
+        Self {
+            name,
+            data_type,
+            send_events,
+            allowed_values: &[],
+        }
+    }
+
+    pub const fn allowed(mut self, allowed_values: &'static [&'static str]) -> Self {
+        self.allowed_values = allowed_values;
+        self
+    }
+}
+
+/// Static description of one UPnP service RustCast implements: where it
+/// lives on the HTTP server and what goes in its SCPD document.
+pub struct ServiceSpec {
+    pub service_type: &'static str,
+    pub service_id: &'static str,
+    pub scpd_path: &'static str,
+    pub control_path: &'static str,
+    pub event_path: &'static str,
+    pub actions: &'static [Action],
+    pub state_variables: &'static [StateVariable],
+}
+
+/// Every service listed in the device description, in advertisement order.
+pub const SERVICES: &[&ServiceSpec] = &[&content_directory::SERVICE, &connection_manager::SERVICE];
+
+pub struct Icon {
+    pub path: &'static str,
+    pub mime_type: &'static str,
+    pub size: u32,
+    pub bytes: &'static [u8],
+}
+
+/// Device icons shown by renderers next to the friendly name.
+pub const ICONS: &[Icon] = &[
+    Icon {
+        path: "/icons/icon-120.png",
+        mime_type: "image/png",
+        size: 120,
+        bytes: include_bytes!("../../assets/icon-120.png"),
+    },
+    Icon {
+        path: "/icons/icon-48.png",
+        mime_type: "image/png",
+        size: 48,
+        bytes: include_bytes!("../../assets/icon-48.png"),
+    },
+];
+
+/// Builds the root device description served at `/description.xml`.
+pub fn device_description(config: &Config) -> String {
+    let icons: String = ICONS
+        .iter()
+        .map(|icon| {
+            format!(
+                "
+                <icon>
+                    <mimetype>{}</mimetype>
+                    <width>{}</width>
+                    <height>{}</height>
+                    <depth>24</depth>
+                    <url>{}</url>
+                </icon>",
+                icon.mime_type, icon.size, icon.size, icon.path
+            )
+        })
+        .collect();
+
+    let services: String = SERVICES
+        .iter()
+        .map(|service| {
+            format!(
+                "
+                <service>
+                    <serviceType>{}</serviceType>
+                    <serviceId>{}</serviceId>
+                    <SCPDURL>{}</SCPDURL>
+                    <controlURL>{}</controlURL>
+                    <eventSubURL>{}</eventSubURL>
+                </service>",
+                service.service_type,
+                service.service_id,
+                service.scpd_path,
+                service.control_path,
+                service.event_path
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0"?>
+    <root xmlns="urn:schemas-upnp-org:device-1-0" xmlns:dlna="urn:schemas-dlna-org:device-1-0">
+        <specVersion>
+            <major>1</major>
+            <minor>0</minor>
+        </specVersion>
+        <URLBase>{base}</URLBase>
+        <device>
+            <deviceType>{device_type}</deviceType>
+            <dlna:X_DLNADOC>DMS-1.50</dlna:X_DLNADOC>
+            <friendlyName>{name}</friendlyName>
+            <manufacturer>Understake</manufacturer>
+            <manufacturerURL>https://github.com/celoficial</manufacturerURL>
+            <modelName>DLNA Server v1</modelName>
+            <modelDescription>A Rust-based DLNA Media Server</modelDescription>
+            <modelURL>https://github.com/celoficial/RustCast</modelURL>
+            <UDN>{udn}</UDN>
+            <iconList>{icons}
+            </iconList>
+            <serviceList>{services}
+            </serviceList>
+            <presentationURL>{base}/media</presentationURL>
+        </device>
+    </root>"#,
+        base = config.base_url(),
+        device_type = MEDIA_SERVER,
+        name = xml_escape(&config.friendly_name),
+        udn = config.udn,
+        icons = icons,
+        services = services,
+    )
+}
+
+/// Builds the SCPD document listing a service's actions and state variables.
+pub fn scpd(service: &ServiceSpec) -> String {
+    let actions: String = service
+        .actions
+        .iter()
+        .map(|action| {
+            let arguments: String = action
+                .arguments
+                .iter()
+                .map(|argument| {
+                    format!(
+                        "
+                <argument>
+                    <name>{}</name>
+                    <direction>{}</direction>
+                    <relatedStateVariable>{}</relatedStateVariable>
+                </argument>",
+                        argument.name,
+                        match argument.direction {
+                            Direction::In => "in",
+                            Direction::Out => "out",
+                        },
+                        argument.related_state_variable
+                    )
+                })
+                .collect();
+            format!(
+                "
+        <action>
+            <name>{}</name>
+            <argumentList>{}
+            </argumentList>
+        </action>",
+                action.name, arguments
+            )
+        })
+        .collect();
+
+    let state_variables: String = service
+        .state_variables
+        .iter()
+        .map(|variable| {
+            let allowed = if variable.allowed_values.is_empty() {
+                String::new()
+            } else {
+                let values: String = variable
+                    .allowed_values
+                    .iter()
+                    .map(|value| {
+                        format!("\n                <allowedValue>{}</allowedValue>", value)
+                    })
+                    .collect();
+                format!(
+                    "\n            <allowedValueList>{}\n            </allowedValueList>",
+                    values
+                )
+            };
+            format!(
+                "
+        <stateVariable sendEvents=\"{}\">
+            <name>{}</name>
+            <dataType>{}</dataType>{}
+        </stateVariable>",
+                if variable.send_events { "yes" } else { "no" },
+                variable.name,
+                variable.data_type,
+                allowed
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0">
+    <specVersion>
+        <major>1</major>
+        <minor>0</minor>
+    </specVersion>
+    <actionList>{}
+    </actionList>
+    <serviceStateTable>{}
+    </serviceStateTable>
+</scpd>"#,
+        actions, state_variables
+    )
+}
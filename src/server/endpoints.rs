@@ -1,16 +1,33 @@
 use futures::stream;
 use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full, StreamBody};
 use hyper::body::{Bytes, Frame, Incoming};
-use hyper::{header, Request, Response, StatusCode};
+use hyper::{header, HeaderMap, Method, Request, Response, StatusCode};
 use serde_json::json;
 use std::convert::Infallible;
 use std::io::SeekFrom;
+use std::net::SocketAddr;
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use uuid::Uuid;
 
 use crate::config::Config;
-use crate::media::manager::get_mime_type;
-use crate::media::manager::list_media_files;
+use crate::dlna::metadata::{dlna_features, relay_features, thumbnail_features};
+use crate::media::art::{has_embedded, Thumbnail};
+use crate::media::formats;
+use crate::media::manager::{mtime_secs, MediaClass, MediaFile};
+use crate::media::seek::{format_npt, parse_npt, SeekIndex};
+use crate::media::subtitle::{self, SubtitleFormat};
+use crate::media::views::{view_files, View};
+use crate::server::connection_manager::{self, CONNECTION_MANAGER};
+use crate::server::content_directory::{self, CONTENT_DIRECTORY};
+use crate::server::description::{
+    device_description, scpd, ServiceSpec, DESCRIPTION_PATH, ICONS, SERVICES,
+};
+use crate::server::eventing::SubscribeError;
+use crate::server::relay::IDLE_TIMEOUT;
+use crate::server::state::ServerState;
+use crate::soap::{build_fault, build_response, parse_action_header, UpnpError};
 
 /// Size of each chunk read from disk and sent over the network.
 /// 256 KB balances disk I/O efficiency with renderer buffer granularity
@@ -22,11 +39,17 @@ const STREAM_CHUNK_SIZE: usize = 256 * 1024;
 /// latency spikes without wasting memory.
 const READ_AHEAD_SLOTS: usize = 8;
 
-/// DLNA content features header value.
-/// DLNA.ORG_OP=01 means byte-range seeks supported, time-seek not supported.
-/// DLNA.ORG_FLAGS bits advertise streaming-transfer-mode and related support.
-const DLNA_CONTENT_FEATURES: &str =
-    "DLNA.ORG_OP=01;DLNA.ORG_FLAGS=01700000000000000000000000000000";
+/// Most ranges served from one request after merging. Players ask for a
+/// handful at most (e.g. the MP4 `moov` box plus a data region); beyond
+/// this the Range header is ignored and the whole file is sent.
+const MAX_RANGES: usize = 16;
+
+/// Largest subtitle file the `/subtitle` endpoint converts. Real ones are a
+/// few hundred KB at most.
+const MAX_SUBTITLE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// DLNA time-based seek request/response header.
+const TIME_SEEK_RANGE: &str = "TimeSeekRange.dlna.org";
 const SERVER_HEADER: &str = "RustCast/0.1 DLNA/1.5 UPnP/1.0";
 
 type ResponseBody = BoxBody<Bytes, Infallible>;
@@ -41,16 +64,52 @@ fn empty_body() -> ResponseBody {
 
 pub async fn handle_request(
     req: Request<Incoming>,
-    config: &Config,
+    peer_addr: SocketAddr,
+    state: &ServerState,
 ) -> Result<Response<ResponseBody>, Infallible> {
+    let config = &state.config;
     let uri_path = req.uri().path().to_string();
 
     let response = match uri_path.as_str() {
-        "/description.xml" => handle_description_request(config),
-        "/media" => handle_media_list_request(config),
-        _ => {
-            if let Some(media_name) = uri_path.strip_prefix("/media/") {
-                handle_media_file_request(&req, media_name, config).await
+        DESCRIPTION_PATH => handle_description_request(config),
+        "/media" => handle_media_list_request(req.uri().query(), state),
+        path => {
+            if let Some(service) = SERVICES.iter().find(|s| s.scpd_path == path) {
+                respond_xml(scpd(service))
+            } else if let Some(service) = SERVICES.iter().find(|s| s.control_path == path) {
+                handle_control_request(req, service.service_type, state).await
+            } else if let Some(service) = SERVICES.iter().find(|s| s.event_path == path) {
+                handle_event_request(&req, service, state)
+            } else if let Some(icon) = ICONS.iter().find(|i| i.path == path) {
+                Response::builder()
+                    .header("Content-Type", icon.mime_type)
+                    .header("Content-Length", icon.bytes.len().to_string())
+                    .body(full_body(icon.bytes))
+                    .unwrap()
+            } else if let Some(art_name) = path.strip_prefix("/art/") {
+                if req.method() == Method::GET || req.method() == Method::HEAD {
+                    handle_art_request(&req, art_name, state).await
+                } else {
+                    respond_method_not_allowed()
+                }
+            } else if let Some(subtitle_name) = path.strip_prefix("/subtitle/") {
+                if req.method() == Method::GET || req.method() == Method::HEAD {
+                    handle_subtitle_request(&req, subtitle_name, state).await
+                } else {
+                    respond_method_not_allowed()
+                }
+            } else if let Some(relay_id) = path.strip_prefix("/proxy/") {
+                if req.method() == Method::GET || req.method() == Method::HEAD {
+                    handle_proxy_request(&req, peer_addr, relay_id, state).await
+                } else {
+                    respond_method_not_allowed()
+                }
+            } else if let Some(media_name) = path.strip_prefix("/media/") {
+                if req.method() == Method::GET || req.method() == Method::HEAD {
+                    handle_media_file_request(&req, peer_addr, media_name, state).await
+                } else {
+                    respond_method_not_allowed()
+                }
             } else {
                 respond_not_found()
             }
@@ -61,28 +120,63 @@ pub async fn handle_request(
 }
 
 fn handle_description_request(config: &Config) -> Response<ResponseBody> {
-    let xml = format!(
-        r#"<?xml version="1.0"?>
-    <root xmlns="urn:schemas-upnp-org:device-1-0">
-        <specVersion>
-            <major>1</major>
-            <minor>0</minor>
-        </specVersion>
-        <device>
-            <deviceType>urn:schemas-upnp-org:device:MediaServer:1</deviceType>
-            <friendlyName>{}</friendlyName>
-            <manufacturer>Understake</manufacturer>
-            <manufacturerURL>https://github.com/celoficial</manufacturerURL>
-            <modelName>DLNA Server v1</modelName>
-            <modelDescription>A Rust-based DLNA Media Server</modelDescription>
-            <modelURL>https://github.com/celoficial/RustCast</modelURL>
-            <UDN>{}</UDN>
-        </device>
-    </root>"#,
-        config.friendly_name, config.udn
-    );
+    Response::builder()
+        .header("Content-Type", "text/xml; charset=utf-8")
+        .header("EXT", "")
+        .header("Server", SERVER_HEADER)
+        .body(full_body(device_description(config)))
+        .unwrap()
+}
+
+/// Handles a SOAP action POSTed to one of the server's UPnP control URLs.
+/// Successful actions return 200 with the action response envelope; UPnP
+/// errors return 500 with a SOAP fault, as the UPnP control spec requires.
+async fn handle_control_request(
+    req: Request<Incoming>,
+    service_urn: &str,
+    state: &ServerState,
+) -> Response<ResponseBody> {
+    if req.method() != Method::POST {
+        return respond_method_not_allowed();
+    }
+
+    let action = req
+        .headers()
+        .get("SOAPACTION")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_action_header);
+    let (action_urn, action) = match action {
+        Some(parsed) => parsed,
+        None => return respond_bad_request(),
+    };
+
+    let body = match req.into_body().collect().await {
+        Ok(collected) => String::from_utf8_lossy(&collected.to_bytes()).into_owned(),
+        Err(_) => return respond_bad_request(),
+    };
+
+    let result = if action_urn != service_urn {
+        Err(UpnpError::INVALID_ACTION)
+    } else {
+        match service_urn {
+            CONTENT_DIRECTORY => content_directory::handle_action(&action, &body, state),
+            CONNECTION_MANAGER => {
+                connection_manager::handle_action(&action, &body, &state.config.formats)
+            }
+            _ => Err(UpnpError::INVALID_ACTION),
+        }
+    };
+
+    let (status, xml) = match result {
+        Ok(params) => (
+            StatusCode::OK,
+            build_response(service_urn, &action, &params),
+        ),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, build_fault(error)),
+    };
 
     Response::builder()
+        .status(status)
         .header("Content-Type", "text/xml; charset=utf-8")
         .header("EXT", "")
         .header("Server", SERVER_HEADER)
@@ -90,9 +184,106 @@ fn handle_description_request(config: &Config) -> Response<ResponseBody> {
         .unwrap()
 }
 
-fn handle_media_list_request(config: &Config) -> Response<ResponseBody> {
-    let media_files = list_media_files(&config.media_directory);
-    let json = json!(media_files);
+/// Handles GENA SUBSCRIBE (new or renewal) and UNSUBSCRIBE on an event URL.
+fn handle_event_request(
+    req: &Request<Incoming>,
+    service: &ServiceSpec,
+    state: &ServerState,
+) -> Response<ResponseBody> {
+    let header_str = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let sid = header_str("SID");
+    let callback = header_str("CALLBACK");
+    let nt = header_str("NT");
+    let timeout = header_str("TIMEOUT");
+    let subscriptions = &state.subscriptions;
+
+    let result = match (req.method().as_str(), sid) {
+        ("SUBSCRIBE", Some(_)) if callback.is_some() || nt.is_some() => {
+            Err(SubscribeError::IncompatibleHeaders)
+        }
+        ("SUBSCRIBE", Some(sid)) => subscriptions
+            .renew(service, &sid, timeout.as_deref())
+            .map(|granted| (sid, granted)),
+        ("SUBSCRIBE", None) => match (callback, nt.as_deref()) {
+            (Some(callback), Some("upnp:event")) => {
+                let subscribed = subscriptions.subscribe(service, &callback, timeout.as_deref());
+                if let Ok((sid, _)) = &subscribed {
+                    subscriptions.send_initial_event(sid, evented_state(service, state));
+                }
+                subscribed
+            }
+            _ => Err(SubscribeError::PreconditionFailed),
+        },
+        ("UNSUBSCRIBE", Some(_)) if callback.is_some() || nt.is_some() => {
+            Err(SubscribeError::IncompatibleHeaders)
+        }
+        ("UNSUBSCRIBE", Some(sid)) => {
+            return match subscriptions.unsubscribe(service, &sid) {
+                Ok(()) => Response::builder().body(empty_body()).unwrap(),
+                Err(_) => respond_precondition_failed(),
+            };
+        }
+        ("UNSUBSCRIBE", None) => Err(SubscribeError::PreconditionFailed),
+        _ => return respond_method_not_allowed(),
+    };
+
+    match result {
+        Ok((sid, granted)) => Response::builder()
+            .header("SID", sid)
+            .header("TIMEOUT", format!("Second-{}", granted))
+            .header("Server", SERVER_HEADER)
+            .header("Content-Length", "0")
+            .body(empty_body())
+            .unwrap(),
+        Err(SubscribeError::IncompatibleHeaders) => respond_bad_request(),
+        Err(SubscribeError::PreconditionFailed) => respond_precondition_failed(),
+    }
+}
+
+/// Current values of a service's evented state variables, sent as the
+/// initial event to new subscribers.
+fn evented_state(service: &ServiceSpec, state: &ServerState) -> Vec<(&'static str, String)> {
+    match service.service_type {
+        CONTENT_DIRECTORY => content_directory::evented_state(state.system_update_id()),
+        CONNECTION_MANAGER => connection_manager::evented_state(&state.config.formats),
+        _ => Vec::new(),
+    }
+}
+
+fn respond_xml(xml: String) -> Response<ResponseBody> {
+    Response::builder()
+        .header("Content-Type", "text/xml; charset=utf-8")
+        .header("Server", SERVER_HEADER)
+        .body(full_body(xml))
+        .unwrap()
+}
+
+/// Lists the library as JSON. `?view=<id>` (e.g. `view=recent`) returns the
+/// files of one virtual view instead of the plain path-ordered list.
+fn handle_media_list_request(query: Option<&str>, state: &ServerState) -> Response<ResponseBody> {
+    let view_id = query
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("view="));
+    let view = match view_id {
+        None => View::Folders,
+        Some(id) => match View::from_id(id) {
+            Some(view) => view,
+            None => return respond_bad_request(),
+        },
+    };
+
+    let media_files = state.media_files();
+    let files: Vec<&MediaFile> = view_files(view, &media_files)
+        .into_iter()
+        .map(|i| &media_files[i])
+        .collect();
+    let json = json!(files);
 
     Response::builder()
         .header("Content-Type", "application/json")
@@ -100,36 +291,385 @@ fn handle_media_list_request(config: &Config) -> Response<ResponseBody> {
         .unwrap()
 }
 
-/// Parses a Range header like "bytes=X-Y" or "bytes=X-".
-/// Returns (start, end) clamped to [0, file_size - 1], or None if invalid.
-fn parse_range(range_str: &str, file_size: u64) -> Option<(u64, u64)> {
+/// Serves the cover art of a media file or folder as a DLNA thumbnail, in
+/// the profile named by `?profile=` (JPEG_TN when absent).
+async fn handle_art_request(
+    req: &Request<Incoming>,
+    art_name: &str,
+    state: &ServerState,
+) -> Response<ResponseBody> {
+    let profile_id = req
+        .uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("profile="));
+    let profile = match profile_id {
+        None => Thumbnail::Jpeg,
+        Some(id) => match Thumbnail::from_profile_id(id) {
+            Some(profile) => profile,
+            None => return respond_bad_request(),
+        },
+    };
+
+    // Same containment check as for media files.
+    let media_directory = &state.config.media_directory;
+    let path = Path::new(media_directory).join(art_name);
+    let (Ok(base_canonical), Ok(canonical)) = (
+        std::fs::canonicalize(media_directory),
+        std::fs::canonicalize(&path),
+    ) else {
+        return respond_not_found();
+    };
+    if !canonical.starts_with(&base_canonical) {
+        println!("Path traversal attempt blocked: {:?}", canonical);
+        return respond_bad_request();
+    }
+
+    // What the probe found, when the library has the file; otherwise the
+    // file is read to find out.
+    let files = state.media_files();
+    let embedded = files
+        .binary_search_by(|file| file.relative_path.as_str().cmp(art_name))
+        .ok()
+        .and_then(|pos| files[pos].info.as_ref())
+        .map(|info| info.embedded_art);
+
+    // Keyed like MediaFile::path so ContentDirectory shares the lookups.
+    let art = state.library.art().clone();
+    let thumbnail = tokio::task::spawn_blocking(move || {
+        let source = if path.is_dir() {
+            art.folder_source(&path)?
+        } else {
+            let embedded = embedded.unwrap_or_else(|| has_embedded(&path));
+            art.file_source(&path, embedded)?
+        };
+        art.thumbnail(&source, profile)
+    })
+    .await
+    .ok()
+    .flatten();
+    let Some(thumbnail) = thumbnail else {
+        return respond_not_found();
+    };
+
+    let response = Response::builder()
+        .header("Content-Type", profile.mime_type())
+        .header("Content-Length", thumbnail.len().to_string())
+        .header("transferMode.dlna.org", "Interactive")
+        .header("contentFeatures.dlna.org", thumbnail_features(profile))
+        .header("Server", SERVER_HEADER);
+    if req.method() == Method::HEAD {
+        return response.body(empty_body()).unwrap();
+    }
+    response
+        .body(full_body(Bytes::from(thumbnail.as_ref().clone())))
+        .unwrap()
+}
+
+/// Serves a sidecar subtitle as UTF-8, converted to the format named by
+/// `?format=` (the file's own format when absent) and shifted by
+/// `&offset=` milliseconds.
+async fn handle_subtitle_request(
+    req: &Request<Incoming>,
+    subtitle_name: &str,
+    state: &ServerState,
+) -> Response<ResponseBody> {
+    let Some(source) = Path::new(subtitle_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(SubtitleFormat::from_extension)
+    else {
+        return respond_not_found();
+    };
+    let query = req.uri().query().unwrap_or_default();
+    let param = |name: &str| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    };
+    let target = match param("format") {
+        None => source,
+        Some(name) => match SubtitleFormat::from_extension(name) {
+            Some(format) => format,
+            None => return respond_bad_request(),
+        },
+    };
+    let offset_ms = match param("offset").map(str::parse::<i64>) {
+        None => 0,
+        Some(Ok(offset)) => offset,
+        Some(Err(_)) => return respond_bad_request(),
+    };
+
+    // Same containment check as for media files.
+    let media_directory = &state.config.media_directory;
+    let path = Path::new(media_directory).join(subtitle_name);
+    let (Ok(base_canonical), Ok(canonical)) = (
+        std::fs::canonicalize(media_directory),
+        std::fs::canonicalize(&path),
+    ) else {
+        return respond_not_found();
+    };
+    if !canonical.starts_with(&base_canonical) {
+        println!("Path traversal attempt blocked: {:?}", canonical);
+        return respond_bad_request();
+    }
+
+    match tokio::fs::metadata(&canonical).await {
+        Ok(metadata) if metadata.is_file() && metadata.len() <= MAX_SUBTITLE_SIZE => {}
+        Ok(metadata) if metadata.is_file() => {
+            return respond_internal_server_error("Subtitle file too large")
+        }
+        _ => return respond_not_found(),
+    }
+    let Ok(bytes) = tokio::fs::read(&canonical).await else {
+        return respond_internal_server_error("Error reading subtitle file");
+    };
+    let text = subtitle::convert(&bytes, source, target, offset_ms);
+
+    let response = Response::builder()
+        .header(
+            "Content-Type",
+            format!("{}; charset=utf-8", target.mime_type()),
+        )
+        .header("Content-Length", text.len().to_string())
+        .header("transferMode.dlna.org", "Interactive")
+        .header("Server", SERVER_HEADER);
+    if req.method() == Method::HEAD {
+        return response.body(empty_body()).unwrap();
+    }
+    response.body(full_body(text)).unwrap()
+}
+
+/// Parses a Range header like "bytes=X-Y", "bytes=X-", "bytes=-N" or a
+/// comma-separated set of those ("bytes=0-99,500-599").
+/// Each range is clamped to [0, file_size - 1]; ranges that start past the
+/// end are dropped. The rest are sorted and overlapping or adjacent ones are
+/// merged. Returns None if the header is invalid or nothing is satisfiable.
+fn parse_ranges(range_str: &str, file_size: u64) -> Option<Vec<(u64, u64)>> {
     if file_size == 0 {
         return None;
     }
 
     let stripped = range_str.strip_prefix("bytes=")?;
-    let (start_str, end_str) = stripped.split_once('-')?;
+    let mut ranges = Vec::new();
+    for spec in stripped.split(',') {
+        let (start_str, end_str) = spec.trim().split_once('-')?;
 
-    let (start, end) = if start_str.is_empty() {
-        // Suffix range: bytes=-N means the last N bytes.
-        let suffix: u64 = end_str.parse().ok()?;
-        let start = file_size.saturating_sub(suffix);
-        (start, file_size - 1)
-    } else {
-        let start: u64 = start_str.parse().ok()?;
-        let end: u64 = if end_str.is_empty() {
-            file_size - 1
+        let (start, end) = if start_str.is_empty() {
+            // Suffix range: bytes=-N means the last N bytes.
+            let suffix: u64 = end_str.parse().ok()?;
+            if suffix == 0 {
+                continue;
+            }
+            let start = file_size.saturating_sub(suffix);
+            (start, file_size - 1)
         } else {
-            end_str.parse::<u64>().ok()?.min(file_size - 1)
+            let start: u64 = start_str.parse().ok()?;
+            let end: u64 = if end_str.is_empty() {
+                file_size - 1
+            } else {
+                let end: u64 = end_str.parse().ok()?;
+                if end < start {
+                    return None;
+                }
+                end.min(file_size - 1)
+            };
+            if start >= file_size {
+                continue;
+            }
+            (start, end)
         };
-        (start, end)
+        ranges.push((start, end));
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    if merged.is_empty() {
+        None
+    } else {
+        Some(merged)
+    }
+}
+
+/// The ranges to send for a Range header: none for the whole file, or None
+/// when nothing is satisfiable and a 416 is due.
+fn ranges_to_send(range_str: &str, file_size: u64) -> Option<Vec<(u64, u64)>> {
+    match parse_ranges(range_str, file_size)? {
+        // Past the cap the request is more likely abuse than a player;
+        // ignoring Range and sending the file is always allowed.
+        ranges if ranges.len() > MAX_RANGES => Some(Vec::new()),
+        ranges => Some(ranges),
+    }
+}
+
+/// A resolved `TimeSeekRange.dlna.org` request: the requested npt range
+/// widened to the enclosing seek points, and the bytes that cover it.
+struct TimeSeek {
+    start: f64,
+    end: f64,
+    duration: f64,
+    bytes: (u64, u64),
+}
+
+impl TimeSeek {
+    /// `npt=START-END/DURATION bytes=FIRST-LAST/SIZE`, as DLNA requires in
+    /// the response.
+    fn header_value(&self, file_size: u64) -> String {
+        format!(
+            "npt={}-{}/{} bytes={}-{}/{}",
+            format_npt(self.start),
+            format_npt(self.end),
+            format_npt(self.duration),
+            self.bytes.0,
+            self.bytes.1,
+            file_size
+        )
+    }
+}
+
+/// Parses `npt=START-[END]` and maps it onto the seek index. Playback starts
+/// at the last seek point at or before START; with an END the bytes stop
+/// just before the first seek point after it. Returns None when the range
+/// is malformed or starts past the end.
+fn time_seek_range(value: &str, index: &SeekIndex, file_size: u64) -> Option<TimeSeek> {
+    let (start, end) = value.trim().strip_prefix("npt=")?.split_once('-')?;
+    let start = parse_npt(start)?;
+    let end = match end.trim() {
+        "" => None,
+        end => Some(parse_npt(end)?).filter(|&end| end >= start),
     };
+    if start >= index.duration || file_size == 0 {
+        return None;
+    }
 
-    if start > end {
+    let (start_time, first_byte) = index.seek_point(start);
+    let (end_time, last_byte) = match end.and_then(|end| index.next_point(end)) {
+        Some((time, offset)) if offset > first_byte => (time, offset - 1),
+        _ => (index.duration, file_size - 1),
+    };
+    if first_byte > last_byte {
         return None;
     }
+    Some(TimeSeek {
+        start: start_time,
+        end: end_time,
+        duration: index.duration,
+        bytes: (first_byte, last_byte.min(file_size - 1)),
+    })
+}
 
-    Some((start, end))
+/// One piece of a media response body, produced in order by the disk reader.
+enum BodyPart {
+    /// Fixed bytes, e.g. a multipart boundary and part headers.
+    Literal(Bytes),
+    /// `length` bytes of the file starting at `start`.
+    File { start: u64, length: u64 },
+}
+
+impl BodyPart {
+    fn len(&self) -> u64 {
+        match self {
+            BodyPart::Literal(bytes) => bytes.len() as u64,
+            BodyPart::File { length, .. } => *length,
+        }
+    }
+}
+
+/// Lays out a multipart/byteranges body (RFC 9110 section 14.6): each range
+/// is preceded by a boundary and its own Content-Type and Content-Range.
+fn multipart_parts(
+    ranges: &[(u64, u64)],
+    file_size: u64,
+    mime_type: &str,
+    boundary: &str,
+) -> Vec<BodyPart> {
+    let mut parts = Vec::with_capacity(ranges.len() * 2 + 1);
+    for &(start, end) in ranges {
+        parts.push(BodyPart::Literal(Bytes::from(format!(
+            "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+            boundary, mime_type, start, end, file_size
+        ))));
+        parts.push(BodyPart::File {
+            start,
+            length: end - start + 1,
+        });
+    }
+    parts.push(BodyPart::Literal(Bytes::from(format!(
+        "\r\n--{}--\r\n",
+        boundary
+    ))));
+    parts
+}
+
+/// Strong validators for a media file. The ETag changes whenever the size or
+/// the mtime does, which is what replacing or re-encoding a file changes.
+struct Validators {
+    etag: String,
+    /// Whole seconds, the precision of HTTP dates.
+    last_modified: SystemTime,
+}
+
+impl Validators {
+    fn new(metadata: &std::fs::Metadata) -> Self {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            etag: format!("\"{:x}-{:x}\"", metadata.len(), mtime.as_nanos()),
+            last_modified: UNIX_EPOCH + Duration::from_secs(mtime.as_secs()),
+        }
+    }
+
+    fn last_modified_header(&self) -> String {
+        httpdate::fmt_http_date(self.last_modified)
+    }
+
+    /// True when the client's cached copy is current and a 304 is due.
+    /// If-None-Match takes precedence; If-Modified-Since is only consulted
+    /// without it, as RFC 9110 section 13.2.2 orders them.
+    fn not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(value) = header_str(headers, header::IF_NONE_MATCH) {
+            return value == "*"
+                || value
+                    .split(',')
+                    .map(|tag| tag.trim().trim_start_matches("W/"))
+                    .any(|tag| tag == self.etag);
+        }
+        header_str(headers, header::IF_MODIFIED_SINCE)
+            .and_then(|value| httpdate::parse_http_date(value).ok())
+            .is_some_and(|since| self.last_modified <= since)
+    }
+
+    /// Whether a Range request may be honoured: true without If-Range, or
+    /// when If-Range names the current version. Only strong comparison
+    /// applies here, so weak ETags never match.
+    fn if_range_matches(&self, headers: &HeaderMap) -> bool {
+        match header_str(headers, header::IF_RANGE) {
+            None => true,
+            Some(value) if value.starts_with('"') => value == self.etag,
+            Some(value) if value.starts_with("W/") => false,
+            Some(value) => {
+                httpdate::parse_http_date(value).is_ok_and(|date| date == self.last_modified)
+            }
+        }
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<&str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
 }
 
 /// Serves a media file with full Range support and smooth streaming.
@@ -144,9 +684,12 @@ fn parse_range(range_str: &str, file_size: u64) -> Option<(u64, u64)> {
 /// the disk reader stops on its next send attempt.
 async fn handle_media_file_request(
     req: &Request<Incoming>,
+    peer_addr: SocketAddr,
     media_name: &str,
-    config: &Config,
+    state: &ServerState,
 ) -> Response<ResponseBody> {
+    let config = &state.config;
+
     // Security: canonicalize both paths and verify the file is within
     // the configured media directory.
     let base_canonical = match std::fs::canonicalize(&config.media_directory) {
@@ -177,18 +720,77 @@ async fn handle_media_file_request(
         Err(_) => return respond_internal_server_error("Error reading file metadata"),
     };
     let file_size = metadata.len();
+    let validators = Validators::new(&metadata);
 
-    let mime_type = get_mime_type(canonical.to_str().unwrap_or(""));
+    if validators.not_modified(req.headers()) {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header("ETag", &validators.etag)
+            .header("Last-Modified", validators.last_modified_header())
+            .header("Server", SERVER_HEADER)
+            .body(empty_body())
+            .unwrap();
+    }
 
+    // The library entry carries the probed details the DLNA profile needs.
+    let files = state.media_files();
+    let media_file = files
+        .binary_search_by(|file| file.relative_path.as_str().cmp(media_name))
+        .ok()
+        .map(|pos| &files[pos]);
+    let mime_type = match media_file {
+        Some(file) => file.mime_type(),
+        None => state
+            .config
+            .formats
+            .mime_type(canonical.to_str().unwrap_or("")),
+    };
+
+    // Keyed like MediaFile::path.
+    let seek_indexes = state.seek_indexes.clone();
+    let index_path = raw_path.to_string_lossy().to_string();
+    let modified = mtime_secs(&metadata);
+    let seek_index =
+        tokio::task::spawn_blocking(move || seek_indexes.get(&index_path, file_size, modified))
+            .await
+            .ok()
+            .flatten();
+
+    // A time seek takes precedence over any byte Range in the same request.
+    let time_seek = match header_str(req.headers(), TIME_SEEK_RANGE) {
+        Some(value) => {
+            let Some(index) = &seek_index else {
+                return Response::builder()
+                    .status(StatusCode::NOT_ACCEPTABLE)
+                    .body(full_body("Time seek not supported for this file"))
+                    .unwrap();
+            };
+            match time_seek_range(value, index, file_size) {
+                Some(seek) => Some(seek),
+                None => {
+                    return Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .body(empty_body())
+                        .unwrap();
+                }
+            }
+        }
+        None => None,
+    };
+
+    // A stale If-Range means the client's partial copy is of another version
+    // of the file: ignore the Range and send the whole thing.
     let range_header: Option<String> = req
         .headers()
         .get(header::RANGE)
         .and_then(|value| value.to_str().ok())
+        .filter(|_| validators.if_range_matches(req.headers()))
         .map(str::to_owned);
 
-    let (start, end, is_partial) = if let Some(range_str) = range_header.as_deref() {
-        match parse_range(range_str, file_size) {
-            Some((start, end)) => (start, end, true),
+    let ranges = match (&time_seek, range_header.as_deref()) {
+        (Some(seek), _) => vec![seek.bytes],
+        (None, Some(range_str)) => match ranges_to_send(range_str, file_size) {
+            Some(ranges) => ranges,
             None => {
                 return Response::builder()
                     .status(StatusCode::RANGE_NOT_SATISFIABLE)
@@ -196,43 +798,153 @@ async fn handle_media_file_request(
                     .body(empty_body())
                     .unwrap();
             }
+        },
+        (None, None) => Vec::new(),
+    };
+
+    let response = Response::builder()
+        .status(if ranges.is_empty() {
+            StatusCode::OK
+        } else {
+            StatusCode::PARTIAL_CONTENT
+        })
+        .header("Accept-Ranges", "bytes")
+        .header("ETag", &validators.etag)
+        .header("Last-Modified", validators.last_modified_header())
+        .header(
+            "transferMode.dlna.org",
+            if media_file.is_some_and(|file| file.class() == MediaClass::Image) {
+                "Interactive"
+            } else {
+                "Streaming"
+            },
+        )
+        .header(
+            "contentFeatures.dlna.org",
+            dlna_features(media_file, seek_index.is_some()),
+        )
+        .header("EXT", "")
+        .header("Server", SERVER_HEADER)
+        .header(
+            "Content-Disposition",
+            format!("inline; filename=\"{}\"", media_name),
+        );
+    let response = match &time_seek {
+        Some(seek) => response.header(TIME_SEEK_RANGE, seek.header_value(file_size)),
+        None => response,
+    };
+
+    let (response, parts) = match ranges.as_slice() {
+        [] => (
+            response.header("Content-Type", mime_type),
+            vec![BodyPart::File {
+                start: 0,
+                length: file_size,
+            }],
+        ),
+        &[(start, end)] => (
+            response.header("Content-Type", mime_type).header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end, file_size),
+            ),
+            vec![BodyPart::File {
+                start,
+                length: end - start + 1,
+            }],
+        ),
+        ranges => {
+            let boundary = Uuid::new_v4().simple().to_string();
+            (
+                response.header(
+                    "Content-Type",
+                    format!("multipart/byteranges; boundary={}", boundary),
+                ),
+                multipart_parts(ranges, file_size, mime_type, &boundary),
+            )
         }
-    } else {
-        (0, file_size.saturating_sub(1), false)
     };
+    let content_length: u64 = parts.iter().map(BodyPart::len).sum();
+    let response = response.header("Content-Length", content_length.to_string());
 
-    let content_length = end.saturating_sub(start).saturating_add(1);
+    // HEAD gets the same headers without touching the file contents.
+    if req.method() == Method::HEAD {
+        return response.body(empty_body()).unwrap();
+    }
 
     let mut file = match tokio::fs::File::open(&canonical).await {
         Ok(file) => file,
         Err(_) => return respond_internal_server_error("Error opening file"),
     };
-    if start > 0 && file.seek(SeekFrom::Start(start)).await.is_err() {
-        return respond_internal_server_error("Error seeking file");
-    }
+
+    let mut throttle = state.throttle.stream(peer_addr.ip(), mime_type);
+    let range_desc = match (&time_seek, ranges.as_slice()) {
+        (Some(seek), _) => format!("npt={}-{}", format_npt(seek.start), format_npt(seek.end)),
+        (None, []) => "full".to_string(),
+        (None, ranges) => {
+            let ranges: Vec<String> = ranges
+                .iter()
+                .map(|(start, end)| format!("{}-{}", start, end))
+                .collect();
+            format!("bytes={}", ranges.join(","))
+        }
+    };
+    let session = state.sessions.open(
+        peer_addr,
+        media_name,
+        range_desc,
+        content_length,
+        header_str(req.headers(), header::USER_AGENT).unwrap_or_default(),
+    );
 
     // Bounded channel between disk reader and response stream.
     // When the stream slows down, the disk reader naturally back-pressures here.
     let (chunk_tx, chunk_rx) = tokio::sync::mpsc::channel::<Bytes>(READ_AHEAD_SLOTS);
 
-    // Disk reader task: reads STREAM_CHUNK_SIZE at a time and queues chunks.
-    // Exits on EOF, read error, or when the HTTP body has been dropped.
+    // Disk reader task: emits the body parts in order, reading file regions
+    // STREAM_CHUNK_SIZE at a time and pacing each chunk through the rate
+    // limits. Exits on EOF, read or seek error, when the HTTP body has been
+    // dropped, or when the session is killed. Exiting closes the session.
     tokio::spawn(async move {
-        let mut remaining = content_length;
-        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
-        while remaining > 0 {
-            let to_read = (STREAM_CHUNK_SIZE as u64).min(remaining) as usize;
-            match file.read(&mut buf[..to_read]).await {
-                Ok(0) => break,
-                Ok(read) => {
-                    remaining -= read as u64;
-                    let chunk = Bytes::copy_from_slice(&buf[..read]);
-                    if chunk_tx.send(chunk).await.is_err() {
-                        break;
+        let send_parts = async {
+            let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+            for part in parts {
+                let (start, length) = match part {
+                    BodyPart::Literal(bytes) => {
+                        let len = bytes.len();
+                        throttle.acquire(len).await;
+                        if chunk_tx.send(bytes).await.is_err() {
+                            return;
+                        }
+                        session.record(len);
+                        continue;
+                    }
+                    BodyPart::File { start, length } => (start, length),
+                };
+                if file.seek(SeekFrom::Start(start)).await.is_err() {
+                    return;
+                }
+                let mut remaining = length;
+                while remaining > 0 {
+                    let to_read = (STREAM_CHUNK_SIZE as u64).min(remaining) as usize;
+                    match file.read(&mut buf[..to_read]).await {
+                        Ok(0) => return,
+                        Ok(read) => {
+                            remaining -= read as u64;
+                            let chunk = Bytes::copy_from_slice(&buf[..read]);
+                            throttle.acquire(read).await;
+                            if chunk_tx.send(chunk).await.is_err() {
+                                return;
+                            }
+                            session.record(read);
+                        }
+                        Err(_) => return,
                     }
                 }
-                Err(_) => break,
             }
+        };
+        tokio::select! {
+            _ = send_parts => {}
+            _ = session.killed() => {}
         }
     });
 
@@ -246,36 +958,122 @@ async fn handle_media_file_request(
     });
     let body = StreamBody::new(body_stream).boxed();
 
-    // Build and return the response immediately so the renderer can start
+    // Return the response immediately so the renderer can start
     // buffering before the whole file is read from disk.
-    let response = Response::builder()
-        .status(if is_partial {
-            StatusCode::PARTIAL_CONTENT
-        } else {
-            StatusCode::OK
-        })
-        .header("Content-Type", mime_type)
-        .header("Content-Length", content_length.to_string())
-        .header("Accept-Ranges", "bytes")
-        .header("transferMode.dlna.org", "Streaming")
-        .header("contentFeatures.dlna.org", DLNA_CONTENT_FEATURES)
-        .header("EXT", "")
-        .header("Server", SERVER_HEADER)
-        .header(
-            "Content-Disposition",
-            format!("inline; filename=\"{}\"", media_name),
-        );
+    response.body(body).unwrap()
+}
 
-    let response = if is_partial {
-        response.header(
-            "Content-Range",
-            format!("bytes {}-{}/{}", start, end, file_size),
-        )
-    } else {
-        response
+/// Relays a remote resource registered by a URL cast (see `server::relay`).
+/// Range requests are forwarded and the origin's status, length and range
+/// headers passed back, with the DLNA headers local files get added. The
+/// body is streamed through the same rate limits and session registry as
+/// local files.
+async fn handle_proxy_request(
+    req: &Request<Incoming>,
+    peer_addr: SocketAddr,
+    relay_id: &str,
+    state: &ServerState,
+) -> Response<ResponseBody> {
+    let Some(relay) = relay_id.parse().ok().and_then(|id| state.relays.get(id)) else {
+        return respond_not_found();
     };
 
-    response.body(body).unwrap()
+    let mut request = state
+        .relays
+        .client()
+        .request(req.method().clone(), &relay.url);
+    for name in [header::RANGE, header::IF_RANGE] {
+        if let Some(value) = req.headers().get(&name) {
+            request = request.header(name, value);
+        }
+    }
+    let mut origin = match request.send().await {
+        Ok(origin) => origin,
+        Err(e) => {
+            println!("Relay of {} failed: {}", relay.url, e);
+            return Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(full_body("Bad Gateway"))
+                .unwrap();
+        }
+    };
+
+    let headers = origin.headers();
+    let mime_type = header_str(headers, header::CONTENT_TYPE)
+        .unwrap_or(&relay.mime_type)
+        .to_string();
+    let class = formats::class_of_mime(&mime_type);
+    let byte_seek = header_str(headers, header::ACCEPT_RANGES) == Some("bytes");
+    let mut response = Response::builder()
+        .status(origin.status())
+        .header("Content-Type", &mime_type)
+        .header(
+            "transferMode.dlna.org",
+            if class == MediaClass::Image {
+                "Interactive"
+            } else {
+                "Streaming"
+            },
+        )
+        .header("contentFeatures.dlna.org", relay_features(class, byte_seek))
+        .header("EXT", "")
+        .header("Server", SERVER_HEADER);
+    for name in [
+        header::CONTENT_LENGTH,
+        header::CONTENT_RANGE,
+        header::ACCEPT_RANGES,
+        header::ETAG,
+        header::LAST_MODIFIED,
+    ] {
+        if let Some(value) = headers.get(&name) {
+            response = response.header(name, value);
+        }
+    }
+
+    if req.method() == Method::HEAD || !origin.status().is_success() {
+        return response.body(empty_body()).unwrap();
+    }
+
+    let mut throttle = state.throttle.stream(peer_addr.ip(), &mime_type);
+    let session = state.sessions.open(
+        peer_addr,
+        &relay.url,
+        header_str(req.headers(), header::RANGE)
+            .unwrap_or("full")
+            .to_string(),
+        origin.content_length().unwrap_or(0),
+        header_str(req.headers(), header::USER_AGENT).unwrap_or_default(),
+    );
+
+    // Same shape as the disk reader of handle_media_file_request, with the
+    // origin's body as the source.
+    let (chunk_tx, chunk_rx) = tokio::sync::mpsc::channel::<Bytes>(READ_AHEAD_SLOTS);
+    tokio::spawn(async move {
+        let send_chunks = async {
+            // An origin that stalls would otherwise hold the session open
+            while let Ok(Ok(Some(chunk))) = tokio::time::timeout(IDLE_TIMEOUT, origin.chunk()).await
+            {
+                let len = chunk.len();
+                throttle.acquire(len).await;
+                if chunk_tx.send(chunk).await.is_err() {
+                    return;
+                }
+                session.record(len);
+            }
+        };
+        tokio::select! {
+            _ = send_chunks => {}
+            _ = session.killed() => {}
+        }
+    });
+
+    let body_stream = stream::unfold(chunk_rx, |mut chunk_rx| async move {
+        chunk_rx
+            .recv()
+            .await
+            .map(|chunk| (Ok::<Frame<Bytes>, Infallible>(Frame::data(chunk)), chunk_rx))
+    });
+    response.body(StreamBody::new(body_stream).boxed()).unwrap()
 }
 
 fn respond_not_found() -> Response<ResponseBody> {
@@ -292,9 +1090,174 @@ fn respond_bad_request() -> Response<ResponseBody> {
         .unwrap()
 }
 
+fn respond_precondition_failed() -> Response<ResponseBody> {
+    Response::builder()
+        .status(StatusCode::PRECONDITION_FAILED)
+        .body(full_body("Precondition Failed"))
+        .unwrap()
+}
+
+fn respond_method_not_allowed() -> Response<ResponseBody> {
+    Response::builder()
+        .status(StatusCode::METHOD_NOT_ALLOWED)
+        .body(full_body("Method Not Allowed"))
+        .unwrap()
+}
+
 fn respond_internal_server_error(message: &str) -> Response<ResponseBody> {
     Response::builder()
         .status(StatusCode::INTERNAL_SERVER_ERROR)
         .body(full_body(message.to_owned()))
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(parse_ranges("bytes=0-99", 1000), Some(vec![(0, 99)]));
+        assert_eq!(parse_ranges("bytes=500-", 1000), Some(vec![(500, 999)]));
+        assert_eq!(parse_ranges("bytes=-100", 1000), Some(vec![(900, 999)]));
+        // Clamped to the file
+        assert_eq!(parse_ranges("bytes=900-5000", 1000), Some(vec![(900, 999)]));
+        assert_eq!(parse_ranges("bytes=-5000", 1000), Some(vec![(0, 999)]));
+    }
+
+    #[test]
+    fn multiple_ranges_are_sorted_and_merged() {
+        assert_eq!(
+            parse_ranges("bytes=500-599,0-99", 1000),
+            Some(vec![(0, 99), (500, 599)])
+        );
+        // Overlapping and adjacent ranges become one
+        assert_eq!(
+            parse_ranges("bytes=0-99, 50-149, 150-199", 1000),
+            Some(vec![(0, 199)])
+        );
+        assert_eq!(
+            parse_ranges("bytes=0-9,-10", 1000),
+            Some(vec![(0, 9), (990, 999)])
+        );
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_dropped() {
+        assert_eq!(parse_ranges("bytes=1000-", 1000), None);
+        assert_eq!(parse_ranges("bytes=-0", 1000), None);
+        assert_eq!(
+            parse_ranges("bytes=2000-2999,0-9", 1000),
+            Some(vec![(0, 9)])
+        );
+        assert_eq!(parse_ranges("bytes=0-", 0), None);
+    }
+
+    #[test]
+    fn malformed_ranges_are_rejected() {
+        assert_eq!(parse_ranges("0-99", 1000), None);
+        assert_eq!(parse_ranges("items=0-99", 1000), None);
+        assert_eq!(parse_ranges("bytes=99-0", 1000), None);
+        assert_eq!(parse_ranges("bytes=a-b", 1000), None);
+        assert_eq!(parse_ranges("bytes=0-9,x", 1000), None);
+        assert_eq!(parse_ranges("bytes=18446744073709551616-", 1000), None);
+    }
+
+    #[test]
+    fn too_many_ranges_send_the_whole_file() {
+        let spec: Vec<String> = (0..=MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 10, i * 10))
+            .collect();
+        let header = format!("bytes={}", spec.join(","));
+        assert_eq!(
+            parse_ranges(&header, 1000).map(|r| r.len()),
+            Some(MAX_RANGES + 1)
+        );
+        assert_eq!(ranges_to_send(&header, 1000), Some(Vec::new()));
+
+        let header = format!("bytes={}", spec[..MAX_RANGES].join(","));
+        assert_eq!(
+            ranges_to_send(&header, 1000).map(|r| r.len()),
+            Some(MAX_RANGES)
+        );
+        assert_eq!(ranges_to_send("bytes=5000-", 1000), None);
+    }
+
+    fn validators() -> Validators {
+        Validators {
+            etag: "\"3e8-1\"".to_string(),
+            last_modified: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        }
+    }
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn if_range_needs_the_current_version() {
+        let validators = validators();
+        let date = validators.last_modified_header();
+        assert!(validators.if_range_matches(&HeaderMap::new()));
+        assert!(validators.if_range_matches(&headers(header::IF_RANGE, "\"3e8-1\"")));
+        assert!(validators.if_range_matches(&headers(header::IF_RANGE, &date)));
+        assert!(!validators.if_range_matches(&headers(header::IF_RANGE, "\"3e8-2\"")));
+        assert!(!validators.if_range_matches(&headers(header::IF_RANGE, "W/\"3e8-1\"")));
+        assert!(!validators
+            .if_range_matches(&headers(header::IF_RANGE, "Tue, 14 Nov 2023 22:13:21 GMT")));
+        assert!(!validators.if_range_matches(&headers(header::IF_RANGE, "garbage")));
+    }
+
+    #[test]
+    fn not_modified_prefers_if_none_match() {
+        let validators = validators();
+        let date = validators.last_modified_header();
+        assert!(validators.not_modified(&headers(header::IF_NONE_MATCH, "\"x\", W/\"3e8-1\"")));
+        assert!(validators.not_modified(&headers(header::IF_NONE_MATCH, "*")));
+        assert!(validators.not_modified(&headers(header::IF_MODIFIED_SINCE, &date)));
+
+        let mut both = headers(header::IF_NONE_MATCH, "\"other\"");
+        both.insert(header::IF_MODIFIED_SINCE, date.parse().unwrap());
+        assert!(!validators.not_modified(&both));
+    }
+
+    fn seek_index() -> SeekIndex {
+        SeekIndex::new(30.0, vec![(0.0, 100), (10.0, 1000), (20.0, 2000)]).unwrap()
+    }
+
+    #[test]
+    fn time_seek_widens_to_seek_points() {
+        let index = seek_index();
+        let seek = time_seek_range("npt=12.5-", &index, 3000).unwrap();
+        assert_eq!(
+            (seek.start, seek.end, seek.bytes),
+            (10.0, 30.0, (1000, 2999))
+        );
+        assert_eq!(
+            seek.header_value(3000),
+            "npt=0:00:10.000-0:00:30.000/0:00:30.000 bytes=1000-2999/3000"
+        );
+
+        let seek = time_seek_range("npt=0:00:01-0:00:15", &index, 3000).unwrap();
+        assert_eq!((seek.start, seek.end, seek.bytes), (0.0, 20.0, (100, 1999)));
+        // An end in the last segment runs to the end of the file
+        let seek = time_seek_range("npt=5-25", &index, 3000).unwrap();
+        assert_eq!((seek.end, seek.bytes), (30.0, (100, 2999)));
+        // An end before the start is ignored
+        let seek = time_seek_range("npt=20-10", &index, 3000).unwrap();
+        assert_eq!((seek.start, seek.bytes), (20.0, (2000, 2999)));
+    }
+
+    #[test]
+    fn unsatisfiable_time_seeks() {
+        let index = seek_index();
+        assert!(time_seek_range("npt=30-", &index, 3000).is_none());
+        assert!(time_seek_range("npt=abc-", &index, 3000).is_none());
+        assert!(time_seek_range("bytes=0-", &index, 3000).is_none());
+        assert!(time_seek_range("npt=5-", &index, 0).is_none());
+        // Seek points past the end of a file that was cut short
+        assert!(time_seek_range("npt=25-", &index, 1500).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use reqwest::{Client, Method};
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::server::description::ServiceSpec;
+use crate::soap::xml_escape;
+
+/// Subscription lifetime granted when the subscriber asks for more (or for
+/// "infinite"). Control points renew well before this runs out.
+const MAX_TIMEOUT_SECS: u64 = 1800;
+
+/// Floor on the granted lifetime so a tiny TIMEOUT does not cause churn.
+const MIN_TIMEOUT_SECS: u64 = 60;
+
+/// How long to wait for a subscriber to accept a NOTIFY before trying the
+/// next callback URL.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The initial event must arrive after the SUBSCRIBE response; this delay
+/// gives hyper time to flush it.
+const INITIAL_EVENT_DELAY: Duration = Duration::from_millis(200);
+
+struct Subscription {
+    event_path: &'static str,
+    callbacks: Vec<String>,
+    expires: Instant,
+    /// SEQ of the next event. 0 is reserved for the initial event and the
+    /// counter wraps back to 1, as GENA requires.
+    next_seq: u32,
+}
+
+/// Why a SUBSCRIBE/UNSUBSCRIBE request was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeError {
+    /// 400: SID combined with CALLBACK or NT.
+    IncompatibleHeaders,
+    /// 412: missing/invalid CALLBACK or NT, or unknown SID.
+    PreconditionFailed,
+}
+
+/// GENA subscriptions for every evented service on this server.
+pub struct Subscriptions {
+    table: Mutex<HashMap<String, Subscription>>,
+    client: Client,
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self {
+            table: Mutex::new(HashMap::new()),
+            client: Client::builder()
+                .timeout(NOTIFY_TIMEOUT)
+                .build()
+                .expect("failed to build GENA client"),
+        }
+    }
+
+    /// Creates a subscription and returns `(SID, granted timeout in seconds)`.
+    /// `callback_header` is the raw CALLBACK value: one or more `<url>`s.
+    pub fn subscribe(
+        &self,
+        service: &ServiceSpec,
+        callback_header: &str,
+        timeout_header: Option<&str>,
+    ) -> Result<(String, u64), SubscribeError> {
+        let callbacks = parse_callbacks(callback_header);
+        if callbacks.is_empty() {
+            return Err(SubscribeError::PreconditionFailed);
+        }
+
+        let timeout = granted_timeout(timeout_header);
+        let sid = format!("uuid:{}", Uuid::new_v4());
+        let mut table = self.table.lock().unwrap();
+        purge_expired(&mut table);
+        table.insert(
+            sid.clone(),
+            Subscription {
+                event_path: service.event_path,
+                callbacks,
+                expires: Instant::now() + Duration::from_secs(timeout),
+                next_seq: 1,
+            },
+        );
+        Ok((sid, timeout))
+    }
+
+    /// Extends an existing subscription and returns the granted timeout.
+    pub fn renew(
+        &self,
+        service: &ServiceSpec,
+        sid: &str,
+        timeout_header: Option<&str>,
+    ) -> Result<u64, SubscribeError> {
+        let timeout = granted_timeout(timeout_header);
+        let mut table = self.table.lock().unwrap();
+        purge_expired(&mut table);
+        match table.get_mut(sid) {
+            Some(subscription) if subscription.event_path == service.event_path => {
+                subscription.expires = Instant::now() + Duration::from_secs(timeout);
+                Ok(timeout)
+            }
+            _ => Err(SubscribeError::PreconditionFailed),
+        }
+    }
+
+    pub fn unsubscribe(&self, service: &ServiceSpec, sid: &str) -> Result<(), SubscribeError> {
+        let mut table = self.table.lock().unwrap();
+        match table.get(sid) {
+            Some(subscription) if subscription.event_path == service.event_path => {
+                table.remove(sid);
+                Ok(())
+            }
+            _ => Err(SubscribeError::PreconditionFailed),
+        }
+    }
+
+    /// Sends the initial event (SEQ 0) with every evented variable to a new
+    /// subscriber. Runs in the background so the SUBSCRIBE response goes first.
+    pub fn send_initial_event(&self, sid: &str, properties: Vec<(&'static str, String)>) {
+        let callbacks = match self.table.lock().unwrap().get(sid) {
+            Some(subscription) => subscription.callbacks.clone(),
+            None => return,
+        };
+        let client = self.client.clone();
+        let sid = sid.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(INITIAL_EVENT_DELAY).await;
+            deliver(&client, &callbacks, &sid, 0, &property_set(&properties)).await;
+        });
+    }
+
+    /// Notifies every live subscriber of a service that `properties` changed.
+    pub fn notify(&self, service: &ServiceSpec, properties: &[(&'static str, String)]) {
+        let body = property_set(properties);
+        let mut table = self.table.lock().unwrap();
+        purge_expired(&mut table);
+
+        for (sid, subscription) in table.iter_mut() {
+            if subscription.event_path != service.event_path {
+                continue;
+            }
+            let seq = subscription.next_seq;
+            subscription.next_seq = subscription.next_seq.checked_add(1).unwrap_or(1);
+
+            let client = self.client.clone();
+            let callbacks = subscription.callbacks.clone();
+            let sid = sid.clone();
+            let body = body.clone();
+            tokio::spawn(async move {
+                deliver(&client, &callbacks, &sid, seq, &body).await;
+            });
+        }
+    }
+}
+
+fn purge_expired(table: &mut HashMap<String, Subscription>) {
+    let now = Instant::now();
+    table.retain(|_, subscription| subscription.expires > now);
+}
+
+/// Parses `<http://a/cb><http://b/cb>` into its HTTP URLs.
+fn parse_callbacks(header: &str) -> Vec<String> {
+    header
+        .split('<')
+        .filter_map(|part| part.split_once('>').map(|(url, _)| url.trim()))
+        .filter(|url| url.starts_with("http://"))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Converts a `TIMEOUT: Second-N` header into the lifetime we grant.
+fn granted_timeout(header: Option<&str>) -> u64 {
+    header
+        .and_then(|value| value.trim().strip_prefix("Second-"))
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or(MAX_TIMEOUT_SECS)
+        .clamp(MIN_TIMEOUT_SECS, MAX_TIMEOUT_SECS)
+}
+
+fn property_set(properties: &[(&'static str, String)]) -> String {
+    let body: String = properties
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "<e:property><{0}>{1}</{0}></e:property>",
+                name,
+                xml_escape(value)
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\"?>\n\
+<e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\">{}</e:propertyset>",
+        body
+    )
+}
+
+/// Tries each callback URL in order until one accepts the NOTIFY.
+async fn deliver(client: &Client, callbacks: &[String], sid: &str, seq: u32, body: &str) {
+    let method = Method::from_bytes(b"NOTIFY").expect("NOTIFY is a valid method token");
+    for url in callbacks {
+        let result = client
+            .request(method.clone(), url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header("NT", "upnp:event")
+            .header("NTS", "upnp:propchange")
+            .header("SID", sid)
+            .header("SEQ", seq.to_string())
+            .body(body.to_owned())
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => eprintln!("GENA: {} rejected event: {}", url, response.status()),
+            Err(e) => eprintln!("GENA: failed to notify {}: {}", url, e),
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use crate::config::Config;
 use crate::server::endpoints::handle_request;
+use crate::server::state::ServerState;
 use hyper::body::Incoming;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
@@ -9,7 +9,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 
-pub async fn start_http_server(port: u16, config: Config) {
+pub async fn start_http_server(port: u16, state: Arc<ServerState>) {
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     println!("Starting HTTP server on port {}", port);
 
@@ -21,9 +21,6 @@ pub async fn start_http_server(port: u16, config: Config) {
         }
     };
 
-    // Share config across all accepted connections without cloning the payload.
-    let shared_config = Arc::new(config);
-
     loop {
         let (stream, peer_addr) = match listener.accept().await {
             Ok(connection) => connection,
@@ -38,13 +35,14 @@ pub async fn start_http_server(port: u16, config: Config) {
         }
 
         let io = TokioIo::new(stream);
-        let config = Arc::clone(&shared_config);
+        // Share state across all accepted connections without cloning the payload.
+        let state = Arc::clone(&state);
 
         tokio::spawn(async move {
-            // Clone the Arc per request so the handler can borrow config safely.
+            // Clone the Arc per request so the handler can borrow state safely.
             let service = service_fn(move |req: Request<Incoming>| {
-                let config = Arc::clone(&config);
-                async move { handle_request(req, &config).await }
+                let state = Arc::clone(&state);
+                async move { handle_request(req, peer_addr, &state).await }
             });
 
             if let Err(err) = http1::Builder::new().serve_connection(io, service).await {
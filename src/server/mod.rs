@@ -1,2 +1,10 @@
+pub mod connection_manager;
+pub mod content_directory;
+pub mod description;
 pub mod endpoints;
+pub mod eventing;
 pub mod http_server;
+pub mod relay;
+pub mod sessions;
+pub mod state;
+pub mod throttle;
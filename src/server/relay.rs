@@ -0,0 +1,79 @@
+//! Remote resources cast by URL and relayed through the `/proxy` endpoint.
+//!
+//! Renderers are picky about what they stream from: many want the DLNA
+//! headers RustCast sends for local files and reject anything else. A URL
+//! cast is therefore registered here and handed to the renderer as
+//! `/proxy/<id>`, which fetches the original and adds those headers. Only
+//! the URL being cast is relayed, so the endpoint is not an open proxy.
+//! Each cast gets a new ID and stopping it drops the URL, so the IDs of
+//! earlier casts answer 404.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::Client;
+
+/// How long to wait for the origin to accept a connection. There is no
+/// limit on the transfer itself: live feeds never end.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the origin may send nothing before the relay gives up on it.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One relayed resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relay {
+    pub url: String,
+    /// Sent when the origin gives no Content-Type.
+    pub mime_type: String,
+}
+
+pub struct Relays {
+    client: Client,
+    next_id: AtomicUsize,
+    /// The URL being cast and its ID, if any.
+    current: Mutex<Option<(usize, Relay)>>,
+}
+
+impl Relays {
+    pub fn new() -> Self {
+        Self {
+            client: Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .expect("failed to build relay client"),
+            next_id: AtomicUsize::new(0),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// HTTP client used to fetch relayed resources.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Registers the URL being cast, replacing the previous one, and
+    /// returns its new ID.
+    pub fn register(&self, url: &str, mime_type: &str) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let relay = Relay {
+            url: url.to_string(),
+            mime_type: mime_type.to_string(),
+        };
+        *self.current.lock().unwrap() = Some((id, relay));
+        id
+    }
+
+    pub fn get(&self, id: usize) -> Option<Relay> {
+        match &*self.current.lock().unwrap() {
+            Some((current, relay)) if *current == id => Some(relay.clone()),
+            _ => None,
+        }
+    }
+
+    /// Stops relaying the URL being cast, once playback has stopped.
+    pub fn clear(&self) {
+        *self.current.lock().unwrap() = None;
+    }
+}
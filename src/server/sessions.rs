@@ -0,0 +1,144 @@
+//! Registry of media streams in flight.
+//!
+//! Every media response with a body opens a session before its disk reader
+//! starts. The reader reports each chunk it hands to the connection, and
+//! the session disappears when the reader ends: on completion, on client
+//! disconnect, or when it is killed from the TUI.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tokio::sync::Notify;
+
+use crate::server::throttle::Meter;
+
+/// Snapshot of one stream, as returned by `Sessions::list`.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: u64,
+    pub peer: SocketAddr,
+    /// Path of the file relative to the media root.
+    pub file: String,
+    /// What is being sent: `full`, the byte ranges, or the time seek.
+    pub range: String,
+    /// Body length in bytes.
+    pub length: u64,
+    pub bytes_sent: u64,
+    /// Current throughput in bytes per second.
+    pub rate: u64,
+    pub started: Instant,
+    pub user_agent: String,
+}
+
+struct Entry {
+    session: Session,
+    meter: Meter,
+    kill: Arc<Notify>,
+}
+
+#[derive(Default)]
+pub struct Sessions {
+    next_id: AtomicU64,
+    entries: Mutex<BTreeMap<u64, Entry>>,
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a stream. It stays listed until the returned handle is
+    /// dropped, which the disk reader does when it exits.
+    pub fn open(
+        self: &Arc<Self>,
+        peer: SocketAddr,
+        file: &str,
+        range: String,
+        length: u64,
+        user_agent: &str,
+    ) -> SessionHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let session = Session {
+            id,
+            peer,
+            file: file.to_string(),
+            range,
+            length,
+            bytes_sent: 0,
+            rate: 0,
+            started: Instant::now(),
+            user_agent: user_agent.to_string(),
+        };
+        let kill = Arc::new(Notify::new());
+        self.entries.lock().unwrap().insert(
+            id,
+            Entry {
+                session,
+                meter: Meter::default(),
+                kill: Arc::clone(&kill),
+            },
+        );
+        SessionHandle {
+            sessions: Arc::clone(self),
+            id,
+            kill,
+        }
+    }
+
+    /// Open sessions, oldest first.
+    pub fn list(&self) -> Vec<Session> {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .values_mut()
+            .map(|entry| Session {
+                rate: entry.meter.rate(now),
+                ..entry.session.clone()
+            })
+            .collect()
+    }
+
+    /// Stops a stream: its disk reader exits and the client sees the
+    /// connection close mid-body. Returns false if it already ended.
+    pub fn kill(&self, id: u64) -> bool {
+        match self.entries.lock().unwrap().remove(&id) {
+            Some(entry) => {
+                entry.kill.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Handle held by a stream's disk reader; see `Sessions::open`.
+pub struct SessionHandle {
+    sessions: Arc<Sessions>,
+    id: u64,
+    kill: Arc<Notify>,
+}
+
+impl SessionHandle {
+    /// Resolves once the session has been killed.
+    pub async fn killed(&self) {
+        self.kill.notified().await;
+    }
+
+    /// Accounts for a chunk handed to the connection.
+    pub fn record(&self, bytes: usize) {
+        if let Some(entry) = self.sessions.entries.lock().unwrap().get_mut(&self.id) {
+            entry.session.bytes_sent += bytes as u64;
+            entry.meter.record(bytes, Instant::now());
+        }
+    }
+}
+
+impl Drop for SessionHandle {
+    fn drop(&mut self) {
+        self.sessions.entries.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::media::library::Library;
+use crate::media::manager::MediaFile;
+use crate::media::seek::SeekIndexes;
+use crate::server::content_directory;
+use crate::server::eventing::Subscriptions;
+use crate::server::relay::Relays;
+use crate::server::sessions::Sessions;
+use crate::server::throttle::Throttle;
+
+/// State shared by every HTTP connection for the lifetime of the server.
+pub struct ServerState {
+    pub config: Config,
+    pub subscriptions: Subscriptions,
+    pub library: Arc<Library>,
+    pub seek_indexes: SeekIndexes,
+    pub throttle: Arc<Throttle>,
+    pub sessions: Arc<Sessions>,
+    pub relays: Arc<Relays>,
+}
+
+impl ServerState {
+    pub fn new(config: Config, library: Arc<Library>) -> Self {
+        Self {
+            throttle: Arc::new(Throttle::new(config.rate_limits.clone())),
+            config,
+            subscriptions: Subscriptions::new(),
+            library,
+            seek_indexes: SeekIndexes::new(),
+            sessions: Arc::new(Sessions::new()),
+            relays: Arc::new(Relays::new()),
+        }
+    }
+
+    /// ContentDirectory SystemUpdateID: the library update counter, which
+    /// wraps like the ui4 it is sent as.
+    pub fn system_update_id(&self) -> u32 {
+        self.library.update_id() as u32
+    }
+
+    pub fn media_files(&self) -> Arc<Vec<MediaFile>> {
+        self.library.files()
+    }
+}
+
+/// Notifies ContentDirectory subscribers whenever the library changes.
+pub fn start_library_events(state: Arc<ServerState>) -> JoinHandle<()> {
+    let mut updates = state.library.subscribe();
+    tokio::spawn(async move {
+        while updates.changed().await.is_ok() {
+            state.subscriptions.notify(
+                &content_directory::SERVICE,
+                &content_directory::evented_state(state.system_update_id()),
+            );
+        }
+    })
+}
@@ -0,0 +1,403 @@
+//! Bandwidth limits for media streaming.
+//!
+//! Three kinds of cap apply to every media response, each a token bucket:
+//! a global cap shared by all streams, a cap per client IP shared by that
+//! client's streams, and optional caps by MIME type that apply to each
+//! stream of a matching type on its own. The disk reader takes tokens for
+//! every chunk before sending it and sleeps off any debt, so a stream moves
+//! at the pace of its tightest bucket. Limits can be replaced at runtime;
+//! running streams pick them up with their next chunk.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// How much unused allowance a bucket may save up, in seconds of its rate.
+/// Small enough that an idle stream cannot burst far past its cap.
+const BURST: f64 = 0.5;
+
+/// Period over which the current transfer rate is averaged.
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Configured caps in bytes per second; None means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub global: Option<u64>,
+    pub per_client: Option<u64>,
+    /// `(pattern, cap)` where the pattern is a MIME type (`audio/mpeg`) or a
+    /// top-level type (`video/*`). The first match applies.
+    pub per_mime: Vec<(String, u64)>,
+}
+
+impl RateLimits {
+    /// Parses a limit spec such as `global=40M client=10M video/*=8M`.
+    /// Rates are in bits per second with an optional `k`, `M` or `G`
+    /// suffix; `off` or `0` removes a cap. Entries are separated by spaces
+    /// or commas, and an empty spec means no limits at all.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut limits = RateLimits::default();
+        for entry in spec.split([' ', ',']).filter(|e| !e.is_empty()) {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=rate, got '{}'", entry))?;
+            let rate = parse_rate(value)?;
+            match key {
+                "global" => limits.global = rate,
+                "client" => limits.per_client = rate,
+                mime if mime.contains('/') => {
+                    limits.per_mime.retain(|(pattern, _)| pattern != mime);
+                    if let Some(rate) = rate {
+                        limits.per_mime.push((mime.to_string(), rate));
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown limit '{}' (use global, client or a MIME type)",
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Cap for a single stream of the given MIME type.
+    fn mime_limit(&self, mime_type: &str) -> Option<u64> {
+        self.per_mime
+            .iter()
+            .find(|(pattern, _)| match pattern.strip_suffix("/*") {
+                Some(top_level) => mime_type.split('/').next() == Some(top_level),
+                None => pattern == mime_type,
+            })
+            .map(|&(_, rate)| rate)
+    }
+}
+
+/// Formats the limits back into the spec syntax accepted by `parse`.
+impl fmt::Display for RateLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries = Vec::new();
+        if let Some(rate) = self.global {
+            entries.push(format!("global={}", format_rate_spec(rate)));
+        }
+        if let Some(rate) = self.per_client {
+            entries.push(format!("client={}", format_rate_spec(rate)));
+        }
+        for (pattern, rate) in &self.per_mime {
+            entries.push(format!("{}={}", pattern, format_rate_spec(*rate)));
+        }
+        write!(f, "{}", entries.join(" "))
+    }
+}
+
+/// Parses a rate in bits per second (`8M`, `512k`, `1.5G`) into bytes per
+/// second. `off` and `0` mean unlimited.
+fn parse_rate(value: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1e3),
+        Some((i, 'm' | 'M')) => (&value[..i], 1e6),
+        Some((i, 'g' | 'G')) => (&value[..i], 1e9),
+        _ => (value, 1.0),
+    };
+    let bits: f64 = number
+        .parse()
+        .ok()
+        .filter(|bits: &f64| bits.is_finite() && *bits >= 0.0)
+        .ok_or_else(|| format!("invalid rate '{}' (e.g. 8M, 512k or off)", value))?;
+    let bytes = (bits * multiplier / 8.0).round() as u64;
+    if bytes == 0 && bits > 0.0 {
+        // Would silently mean "off"; the smallest rate is one byte a second.
+        return Err(format!("rate '{}' is too low (at least 8 bit/s)", value));
+    }
+    Ok((bytes > 0).then_some(bytes))
+}
+
+/// A rate in the shortest spec form that round-trips through `parse_rate`.
+fn format_rate_spec(bytes_per_sec: u64) -> String {
+    let bits = bytes_per_sec.saturating_mul(8);
+    if bits.is_multiple_of(1_000_000) {
+        format!("{}M", bits / 1_000_000)
+    } else if bits.is_multiple_of(1000) {
+        format!("{}k", bits / 1000)
+    } else {
+        bits.to_string()
+    }
+}
+
+/// Formats a rate in bytes per second for display, e.g. `"12.3 Mb/s"`.
+pub fn format_rate(bytes_per_sec: u64) -> String {
+    let bits = bytes_per_sec as f64 * 8.0;
+    if bits >= 1e6 {
+        format!("{:.1} Mb/s", bits / 1e6)
+    } else {
+        format!("{:.0} kb/s", bits / 1e3)
+    }
+}
+
+/// A token bucket that may go into debt: `take` always succeeds and returns
+/// how long the caller must wait for the debt to be paid off.
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    /// Starts full, so a new stream is not held back before its first chunk.
+    fn new() -> Self {
+        Self {
+            tokens: f64::MAX,
+            last: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, bytes: usize, rate: Option<u64>, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        let Some(rate) = rate.map(|rate| rate as f64) else {
+            self.tokens = 0.0;
+            return Duration::ZERO;
+        };
+        self.tokens = (self.tokens + elapsed * rate).min(rate * BURST) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+/// Bytes sent over the last `RATE_WINDOW`.
+#[derive(Default)]
+pub(crate) struct Meter {
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl Meter {
+    pub(crate) fn record(&mut self, bytes: usize, now: Instant) {
+        self.samples.push_back((now, bytes));
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Average bytes per second over the window.
+    pub(crate) fn rate(&mut self, now: Instant) -> u64 {
+        self.prune(now);
+        let total: usize = self.samples.iter().map(|(_, bytes)| bytes).sum();
+        (total as f64 / RATE_WINDOW.as_secs_f64()) as u64
+    }
+}
+
+struct Client {
+    bucket: Bucket,
+    meter: Meter,
+    streams: usize,
+}
+
+/// Shared limiter for all media streams; see the module docs.
+pub struct Throttle {
+    limits: RwLock<RateLimits>,
+    global: Mutex<(Bucket, Meter)>,
+    clients: Mutex<HashMap<IpAddr, Client>>,
+}
+
+impl Throttle {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: RwLock::new(limits),
+            global: Mutex::new((Bucket::new(), Meter::default())),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.limits.read().unwrap().clone()
+    }
+
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().unwrap() = limits;
+    }
+
+    /// Current total transfer rate in bytes per second.
+    pub fn rate(&self) -> u64 {
+        self.global.lock().unwrap().1.rate(Instant::now())
+    }
+
+    /// Current transfer rate per client with open streams, busiest first.
+    pub fn client_rates(&self) -> Vec<(IpAddr, u64)> {
+        let now = Instant::now();
+        let mut rates: Vec<(IpAddr, u64)> = self
+            .clients
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .map(|(ip, client)| (*ip, client.meter.rate(now)))
+            .collect();
+        rates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        rates
+    }
+
+    /// Registers a stream to `client` of the given MIME type. The returned
+    /// handle paces the stream and unregisters it when dropped.
+    pub fn stream(self: &Arc<Self>, client: IpAddr, mime_type: &str) -> StreamThrottle {
+        self.clients
+            .lock()
+            .unwrap()
+            .entry(client)
+            .or_insert_with(|| Client {
+                bucket: Bucket::new(),
+                meter: Meter::default(),
+                streams: 0,
+            })
+            .streams += 1;
+        StreamThrottle {
+            throttle: Arc::clone(self),
+            client,
+            mime_type: mime_type.to_string(),
+            bucket: Bucket::new(),
+        }
+    }
+}
+
+/// Per-stream handle from `Throttle::stream`.
+pub struct StreamThrottle {
+    throttle: Arc<Throttle>,
+    client: IpAddr,
+    mime_type: String,
+    /// Per-MIME cap of this stream alone.
+    bucket: Bucket,
+}
+
+impl StreamThrottle {
+    /// Accounts for `bytes` about to be sent and waits as long as the
+    /// tightest applicable limit requires.
+    pub async fn acquire(&mut self, bytes: usize) {
+        let wait = {
+            let limits = self.throttle.limits.read().unwrap();
+            let now = Instant::now();
+
+            let mut global = self.throttle.global.lock().unwrap();
+            let mut wait = global.0.take(bytes, limits.global, now);
+            global.1.record(bytes, now);
+            drop(global);
+
+            if let Some(client) = self.throttle.clients.lock().unwrap().get_mut(&self.client) {
+                wait = wait.max(client.bucket.take(bytes, limits.per_client, now));
+                client.meter.record(bytes, now);
+            }
+
+            wait.max(
+                self.bucket
+                    .take(bytes, limits.mime_limit(&self.mime_type), now),
+            )
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl Drop for StreamThrottle {
+    fn drop(&mut self) {
+        let mut clients = self.throttle.clients.lock().unwrap();
+        if let Some(client) = clients.get_mut(&self.client) {
+            client.streams -= 1;
+            if client.streams == 0 {
+                clients.remove(&self.client);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_spec() {
+        let limits =
+            RateLimits::parse("global=40M, client=10M  video/*=8M audio/mpeg=320k").unwrap();
+        assert_eq!(limits.global, Some(5_000_000));
+        assert_eq!(limits.per_client, Some(1_250_000));
+        assert_eq!(
+            limits.per_mime,
+            vec![
+                ("video/*".to_string(), 1_000_000),
+                ("audio/mpeg".to_string(), 40_000)
+            ]
+        );
+        assert_eq!(RateLimits::parse("").unwrap(), RateLimits::default());
+    }
+
+    #[test]
+    fn off_and_zero_remove_a_cap() {
+        let limits =
+            RateLimits::parse("global=8M video/*=8M global=off video/*=0 client=0").unwrap();
+        assert_eq!(limits, RateLimits::default());
+    }
+
+    #[test]
+    fn bad_specs_are_errors() {
+        assert!(RateLimits::parse("global").is_err());
+        assert!(RateLimits::parse("server=8M").is_err());
+        assert!(RateLimits::parse("global=fast").is_err());
+        assert!(RateLimits::parse("global=-8M").is_err());
+        assert!(RateLimits::parse("global=infk").is_err());
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(parse_rate("8M"), Ok(Some(1_000_000)));
+        assert_eq!(parse_rate("512k"), Ok(Some(64_000)));
+        assert_eq!(parse_rate("1.5G"), Ok(Some(187_500_000)));
+        assert_eq!(parse_rate(" 800 "), Ok(Some(100)));
+        assert_eq!(parse_rate("OFF"), Ok(None));
+        assert_eq!(parse_rate("0k"), Ok(None));
+        assert_eq!(parse_rate("8"), Ok(Some(1)));
+        assert!(parse_rate("3").unwrap_err().contains("too low"));
+        assert!(parse_rate("0.001k").unwrap_err().contains("too low"));
+    }
+
+    #[test]
+    fn specs_round_trip() {
+        for rate in [1, 125, 1000, 64_000, 1_000_000, 1_234_567] {
+            assert_eq!(parse_rate(&format_rate_spec(rate)), Ok(Some(rate)));
+        }
+        assert_eq!(format_rate_spec(1_000_000), "8M");
+        assert_eq!(format_rate_spec(64_000), "512k");
+        assert_eq!(format_rate_spec(100), "800");
+
+        let spec = "global=40M client=10M video/*=8M audio/mpeg=321";
+        let limits = RateLimits::parse(spec).unwrap();
+        assert_eq!(
+            limits.to_string(),
+            "global=40M client=10M video/*=8M audio/mpeg=320"
+        );
+        assert_eq!(RateLimits::parse(&limits.to_string()).unwrap(), limits);
+    }
+
+    #[test]
+    fn mime_limits_match_the_type() {
+        let limits = RateLimits::parse("audio/flac=4M video/*=8M audio/*=1M").unwrap();
+        assert_eq!(limits.mime_limit("video/mp4"), Some(1_000_000));
+        assert_eq!(limits.mime_limit("video/x-matroska"), Some(1_000_000));
+        assert_eq!(limits.mime_limit("audio/flac"), Some(500_000));
+        assert_eq!(limits.mime_limit("audio/mpeg"), Some(125_000));
+        assert_eq!(limits.mime_limit("image/jpeg"), None);
+        assert_eq!(limits.mime_limit("videos/mp4"), None);
+    }
+}
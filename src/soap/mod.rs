@@ -91,3 +91,174 @@ pub async fn send(
 
     String::from_utf8(bytes.to_vec()).map_err(|e| e.into())
 }
+
+pub fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the unescaped text content of the first `<tag>...</tag>` element.
+/// Namespace prefixes on the element are accepted (`<u:tag>` matches `tag`).
+pub fn extract_tag(xml: &str, tag: &str) -> Option<String> {
+    let mut search_from = 0;
+    while let Some(offset) = xml[search_from..].find('<') {
+        let open = search_from + offset;
+        let close = open + xml[open..].find('>')?;
+        let name_part = &xml[open + 1..close];
+        let name = name_part
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        let local = name.rsplit(':').next().unwrap_or(name);
+
+        if local == tag && !name.starts_with('/') {
+            if name_part.ends_with('/') {
+                return Some(String::new());
+            }
+            let content_start = close + 1;
+            let end_marker = format!("</{}>", name);
+            let content_end = content_start + xml[content_start..].find(&end_marker)?;
+            return Some(xml_unescape(&xml[content_start..content_end]));
+        }
+        search_from = close + 1;
+    }
+    None
+}
+
+/// Splits a SOAPAction header value such as
+/// `"urn:schemas-upnp-org:service:ContentDirectory:1#Browse"` into
+/// (service URN, action name).
+pub fn parse_action_header(value: &str) -> Option<(String, String)> {
+    let (urn, action) = value.trim().trim_matches('"').split_once('#')?;
+    Some((urn.to_string(), action.to_string()))
+}
+
+/// Builds the SOAP envelope returned by a server-side UPnP action.
+/// `params` holds the output arguments, already escaped where necessary.
+pub fn build_response(service_urn: &str, method: &str, params: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\"?>\n\
+<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\n\
+    <s:Body>\n\
+        <u:{0}Response xmlns:u=\"{1}\">\n\
+            {2}\n\
+        </u:{0}Response>\n\
+    </s:Body>\n\
+</s:Envelope>",
+        method, service_urn, params
+    )
+}
+
+/// A UPnP error returned from a server-side action as a SOAP fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpnpError {
+    pub code: u16,
+    pub description: &'static str,
+}
+
+impl UpnpError {
+    pub const INVALID_ACTION: Self = Self {
+        code: 401,
+        description: "Invalid Action",
+    };
+    pub const INVALID_ARGS: Self = Self {
+        code: 402,
+        description: "Invalid Args",
+    };
+    pub const NO_SUCH_OBJECT: Self = Self {
+        code: 701,
+        description: "No such object",
+    };
+    pub const INVALID_CONNECTION_REFERENCE: Self = Self {
+        code: 706,
+        description: "Invalid connection reference",
+    };
+    pub const INVALID_SEARCH_CRITERIA: Self = Self {
+        code: 708,
+        description: "Unsupported or invalid search criteria",
+    };
+    pub const NO_SUCH_CONTAINER: Self = Self {
+        code: 710,
+        description: "No such container",
+    };
+}
+
+/// Builds a SOAP fault envelope carrying a UPnPError detail block.
+pub fn build_fault(error: UpnpError) -> String {
+    format!(
+        "<?xml version=\"1.0\"?>\n\
+<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\n\
+    <s:Body>\n\
+        <s:Fault>\n\
+            <faultcode>s:Client</faultcode>\n\
+            <faultstring>UPnPError</faultstring>\n\
+            <detail>\n\
+                <UPnPError xmlns=\"urn:schemas-upnp-org:control-1-0\">\n\
+                    <errorCode>{}</errorCode>\n\
+                    <errorDescription>{}</errorDescription>\n\
+                </UPnPError>\n\
+            </detail>\n\
+        </s:Fault>\n\
+    </s:Body>\n\
+</s:Envelope>",
+        error.code, error.description
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_element_text() {
+        let xml = "<u:Browse xmlns:u=\"urn:x\"><ObjectID>0</ObjectID>\
+                   <u:Filter>dc:title,res@size</u:Filter><Title>Tom &amp; Jerry</Title></u:Browse>";
+        assert_eq!(extract_tag(xml, "ObjectID").as_deref(), Some("0"));
+        assert_eq!(
+            extract_tag(xml, "Filter").as_deref(),
+            Some("dc:title,res@size")
+        );
+        assert_eq!(extract_tag(xml, "Title").as_deref(), Some("Tom & Jerry"));
+        assert_eq!(extract_tag(xml, "Missing"), None);
+    }
+
+    #[test]
+    fn self_closing_elements_are_empty() {
+        let xml = "<ObjectID>0</ObjectID><Filter/><SortCriteria>+dc:title</SortCriteria>";
+        assert_eq!(extract_tag(xml, "Filter").as_deref(), Some(""));
+        assert_eq!(
+            extract_tag(xml, "SortCriteria").as_deref(),
+            Some("+dc:title")
+        );
+        assert_eq!(extract_tag("<u:Filter/>", "Filter").as_deref(), Some(""));
+        assert_eq!(extract_tag("<Filter />", "Filter").as_deref(), Some(""));
+        assert_eq!(
+            extract_tag("<u:Filter a=\"1\"/>", "Filter").as_deref(),
+            Some("")
+        );
+    }
+
+    #[test]
+    fn unterminated_elements() {
+        assert_eq!(extract_tag("<Filter>*", "Filter"), None);
+        assert_eq!(extract_tag("<Filter", "Filter"), None);
+    }
+
+    #[test]
+    fn action_headers() {
+        assert_eq!(
+            parse_action_header("\"urn:schemas-upnp-org:service:ContentDirectory:1#Browse\""),
+            Some((
+                "urn:schemas-upnp-org:service:ContentDirectory:1".to_string(),
+                "Browse".to_string()
+            ))
+        );
+        assert_eq!(parse_action_header("Browse"), None);
+    }
+}
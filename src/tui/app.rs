@@ -1,9 +1,20 @@
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tokio::{sync::watch, task::JoinHandle};
 
 use crate::discovery::health::PollSignal;
+use crate::media::finder::Subtitle;
+use crate::media::library::Library;
 use crate::media::manager::MediaFile;
+use crate::media::playlist::Playlist;
+use crate::media::subtitle::{SubtitleFormat, SubtitleOffsets};
+use crate::media::views::{view_files, View};
+use crate::server::relay::Relays;
+use crate::server::sessions::{Session, Sessions};
+use crate::server::throttle::Throttle;
+use crate::tui::queue::SavedQueue;
 
 // ── Device entry returned by the background scan ──────────────────────────────
 
@@ -30,6 +41,67 @@ pub enum AppPhase {
 pub enum FocusPanel {
     Devices,
     Media,
+    Streams,
+}
+
+// ── Subtitle choice ───────────────────────────────────────────────────────────
+
+/// Which subtitle track to cast with each video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubtitleChoice {
+    /// The untagged track if there is one, else the first by language.
+    Auto,
+    Off,
+    /// This language, or the untagged track when a video lacks it.
+    Language(String),
+}
+
+impl SubtitleChoice {
+    pub fn label(&self) -> String {
+        match self {
+            SubtitleChoice::Auto => "Auto".to_string(),
+            SubtitleChoice::Off => "Off".to_string(),
+            SubtitleChoice::Language(language) => language.clone(),
+        }
+    }
+
+    /// Orders a video's subtitles for casting with the chosen track first.
+    /// Empty when subtitles are off or no track fits the choice.
+    pub fn arrange(&self, mut subtitles: Vec<Subtitle>) -> Vec<Subtitle> {
+        let chosen = match self {
+            SubtitleChoice::Off => None,
+            SubtitleChoice::Auto => (!subtitles.is_empty()).then_some(0),
+            SubtitleChoice::Language(language) => subtitles
+                .iter()
+                .position(|s| {
+                    s.language
+                        .as_deref()
+                        .is_some_and(|l| l.eq_ignore_ascii_case(language))
+                })
+                .or_else(|| subtitles.iter().position(|s| s.language.is_none())),
+        };
+        match chosen {
+            Some(i) => {
+                let track = subtitles.remove(i);
+                subtitles.insert(0, track);
+                subtitles
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Popup listing the subtitle choices; open while picking.
+pub struct SubtitleMenu {
+    pub options: Vec<SubtitleChoice>,
+    pub cursor: usize,
+}
+
+/// Popup listing the playlist files of the library; open while picking one
+/// to load.
+pub struct PlaylistMenu {
+    pub playlists: Vec<Playlist>,
+    pub cursor: usize,
 }
 
 // ── Unified app state ─────────────────────────────────────────────────────────
@@ -48,9 +120,13 @@ pub struct AppState {
     pub cm_url: String,
 
     // media panel
+    pub library: Arc<Library>,
+    pub library_rx: watch::Receiver<u64>,
     pub media_files: Vec<MediaFile>,
-    pub media_cursor: usize,
-    pub media_selected: HashSet<usize>,
+    pub media_view: View,
+    pub media_visible: Vec<usize>, // indices into media_files, in view order
+    pub media_cursor: usize,       // index into media_visible
+    pub media_selected: HashSet<usize>, // indices into media_files
     pub media_scroll: usize,
     pub media_viewport_h: usize,
 
@@ -58,12 +134,57 @@ pub struct AppState {
     pub focus: FocusPanel,
 
     // playback
-    pub playlist: Vec<usize>, // sorted indices into media_files
+    pub playlist: Vec<usize>, // indices into media_files, in play order
     pub playlist_pos: usize,  // current position in playlist
     pub transport_state: String,
     pub poll_task: Option<JoinHandle<()>>,
     pub poll_rx: Option<watch::Receiver<PollSignal>>,
     pub seek_input: String,
+    pub playlist_menu: Option<PlaylistMenu>,
+    /// Name for saving the queue as a playlist file; the popup is open
+    /// while typing, independent of phase.
+    pub playlist_name_input: Option<String>,
+
+    // queue left by the previous run; offered once a device is connected
+    // and kept on disk until answered
+    pub saved_queue: Option<SavedQueue>,
+    pub resume_prompt: bool,
+
+    // remote URLs, relayed by the server's /proxy endpoint; the popup is
+    // open while typing one
+    pub relays: Arc<Relays>,
+    pub url_input: Option<String>,
+    /// URL given on the command line, cast once a device is connected.
+    pub pending_cast: Option<String>,
+    /// URL being played instead of a playlist track.
+    pub remote_cast: Option<String>,
+
+    // slideshow: photos advance on a timer, as renderers never report them
+    // as finished
+    pub slide_due: Option<Instant>,
+    /// Time left on the current photo while the slideshow is paused.
+    pub slide_remaining: Option<Duration>,
+
+    // subtitles: the choice applies from the next cast
+    pub subtitle_choice: SubtitleChoice,
+    pub subtitle_menu: Option<SubtitleMenu>,
+    pub current_subtitle: Option<Subtitle>,
+    /// Subtitle formats the connected renderer lists in its protocolInfo.
+    pub subtitle_formats: Vec<SubtitleFormat>,
+    /// Timing offset per media file, saved in the state directory.
+    pub subtitle_offsets: SubtitleOffsets,
+    /// When to re-cast the playing track with its new offset; pushed back
+    /// on every key press so a run of adjustments re-casts once.
+    pub subtitle_offset_due: Option<Instant>,
+
+    // bandwidth limits; the popup is open while editing, independent of phase
+    pub throttle: Arc<Throttle>,
+    pub limit_input: Option<String>,
+
+    // streams panel; refreshed from the session registry every tick
+    pub sessions: Arc<Sessions>,
+    pub streams: Vec<Session>,
+    pub stream_cursor: usize,
 
     // status bar
     pub status_msg: Option<String>,
@@ -71,7 +192,13 @@ pub struct AppState {
 }
 
 impl AppState {
-    pub fn new(media_files: Vec<MediaFile>) -> Self {
+    pub fn new(
+        library: Arc<Library>,
+        throttle: Arc<Throttle>,
+        sessions: Arc<Sessions>,
+        relays: Arc<Relays>,
+    ) -> Self {
+        let media_files = library.files().as_ref().clone();
         Self {
             phase: AppPhase::Idle,
             scan_rx: None,
@@ -83,7 +210,11 @@ impl AppState {
             av_url: String::new(),
             cm_url: String::new(),
 
+            library_rx: library.subscribe(),
+            library,
+            media_visible: view_files(View::Folders, &media_files),
             media_files,
+            media_view: View::Folders,
             media_cursor: 0,
             media_selected: HashSet::new(),
             media_scroll: 0,
@@ -97,6 +228,33 @@ impl AppState {
             poll_task: None,
             poll_rx: None,
             seek_input: String::new(),
+            playlist_menu: None,
+            playlist_name_input: None,
+
+            saved_queue: None,
+            resume_prompt: false,
+
+            relays,
+            url_input: None,
+            pending_cast: None,
+            remote_cast: None,
+
+            slide_due: None,
+            slide_remaining: None,
+
+            subtitle_choice: SubtitleChoice::Auto,
+            subtitle_menu: None,
+            current_subtitle: None,
+            subtitle_formats: Vec::new(),
+            subtitle_offsets: SubtitleOffsets::default(),
+            subtitle_offset_due: None,
+
+            throttle,
+            limit_input: None,
+
+            sessions,
+            streams: vec![],
+            stream_cursor: 0,
 
             status_msg: None,
             status_ticks: 0,
@@ -116,6 +274,12 @@ impl AppState {
         self.playlist.clear();
         self.playlist_pos = 0;
         self.transport_state.clear();
+        self.current_subtitle = None;
+        self.remote_cast = None;
+        self.relays.clear();
+        self.subtitle_offset_due = None;
+        self.slide_due = None;
+        self.slide_remaining = None;
         self.phase = AppPhase::Idle;
     }
 
@@ -130,6 +294,167 @@ impl AppState {
         }
     }
 
+    /// Whether the playing track is a photo on the slideshow timer.
+    pub fn showing_photo(&self) -> bool {
+        self.slide_due.is_some() || self.slide_remaining.is_some()
+    }
+
+    /// Subtitle offset of the playing track in milliseconds, including
+    /// adjustments not yet re-cast.
+    pub fn subtitle_offset(&self) -> i64 {
+        self.current_track()
+            .map(|file| self.subtitle_offsets.get(&file.relative_path))
+            .unwrap_or(0)
+    }
+
+    /// Switches the media panel to another view. Selections are kept, since
+    /// they refer to files rather than rows.
+    pub fn set_media_view(&mut self, view: View) {
+        self.media_view = view;
+        self.media_visible = view_files(view, &self.media_files);
+        self.media_cursor = 0;
+        self.media_scroll = 0;
+    }
+
+    /// Picks up the latest library snapshot. Indices into media_files are
+    /// remapped by relative path, so the cursor, selections and playlist
+    /// follow their files; entries whose file disappeared are dropped.
+    ///
+    /// Returns true when the track being played was removed. playlist_pos
+    /// then already points at the track that should play next.
+    pub fn reload_library(&mut self) -> bool {
+        let files = self.library.files().as_ref().clone();
+        let old_files = std::mem::replace(&mut self.media_files, files);
+        let remap = |old: usize| {
+            let path = &old_files.get(old)?.relative_path;
+            self.media_files
+                .binary_search_by(|f| f.relative_path.cmp(path))
+                .ok()
+        };
+
+        let cursor_file = self.media_at_cursor().and_then(remap);
+        self.media_selected = self
+            .media_selected
+            .iter()
+            .filter_map(|&i| remap(i))
+            .collect();
+
+        let playing = self.current_track().is_some();
+        let current_kept = self
+            .playlist
+            .get(self.playlist_pos)
+            .and_then(|&i| remap(i))
+            .is_some();
+        self.playlist_pos = self.playlist[..self.playlist_pos.min(self.playlist.len())]
+            .iter()
+            .filter(|&&i| remap(i).is_some())
+            .count();
+        self.playlist = self.playlist.iter().filter_map(|&i| remap(i)).collect();
+
+        self.media_visible = view_files(self.media_view, &self.media_files);
+        self.media_cursor = cursor_file
+            .and_then(|file| self.media_visible.iter().position(|&i| i == file))
+            .unwrap_or(self.media_cursor)
+            .min(self.media_visible.len().saturating_sub(1));
+        self.scroll_media_to_cursor();
+
+        playing && !current_kept
+    }
+
+    /// Indices into media_files of a playlist's entries, in playlist order.
+    /// Entries that are not in the library are left out.
+    pub fn playlist_files(&self, playlist: &Playlist) -> Vec<usize> {
+        playlist
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                self.media_files
+                    .binary_search_by(|f| f.relative_path.cmp(entry))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Files to save as a playlist: the queue being played, else the
+    /// selection.
+    pub fn queue_to_save(&self) -> Vec<usize> {
+        if self.current_track().is_some() {
+            self.playlist.clone()
+        } else {
+            self.selected_in_view_order()
+        }
+    }
+
+    /// The active queue as it should be resumed next time, if any.
+    pub fn queue_snapshot(&self, elapsed: Option<String>) -> Option<SavedQueue> {
+        self.current_track()?;
+        Some(SavedQueue {
+            entries: self
+                .playlist
+                .iter()
+                .filter_map(|&i| self.media_files.get(i))
+                .map(|file| file.relative_path.clone())
+                .collect(),
+            position: self.playlist_pos,
+            elapsed,
+        })
+    }
+
+    /// Indices into media_files of a saved queue's entries that are still
+    /// in the library, and the position to resume at: the saved track, or
+    /// the one after it when it is gone.
+    pub fn saved_queue_files(&self, saved: &SavedQueue) -> (Vec<usize>, usize) {
+        let find = |entry: &String| {
+            self.media_files
+                .binary_search_by(|f| f.relative_path.cmp(entry))
+                .ok()
+        };
+        let queue: Vec<usize> = saved.entries.iter().filter_map(find).collect();
+        let position = saved.entries[..saved.position.min(saved.entries.len())]
+            .iter()
+            .filter(|entry| find(entry).is_some())
+            .count()
+            .min(queue.len().saturating_sub(1));
+        (queue, position)
+    }
+
+    /// Index into media_files of the row under the cursor.
+    pub fn media_at_cursor(&self) -> Option<usize> {
+        self.media_visible.get(self.media_cursor).copied()
+    }
+
+    /// Selected files in the order the current view lists them, followed by
+    /// selections hidden by the view in path order.
+    pub fn selected_in_view_order(&self) -> Vec<usize> {
+        let mut ordered: Vec<usize> = self
+            .media_visible
+            .iter()
+            .copied()
+            .filter(|i| self.media_selected.contains(i))
+            .collect();
+        let listed: HashSet<usize> = ordered.iter().copied().collect();
+        let mut hidden: Vec<usize> = self
+            .media_selected
+            .iter()
+            .copied()
+            .filter(|i| !listed.contains(i))
+            .collect();
+        hidden.sort_unstable();
+        ordered.extend(hidden);
+        ordered
+    }
+
+    /// Takes a fresh snapshot of the active streams, keeping the cursor on
+    /// the same stream when it is still open.
+    pub fn refresh_streams(&mut self) {
+        let selected = self.streams.get(self.stream_cursor).map(|s| s.id);
+        self.streams = self.sessions.list();
+        self.stream_cursor = selected
+            .and_then(|id| self.streams.iter().position(|s| s.id == id))
+            .unwrap_or(self.stream_cursor)
+            .min(self.streams.len().saturating_sub(1));
+    }
+
     /// Scroll media list so cursor is in viewport.
     pub fn scroll_media_to_cursor(&mut self) {
         if self.media_cursor < self.media_scroll {
@@ -1,5 +1,6 @@
 pub mod app;
 pub mod event;
+pub mod queue;
 pub mod screens;
 pub mod terminal;
 
@@ -0,0 +1,49 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// File name of the saved queue inside the state directory.
+const QUEUE_FILE: &str = "last_queue.json";
+
+/// The play queue that was active when the TUI last quit, offered for
+/// resuming on the next launch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedQueue {
+    /// Paths relative to the media root, in play order.
+    pub entries: Vec<String>,
+    /// Index into `entries` of the track that was playing.
+    pub position: usize,
+    /// How far into that track playback was, as `H:MM:SS`; None for photos
+    /// or when the renderer did not say.
+    #[serde(default)]
+    pub elapsed: Option<String>,
+}
+
+impl SavedQueue {
+    /// Loads the saved queue, or None when there is none or it cannot be
+    /// parsed.
+    pub fn load(state_directory: &Path) -> Option<Self> {
+        let data = fs::read(state_directory.join(QUEUE_FILE)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Writes the queue atomically (temp file + rename), creating the state
+    /// directory if needed.
+    pub fn save(&self, state_directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(state_directory)?;
+        let target = state_directory.join(QUEUE_FILE);
+        let temp = target.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(temp, target)
+    }
+
+    /// Forgets the saved queue, if any.
+    pub fn clear(state_directory: &Path) -> io::Result<()> {
+        match fs::remove_file(state_directory.join(QUEUE_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -8,6 +8,9 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
     Frame,
 };
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::time::{interval, Duration};
 
 use crate::config::Config;
@@ -16,18 +19,33 @@ use crate::discovery::device::{
 };
 use crate::discovery::health::{spawn_poll_task, PollSignal};
 use crate::discovery::ssdp::discover_ssdp;
-use crate::media::finder::find_subtitle;
-use crate::media::manager::MediaFile;
-use crate::media::stream::stream_media;
+use crate::media::finder::{find_subtitles, Subtitle};
+use crate::media::library::Library;
+use crate::media::manager::{MediaClass, MediaFile};
+use crate::media::playlist::write_m3u;
+use crate::media::stream::{stream_media, stream_url};
+use crate::media::subtitle::{SubtitleFormat, SubtitleOffsets};
+use crate::server::relay::Relays;
+use crate::server::sessions::Sessions;
+use crate::server::throttle::{format_rate, RateLimits, Throttle};
 use crate::soap::SoapClient;
 use crate::tui::{
-    app::{AppPhase, AppState, FocusPanel, ScannedDevice},
+    app::{
+        AppPhase, AppState, FocusPanel, PlaylistMenu, ScannedDevice, SubtitleChoice, SubtitleMenu,
+    },
     event::TuiEvent,
+    queue::SavedQueue,
     terminal::TerminalGuard,
 };
 
 const SPIN: &[char] = &['|', '/', '-', '\\'];
 
+/// Subtitle offset change per key press.
+const SUBTITLE_OFFSET_STEP_MS: i64 = 100;
+
+/// How long after the last offset key press the track is re-cast.
+const SUBTITLE_OFFSET_SETTLE: Duration = Duration::from_millis(1000);
+
 // ── Scan thread ───────────────────────────────────────────────────────────────
 
 fn start_scan(config: &Config) -> std::sync::mpsc::Receiver<Vec<ScannedDevice>> {
@@ -91,11 +109,32 @@ fn start_scan(config: &Config) -> std::sync::mpsc::Receiver<Vec<ScannedDevice>>
 
 pub async fn run_app(
     terminal: &mut TerminalGuard,
-    media_files: Vec<MediaFile>,
+    library: Arc<Library>,
+    throttle: Arc<Throttle>,
+    sessions: Arc<Sessions>,
+    relays: Arc<Relays>,
     config: Config,
     soap: SoapClient,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut state = AppState::new(media_files);
+    let mut state = AppState::new(library, throttle, sessions, relays);
+    state.subtitle_offsets = SubtitleOffsets::load(Path::new(&config.state_directory));
+    if let Some(saved) = SavedQueue::load(Path::new(&config.state_directory)) {
+        let (queue, _) = state.saved_queue_files(&saved);
+        if !queue.is_empty() {
+            state.set_status(
+                format!(
+                    "Last queue ({} tracks) can be resumed once a device is connected",
+                    queue.len()
+                ),
+                50,
+            );
+            state.saved_queue = Some(saved);
+        }
+    }
+    if let Some(url) = &config.cast_url {
+        state.set_status(format!("Connect to a device to cast {}", url), 50);
+        state.pending_cast = Some(url.clone());
+    }
     state.scan_rx = Some(start_scan(&config));
     state.phase = AppPhase::Scanning;
 
@@ -147,6 +186,23 @@ pub async fn run_app(
         match evt {
             TuiEvent::Tick => {
                 state.tick = state.tick.wrapping_add(1);
+                state.refresh_streams();
+                if state
+                    .subtitle_offset_due
+                    .is_some_and(|due| due <= Instant::now())
+                {
+                    apply_subtitle_offset(&mut state, &soap, &config).await;
+                }
+                if state.slide_due.is_some_and(|due| due <= Instant::now()) {
+                    state.slide_due = None;
+                    advance_playlist(&mut state, &soap, &config).await;
+                }
+                if state.library_rx.has_changed().unwrap_or(false) {
+                    state.library_rx.mark_unchanged();
+                    if state.reload_library() {
+                        skip_removed_track(&mut state, &soap, &config).await;
+                    }
+                }
             }
             TuiEvent::Poll(signal) => {
                 if handle_poll(&mut state, signal, &soap, &config).await {
@@ -182,6 +238,10 @@ async fn handle_poll(
     config: &Config,
 ) -> bool {
     match signal {
+        // A photo moves on only by the slideshow timer; whatever state the
+        // renderer reports for a still image is ignored
+        PollSignal::Paused | PollSignal::Resumed | PollSignal::Stopped if state.showing_photo() => {
+        }
         PollSignal::Paused => {
             state.transport_state = "PAUSED_PLAYBACK".to_string();
         }
@@ -211,6 +271,10 @@ async fn advance_playlist(state: &mut AppState, soap: &SoapClient, config: &Conf
         state.playlist_pos = next;
         start_track(state, soap, config).await;
     } else {
+        // A photo stays on screen until told otherwise
+        crate::dlna::av_transport::stop(soap, &state.av_url)
+            .await
+            .ok();
         state.clear_playback();
         state.focus = FocusPanel::Media;
         state.set_status(
@@ -220,6 +284,25 @@ async fn advance_playlist(state: &mut AppState, soap: &SoapClient, config: &Conf
     }
 }
 
+/// The playing file was deleted from the media directory: move on to the
+/// track reload_library lined up, or go idle if it was the last one.
+async fn skip_removed_track(state: &mut AppState, soap: &SoapClient, config: &Config) {
+    if let Some(h) = state.poll_task.take() {
+        h.abort();
+    }
+    state.poll_rx = None;
+    state.set_status("Current file was removed from the library", 30);
+
+    if state.playlist_pos < state.playlist.len() {
+        start_track(state, soap, config).await;
+    } else {
+        crate::dlna::av_transport::stop(soap, &state.av_url)
+            .await
+            .ok();
+        state.clear_playback();
+    }
+}
+
 /// Try to reconnect after device goes offline. Restarts track on success.
 async fn handle_device_offline(state: &mut AppState, soap: &SoapClient, config: &Config) {
     if let Some(h) = state.poll_task.take() {
@@ -249,6 +332,44 @@ async fn handle_device_offline(state: &mut AppState, soap: &SoapClient, config:
     }
 }
 
+/// Re-casts the playing track so the renderer fetches its subtitles with
+/// the new offset, then seeks back to where it was.
+async fn apply_subtitle_offset(state: &mut AppState, soap: &SoapClient, config: &Config) {
+    state.subtitle_offset_due = None;
+    let Some(subtitle) = &state.current_subtitle else {
+        return;
+    };
+    let offset = state.subtitle_offset();
+    if state.phase != AppPhase::Playing || subtitle.offset_ms == offset {
+        return;
+    }
+
+    let position = crate::dlna::av_transport::get_position(soap, &state.av_url)
+        .await
+        .ok();
+    if let Some(h) = state.poll_task.take() {
+        h.abort();
+    }
+    state.poll_rx = None;
+    start_track(state, soap, config).await;
+    if state.phase != AppPhase::Playing {
+        return;
+    }
+    let msg = match position {
+        Some(position) => {
+            match crate::dlna::av_transport::seek(soap, &state.av_url, &position).await {
+                Ok(()) => format!("Subtitle offset {} applied", format_offset(offset)),
+                Err(e) => format!("Subtitle offset applied, seek back failed: {}", e),
+            }
+        }
+        None => format!(
+            "Subtitle offset {} applied from the start",
+            format_offset(offset)
+        ),
+    };
+    state.set_status(msg, 30);
+}
+
 /// Call stream_media + spawn_poll_task for the current playlist position.
 async fn start_track(state: &mut AppState, soap: &SoapClient, config: &Config) {
     let media_file = match state
@@ -263,7 +384,19 @@ async fn start_track(state: &mut AppState, soap: &SoapClient, config: &Config) {
         }
     };
 
-    let subtitle_url = find_subtitle(media_file, &config.http_address, config.http_port);
+    let is_photo = media_file.class() == MediaClass::Image;
+    let offset = state.subtitle_offsets.get(&media_file.relative_path);
+    let subtitles: Vec<Subtitle> = state
+        .subtitle_choice
+        .arrange(find_subtitles(media_file, config))
+        .into_iter()
+        .map(|subtitle| {
+            let format = subtitle.source.target_for(&state.subtitle_formats);
+            subtitle
+                .with_format(format, config)
+                .with_offset(offset, config)
+        })
+        .collect();
 
     if let Err(e) = stream_media(
         soap,
@@ -271,7 +404,8 @@ async fn start_track(state: &mut AppState, soap: &SoapClient, config: &Config) {
         &state.av_url,
         &state.cm_url,
         media_file,
-        subtitle_url.as_deref(),
+        &subtitles,
+        state.library.art(),
     )
     .await
     {
@@ -289,13 +423,172 @@ async fn start_track(state: &mut AppState, soap: &SoapClient, config: &Config) {
     }
 
     state.transport_state = "PLAYING".to_string();
+    state.current_subtitle = subtitles.into_iter().next();
+    state.subtitle_offset_due = None;
+    state.slide_remaining = None;
     state.phase = AppPhase::Playing;
 
+    // A photo stays up until told otherwise, so only the slideshow timer
+    // moves on; the poll task is there to notice a lost device.
+    state.slide_due = is_photo.then(|| Instant::now() + Duration::from_secs(config.slideshow_secs));
+
     let (poll_task, poll_rx) = spawn_poll_task(soap.clone(), state.av_url.clone());
     state.poll_task = Some(poll_task);
     state.poll_rx = Some(poll_rx);
 }
 
+/// Replaces the playlist with `queue` and casts the track at `position`,
+/// stopping whatever is playing.
+async fn play_queue(
+    state: &mut AppState,
+    queue: Vec<usize>,
+    position: usize,
+    soap: &SoapClient,
+    config: &Config,
+) {
+    if matches!(state.phase, AppPhase::Playing | AppPhase::SeekInput) {
+        crate::dlna::av_transport::stop(soap, &state.av_url)
+            .await
+            .ok();
+        if let Some(h) = state.poll_task.take() {
+            h.abort();
+        }
+        state.poll_rx = None;
+    }
+    // A local queue replaces any remote cast, whose relay stops serving it
+    state.remote_cast = None;
+    state.relays.clear();
+    state.playlist = queue;
+    state.playlist_pos = position;
+    start_track(state, soap, config).await;
+}
+
+/// Plays the queue left by the previous run from where it stopped.
+async fn resume_queue(state: &mut AppState, saved: SavedQueue, soap: &SoapClient, config: &Config) {
+    let (queue, position) = state.saved_queue_files(&saved);
+    if queue.is_empty() {
+        state.set_status("Nothing in the last queue is in the library any more", 30);
+        return;
+    }
+    // The elapsed time only applies if the saved track itself is still there
+    let same_track = saved.entries.get(saved.position).is_some_and(|entry| {
+        state
+            .media_files
+            .get(queue[position])
+            .is_some_and(|file| &file.relative_path == entry)
+    });
+    let total = queue.len();
+    state.set_status(format!("Resumed at track {}/{}", position + 1, total), 30);
+    play_queue(state, queue, position, soap, config).await;
+
+    if let Some(elapsed) = saved.elapsed.filter(|_| same_track) {
+        if state.phase == AppPhase::Playing && !state.showing_photo() {
+            let msg = match crate::dlna::av_transport::seek(soap, &state.av_url, &elapsed).await {
+                Ok(()) => format!("Resumed at track {}/{}, {}", position + 1, total, elapsed),
+                Err(e) => format!(
+                    "Resumed at track {}/{}, seek failed: {}",
+                    position + 1,
+                    total,
+                    e
+                ),
+            };
+            state.set_status(msg, 30);
+        }
+    }
+}
+
+/// Casts a remote URL through the server's relay, replacing the queue.
+async fn cast_remote(state: &mut AppState, url: String, soap: &SoapClient, config: &Config) {
+    if matches!(state.phase, AppPhase::Playing | AppPhase::SeekInput) {
+        crate::dlna::av_transport::stop(soap, &state.av_url)
+            .await
+            .ok();
+    }
+    state.clear_playback();
+
+    let mime_type = match stream_url(
+        soap,
+        config,
+        &state.relays,
+        &state.av_url,
+        &state.cm_url,
+        &url,
+    )
+    .await
+    {
+        Ok(mime_type) => mime_type,
+        Err(e) => {
+            state.set_status(format!("Cast failed: {}", e), 30);
+            return;
+        }
+    };
+    state.set_status(format!("Casting {} ({})", url, mime_type), 30);
+    state.transport_state = "PLAYING".to_string();
+    state.remote_cast = Some(url);
+    state.phase = AppPhase::Playing;
+
+    // A remote photo stays up until stopped on the renderer, which the poll
+    // task notices like the end of any other stream
+    let (poll_task, poll_rx) = spawn_poll_task(soap.clone(), state.av_url.clone());
+    state.poll_task = Some(poll_task);
+    state.poll_rx = Some(poll_rx);
+}
+
+/// Writes the queue (or selection) to `<name>.m3u8` in the media root and
+/// returns the file name.
+fn save_queue_as(state: &AppState, name: &str, config: &Config) -> Result<String, String> {
+    let name = name.trim();
+    let name = name
+        .strip_suffix(".m3u8")
+        .or_else(|| name.strip_suffix(".m3u"))
+        .unwrap_or(name)
+        .trim();
+    if name.is_empty() {
+        return Err("Enter a name".to_string());
+    }
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err("The name cannot contain slashes or start with a dot".to_string());
+    }
+    let files: Vec<&MediaFile> = state
+        .queue_to_save()
+        .into_iter()
+        .filter_map(|i| state.media_files.get(i))
+        .collect();
+    let file_name = format!("{}.m3u8", name);
+    let path = Path::new(&config.media_directory).join(&file_name);
+    write_m3u(&path, name, &files).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => format!("{} already exists", file_name),
+        _ => format!("Could not save {}: {}", file_name, e),
+    })?;
+    Ok(file_name)
+}
+
+/// Remembers the active queue for the next launch and stops the renderer.
+/// Without an active queue an unanswered resume offer is kept; otherwise the
+/// saved queue is dropped.
+async fn quit(state: &mut AppState, soap: &SoapClient, config: &Config) {
+    let playing = matches!(state.phase, AppPhase::Playing | AppPhase::SeekInput);
+    let elapsed = if playing && !state.showing_photo() {
+        crate::dlna::av_transport::get_position(soap, &state.av_url)
+            .await
+            .ok()
+    } else {
+        None
+    };
+    let state_directory = Path::new(&config.state_directory);
+    // Nowhere to report a failure once the TUI is closing
+    match state.queue_snapshot(elapsed) {
+        Some(queue) => queue.save(state_directory).ok(),
+        None if state.saved_queue.is_some() => None,
+        None => SavedQueue::clear(state_directory).ok(),
+    };
+    if playing {
+        crate::dlna::av_transport::stop(soap, &state.av_url)
+            .await
+            .ok();
+    }
+}
+
 // ── Key handler ───────────────────────────────────────────────────────────────
 
 /// Returns true if the app should quit.
@@ -307,14 +600,162 @@ async fn handle_key(
 ) -> Result<bool, Box<dyn std::error::Error>> {
     // Global quit
     if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        if matches!(state.phase, AppPhase::Playing | AppPhase::SeekInput) {
-            crate::dlna::av_transport::stop(soap, &state.av_url)
-                .await
-                .ok();
-        }
+        quit(state, soap, config).await;
         return Ok(true);
     }
 
+    // Resume offer — answer before anything else
+    if state.resume_prompt {
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                state.resume_prompt = false;
+                if let Some(saved) = state.saved_queue.take() {
+                    resume_queue(state, saved, soap, config).await;
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                state.resume_prompt = false;
+                state.saved_queue = None;
+                state.set_status("Last queue discarded", 20);
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // URL input — capture all chars
+    if let Some(input) = state.url_input.as_mut() {
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let url = input.trim().to_string();
+                if state.active_device.is_none() {
+                    state.set_status("Connect to a device first (Tab → Devices, Enter)", 30);
+                } else if !url.is_empty() {
+                    state.url_input = None;
+                    cast_remote(state, url, soap, config).await;
+                }
+            }
+            KeyCode::Esc => state.url_input = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // Playlist name input — capture all chars
+    if let Some(input) = state.playlist_name_input.as_mut() {
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let name = input.clone();
+                match save_queue_as(state, &name, config) {
+                    Ok(file_name) => {
+                        state.playlist_name_input = None;
+                        state.set_status(format!("Saved {}", file_name), 30);
+                    }
+                    Err(e) => state.set_status(e, 30),
+                }
+            }
+            KeyCode::Esc => state.playlist_name_input = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // Rate limit input — capture all chars
+    if let Some(input) = state.limit_input.as_mut() {
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => match RateLimits::parse(input) {
+                Ok(limits) => {
+                    let summary = limits.to_string();
+                    state.throttle.set_limits(limits);
+                    state.limit_input = None;
+                    if summary.is_empty() {
+                        state.set_status("Rate limits removed", 20);
+                    } else {
+                        state.set_status(format!("Rate limits: {}", summary), 20);
+                    }
+                }
+                Err(e) => state.set_status(format!("Invalid limits: {}", e), 30),
+            },
+            KeyCode::Esc => state.limit_input = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // Subtitle menu — navigate, pick or close
+    if let Some(menu) = state.subtitle_menu.as_mut() {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => menu.cursor = menu.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') if menu.cursor + 1 < menu.options.len() => {
+                menu.cursor += 1;
+            }
+            KeyCode::Enter => {
+                let choice = menu.options[menu.cursor].clone();
+                state.subtitle_menu = None;
+                state.set_status(
+                    format!("Subtitles: {} — applies from the next cast", choice.label()),
+                    30,
+                );
+                state.subtitle_choice = choice;
+            }
+            KeyCode::Esc | KeyCode::Char('u') | KeyCode::Char('U') => state.subtitle_menu = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // Playlist menu — navigate, load or close
+    if let Some(menu) = state.playlist_menu.as_mut() {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => menu.cursor = menu.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') if menu.cursor + 1 < menu.playlists.len() => {
+                menu.cursor += 1;
+            }
+            KeyCode::Enter => {
+                let playlist = menu.playlists.swap_remove(menu.cursor);
+                state.playlist_menu = None;
+                let queue = state.playlist_files(&playlist);
+                let missing = playlist.entries.len() - queue.len();
+                if state.active_device.is_none() {
+                    state.set_status("Connect to a device first (Tab → Devices, Enter)", 30);
+                } else if queue.is_empty() {
+                    state.set_status(
+                        format!("Nothing in {} is in the library", playlist.title),
+                        30,
+                    );
+                } else {
+                    let loaded = match missing {
+                        0 => format!("Playing {} ({} tracks)", playlist.title, queue.len()),
+                        _ => format!(
+                            "Playing {} ({} tracks, {} not found)",
+                            playlist.title,
+                            queue.len(),
+                            missing
+                        ),
+                    };
+                    // Set first so errors from start_track replace it
+                    state.set_status(loaded, 30);
+                    play_queue(state, queue, 0, soap, config).await;
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('o') | KeyCode::Char('O') => state.playlist_menu = None,
+            _ => {}
+        }
+        return Ok(false);
+    }
+
     // Seek input mode — capture all chars
     if state.phase == AppPhase::SeekInput {
         match key.code {
@@ -345,7 +786,8 @@ async fn handle_key(
         KeyCode::Tab => {
             state.focus = match state.focus {
                 FocusPanel::Devices => FocusPanel::Media,
-                FocusPanel::Media => FocusPanel::Devices,
+                FocusPanel::Media => FocusPanel::Streams,
+                FocusPanel::Streams => FocusPanel::Devices,
             };
         }
 
@@ -362,6 +804,9 @@ async fn handle_key(
                     state.scroll_media_to_cursor();
                 }
             }
+            FocusPanel::Streams => {
+                state.stream_cursor = state.stream_cursor.saturating_sub(1);
+            }
         },
         KeyCode::Down | KeyCode::Char('j') => match state.focus {
             FocusPanel::Devices => {
@@ -370,11 +815,16 @@ async fn handle_key(
                 }
             }
             FocusPanel::Media => {
-                if state.media_cursor + 1 < state.media_files.len() {
+                if state.media_cursor + 1 < state.media_visible.len() {
                     state.media_cursor += 1;
                     state.scroll_media_to_cursor();
                 }
             }
+            FocusPanel::Streams => {
+                if state.stream_cursor + 1 < state.streams.len() {
+                    state.stream_cursor += 1;
+                }
+            }
         },
 
         // ── Enter ─────────────────────────────────────────────────────────────
@@ -387,7 +837,18 @@ async fn handle_key(
                     state.av_url = dev.av_url.clone();
                     state.cm_url = dev.cm_url.clone();
                     let name = dev.name.clone();
+                    let sink =
+                        crate::dlna::connection_manager::get_protocol_info(soap, &state.cm_url)
+                            .await
+                            .unwrap_or_default();
+                    state.subtitle_formats = SubtitleFormat::accepted_by(&sink);
                     state.set_status(format!("Connected to {}", name), 20);
+                    if let Some(url) = state.pending_cast.take() {
+                        cast_remote(state, url, soap, config).await;
+                    } else {
+                        state.resume_prompt =
+                            state.saved_queue.is_some() && state.current_track().is_none();
+                    }
                 }
             }
             FocusPanel::Media => {
@@ -396,43 +857,37 @@ async fn handle_key(
                 } else if state.media_selected.is_empty() {
                     state.set_status("Select files with Space first", 20);
                 } else {
-                    let mut indices: Vec<usize> = state.media_selected.iter().copied().collect();
-                    indices.sort_unstable();
-                    state.playlist = indices;
-                    state.playlist_pos = 0;
-
-                    // Stop any existing playback
-                    if matches!(state.phase, AppPhase::Playing | AppPhase::SeekInput) {
-                        crate::dlna::av_transport::stop(soap, &state.av_url)
-                            .await
-                            .ok();
-                        if let Some(h) = state.poll_task.take() {
-                            h.abort();
-                        }
-                        state.poll_rx = None;
-                    }
-
-                    start_track(state, soap, config).await;
+                    let queue = state.selected_in_view_order();
+                    play_queue(state, queue, 0, soap, config).await;
                 }
             }
+            FocusPanel::Streams => {}
         },
 
         // ── Media panel selection ─────────────────────────────────────────────
         KeyCode::Char(' ') if state.focus == FocusPanel::Media => {
-            let cur = state.media_cursor;
-            if state.media_selected.contains(&cur) {
-                state.media_selected.remove(&cur);
-            } else {
-                state.media_selected.insert(cur);
+            if let Some(cur) = state.media_at_cursor() {
+                if state.media_selected.contains(&cur) {
+                    state.media_selected.remove(&cur);
+                } else {
+                    state.media_selected.insert(cur);
+                }
             }
         }
         KeyCode::Char('a') | KeyCode::Char('A') if state.focus == FocusPanel::Media => {
-            if state.media_selected.len() == state.media_files.len() {
-                state.media_selected.clear();
+            let visible = state.media_visible.clone();
+            if visible.iter().all(|i| state.media_selected.contains(i)) {
+                for i in &visible {
+                    state.media_selected.remove(i);
+                }
             } else {
-                state.media_selected = (0..state.media_files.len()).collect();
+                state.media_selected.extend(visible);
             }
         }
+        KeyCode::Char('v') | KeyCode::Char('V') if state.focus == FocusPanel::Media => {
+            let view = state.media_view.next();
+            state.set_media_view(view);
+        }
 
         // ── Rescan ────────────────────────────────────────────────────────────
         KeyCode::Char('r') | KeyCode::Char('R') => {
@@ -448,6 +903,18 @@ async fn handle_key(
         }
 
         // ── Playback controls (only when Playing) ─────────────────────────────
+        KeyCode::Char('p') | KeyCode::Char('P')
+            if matches!(state.phase, AppPhase::Playing) && state.showing_photo() =>
+        {
+            // The photo stays on screen either way; only the timer stops.
+            if let Some(due) = state.slide_due.take() {
+                state.slide_remaining = Some(due.saturating_duration_since(Instant::now()));
+                state.transport_state = "PAUSED_PLAYBACK".to_string();
+            } else if let Some(remaining) = state.slide_remaining.take() {
+                state.slide_due = Some(Instant::now() + remaining);
+                state.transport_state = "PLAYING".to_string();
+            }
+        }
         KeyCode::Char('p') | KeyCode::Char('P') if matches!(state.phase, AppPhase::Playing) => {
             match state.transport_state.as_str() {
                 "PLAYING" => match crate::dlna::av_transport::pause(soap, &state.av_url).await {
@@ -488,13 +955,91 @@ async fn handle_key(
             state.phase = AppPhase::SeekInput;
         }
 
+        // ── Streams ──────────────────────────────────────────────────────────
+        KeyCode::Char('x') | KeyCode::Char('X') | KeyCode::Delete
+            if state.focus == FocusPanel::Streams =>
+        {
+            if let Some(stream) = state.streams.get(state.stream_cursor) {
+                let msg = if state.sessions.kill(stream.id) {
+                    format!("Stopped stream of {} to {}", stream.file, stream.peer.ip())
+                } else {
+                    "Stream already ended".to_string()
+                };
+                state.set_status(msg, 30);
+                state.refresh_streams();
+            }
+        }
+
+        // ── Playlist files ────────────────────────────────────────────────────
+        KeyCode::Char('o') | KeyCode::Char('O') => {
+            let playlists = state.library.playlists();
+            if playlists.is_empty() {
+                state.set_status(
+                    "No playlists (.m3u, .m3u8, .pls) in the media directory",
+                    30,
+                );
+            } else {
+                state.playlist_menu = Some(PlaylistMenu {
+                    playlists: playlists.to_vec(),
+                    cursor: 0,
+                });
+            }
+        }
+
+        // ── Remote URL ────────────────────────────────────────────────────────
+        KeyCode::Char('c') | KeyCode::Char('C') => {
+            state.url_input = Some(String::new());
+        }
+
+        KeyCode::Char('w') | KeyCode::Char('W') => {
+            if state.queue_to_save().is_empty() {
+                state.set_status("Nothing to save — play or select files first", 30);
+            } else {
+                state.playlist_name_input = Some(String::new());
+            }
+        }
+
+        // ── Subtitles ─────────────────────────────────────────────────────────
+        KeyCode::Char('u') | KeyCode::Char('U') => {
+            let menu = subtitle_menu(state, config);
+            state.subtitle_menu = Some(menu);
+        }
+
+        KeyCode::Char('[') | KeyCode::Char(']') if matches!(state.phase, AppPhase::Playing) => {
+            let Some(file) = state.current_track().map(|f| f.relative_path.clone()) else {
+                return Ok(false);
+            };
+            if state.current_subtitle.is_none() {
+                state.set_status("No subtitles on this track", 20);
+                return Ok(false);
+            }
+            let step = if key.code == KeyCode::Char('[') {
+                -SUBTITLE_OFFSET_STEP_MS
+            } else {
+                SUBTITLE_OFFSET_STEP_MS
+            };
+            let offset = state.subtitle_offset() + step;
+            state.subtitle_offsets.set(&file, offset);
+            state.subtitle_offset_due = Some(Instant::now() + SUBTITLE_OFFSET_SETTLE);
+            match state
+                .subtitle_offsets
+                .save(Path::new(&config.state_directory))
+            {
+                Ok(()) => {
+                    state.set_status(format!("Subtitle offset {}", format_offset(offset)), 30)
+                }
+                Err(e) => state.set_status(format!("Could not save subtitle offset: {}", e), 30),
+            }
+        }
+
+        // ── Bandwidth limits ──────────────────────────────────────────────────
+        KeyCode::Char('l') | KeyCode::Char('L') => {
+            state.limit_input = Some(state.throttle.limits().to_string());
+        }
+
         // ── Quit ──────────────────────────────────────────────────────────────
         KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-            if matches!(state.phase, AppPhase::Playing | AppPhase::SeekInput) {
-                crate::dlna::av_transport::stop(soap, &state.av_url)
-                    .await
-                    .ok();
-            }
+            quit(state, soap, config).await;
             return Ok(true);
         }
 
@@ -504,6 +1049,35 @@ async fn handle_key(
     Ok(false)
 }
 
+/// Subtitle choices for the videos about to be cast: the selected files, or
+/// the one under the cursor when nothing is selected.
+fn subtitle_menu(state: &AppState, config: &Config) -> SubtitleMenu {
+    let files = if state.media_selected.is_empty() {
+        state.media_at_cursor().into_iter().collect()
+    } else {
+        state.selected_in_view_order()
+    };
+    let mut languages: Vec<String> = files
+        .into_iter()
+        .filter_map(|i| state.media_files.get(i))
+        .flat_map(|file| find_subtitles(file, config))
+        .filter_map(|subtitle| subtitle.language)
+        .collect();
+    languages.sort_unstable_by_key(|language| language.to_lowercase());
+    languages.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    let mut options = vec![SubtitleChoice::Auto, SubtitleChoice::Off];
+    options.extend(languages.into_iter().map(SubtitleChoice::Language));
+    if !options.contains(&state.subtitle_choice) {
+        options.push(state.subtitle_choice.clone());
+    }
+    let cursor = options
+        .iter()
+        .position(|option| *option == state.subtitle_choice)
+        .unwrap_or(0);
+    SubtitleMenu { options, cursor }
+}
+
 // ── Rendering ─────────────────────────────────────────────────────────────────
 
 fn render_app(f: &mut Frame, state: &mut AppState) {
@@ -522,13 +1096,18 @@ fn render_app(f: &mut Frame, state: &mut AppState) {
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
         .split(rows[0]);
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(panels[0]);
 
     // Update viewport height for media scroll tracking
     state.media_viewport_h = (panels[1].height as usize).saturating_sub(2);
     // Clamp scan spinner to avoid overflow issues
     let scan_spin = SPIN[(state.tick / 3) as usize % SPIN.len()];
 
-    render_devices(f, state, panels[0], scan_spin);
+    render_devices(f, state, left[0], scan_spin);
+    render_streams(f, state, left[1]);
     render_media(f, state, panels[1]);
     render_now_playing(f, state, rows[1], scan_spin);
     render_hints(f, state, rows[2]);
@@ -536,6 +1115,26 @@ fn render_app(f: &mut Frame, state: &mut AppState) {
     if state.phase == AppPhase::SeekInput {
         render_seek_popup(f, state, area);
     }
+    if let Some(menu) = &state.subtitle_menu {
+        render_subtitle_popup(f, menu, area);
+    }
+    if let Some(menu) = &state.playlist_menu {
+        render_playlist_popup(f, state, menu, area);
+    }
+    if let Some(input) = &state.playlist_name_input {
+        render_playlist_name_popup(f, state, input, area);
+    }
+    if let Some(input) = &state.url_input {
+        render_url_popup(f, input, area);
+    }
+    if state.resume_prompt {
+        if let Some(saved) = &state.saved_queue {
+            render_resume_popup(f, state, saved, area);
+        }
+    }
+    if let Some(input) = &state.limit_input {
+        render_limit_popup(f, state, input, area);
+    }
 }
 
 fn render_devices(f: &mut Frame, state: &AppState, area: Rect, spin: char) {
@@ -613,6 +1212,74 @@ fn render_devices(f: &mut Frame, state: &AppState, area: Rect, spin: char) {
     f.render_widget(List::new(items).block(block), area);
 }
 
+/// Active streams, two lines each: peer and file, then what is being sent,
+/// progress, throughput, age and user agent.
+fn render_streams(f: &mut Frame, state: &AppState, area: Rect) {
+    let focused = state.focus == FocusPanel::Streams;
+    let border_style = if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
+    let block = Block::default()
+        .title(format!(" Streams ({}) ", state.streams.len()))
+        .borders(Borders::ALL)
+        .border_style(border_style);
+
+    if state.streams.is_empty() {
+        f.render_widget(
+            Paragraph::new("\n  No active streams.")
+                .style(Style::default().fg(Color::DarkGray))
+                .block(block),
+            area,
+        );
+        return;
+    }
+
+    // Keep the cursor in view; each stream takes two rows.
+    let viewport = ((area.height as usize).saturating_sub(2) / 2).max(1);
+    let scroll = (state.stream_cursor + 1).saturating_sub(viewport);
+    let items: Vec<ListItem> = state
+        .streams
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(viewport)
+        .map(|(i, stream)| {
+            let is_cursor = focused && i == state.stream_cursor;
+            let prefix = if is_cursor { "→ " } else { "  " };
+            let style = if is_cursor {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let elapsed = stream.started.elapsed().as_secs();
+            let details = format!(
+                "    {}  {}/{}  {}  {}:{:02}  {}",
+                stream.range,
+                format_megabytes(stream.bytes_sent),
+                format_megabytes(stream.length),
+                format_rate(stream.rate),
+                elapsed / 60,
+                elapsed % 60,
+                stream.user_agent
+            );
+            ListItem::new(vec![
+                Line::from(Span::styled(
+                    format!("{}{}  {}", prefix, stream.peer.ip(), stream.file),
+                    style,
+                )),
+                Line::from(Span::styled(details, Style::default().fg(Color::DarkGray))),
+            ])
+        })
+        .collect();
+
+    f.render_widget(List::new(items).block(block), area);
+}
+
 fn render_media(f: &mut Frame, state: &AppState, area: Rect) {
     let focused = state.focus == FocusPanel::Media;
     let border_style = if focused {
@@ -622,8 +1289,9 @@ fn render_media(f: &mut Frame, state: &AppState, area: Rect) {
     };
 
     let title = format!(
-        " Media ({} files, {} selected) ",
-        state.media_files.len(),
+        " Media: {} ({} files, {} selected) ",
+        state.media_view.title(),
+        state.media_visible.len(),
         state.media_selected.len()
     );
 
@@ -632,9 +1300,14 @@ fn render_media(f: &mut Frame, state: &AppState, area: Rect) {
         .borders(Borders::ALL)
         .border_style(border_style);
 
-    if state.media_files.is_empty() {
+    if state.media_visible.is_empty() {
+        let msg = if state.media_files.is_empty() {
+            "\n  No media files found."
+        } else {
+            "\n  Nothing in this view. Press V to switch views."
+        };
         f.render_widget(
-            Paragraph::new("\n  No media files found.")
+            Paragraph::new(msg)
                 .style(Style::default().fg(Color::DarkGray))
                 .block(block),
             area,
@@ -644,24 +1317,25 @@ fn render_media(f: &mut Frame, state: &AppState, area: Rect) {
 
     let viewport = state.media_viewport_h.max(1);
     let items: Vec<ListItem> = state
-        .media_files
+        .media_visible
         .iter()
         .enumerate()
         .skip(state.media_scroll)
         .take(viewport)
-        .map(|(i, file)| {
+        .map(|(row, &i)| {
+            let file = &state.media_files[i];
             let check = if state.media_selected.contains(&i) {
                 "x"
             } else {
                 " "
             };
-            let cursor = if focused && i == state.media_cursor {
+            let cursor = if focused && row == state.media_cursor {
                 "→"
             } else {
                 " "
             };
 
-            let (fg, bold) = if focused && i == state.media_cursor {
+            let (fg, bold) = if focused && row == state.media_cursor {
                 (Color::Yellow, true)
             } else if state.media_selected.contains(&i) {
                 (Color::Green, false)
@@ -674,10 +1348,21 @@ fn render_media(f: &mut Frame, state: &AppState, area: Rect) {
                 style = style.add_modifier(Modifier::BOLD);
             }
 
-            ListItem::new(Line::from(Span::styled(
-                format!("{} [{}] {}", cursor, check, file.relative_path),
-                style,
-            )))
+            let details = file
+                .info
+                .as_ref()
+                .map(|info| info.summary())
+                .unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} [{}] {}", cursor, check, file.relative_path),
+                    style,
+                ),
+                Span::styled(
+                    format!("  {}", details),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
         })
         .collect();
 
@@ -685,8 +1370,18 @@ fn render_media(f: &mut Frame, state: &AppState, area: Rect) {
 }
 
 fn render_now_playing(f: &mut Frame, state: &AppState, area: Rect, spin: char) {
+    let limits = state.throttle.limits().to_string();
+    let title = format!(
+        " Now Playing — serving {}{} ",
+        format_rate(state.throttle.rate()),
+        if limits.is_empty() {
+            String::new()
+        } else {
+            format!(", limits {}", limits)
+        }
+    );
     let block = Block::default()
-        .title(" Now Playing ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::DarkGray));
 
@@ -713,6 +1408,57 @@ fn render_now_playing(f: &mut Frame, state: &AppState, area: Rect, spin: char) {
                 format!("   {}", state.transport_state),
                 Style::default().fg(Color::DarkGray),
             ),
+            Span::styled(
+                track
+                    .info
+                    .as_ref()
+                    .map(|info| format!("   {}", info.summary()))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                match (state.slide_due, state.slide_remaining) {
+                    (Some(due), _) => format!(
+                        "   next photo in {:.0}s",
+                        due.saturating_duration_since(Instant::now())
+                            .as_secs_f64()
+                            .ceil()
+                    ),
+                    (None, Some(remaining)) => format!(
+                        "   slideshow paused, {:.0}s left",
+                        remaining.as_secs_f64().ceil()
+                    ),
+                    (None, None) => String::new(),
+                },
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                state
+                    .current_subtitle
+                    .as_ref()
+                    .map(|subtitle| match state.subtitle_offset() {
+                        0 => format!("   subs {}", subtitle.label()),
+                        offset => format!("   subs {} {}", subtitle.label(), format_offset(offset)),
+                    })
+                    .unwrap_or_default(),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ])
+    } else if let Some(url) = &state.remote_cast {
+        let (icon, color) = match state.transport_state.as_str() {
+            "PLAYING" => ("▶", Color::Green),
+            "PAUSED_PLAYBACK" => ("⏸", Color::Yellow),
+            _ => ("■", Color::DarkGray),
+        };
+        Line::from(vec![
+            Span::styled(
+                format!("  {} {}  {}", icon, spin, url),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!("   {}   relayed", state.transport_state),
+                Style::default().fg(Color::DarkGray),
+            ),
         ])
     } else {
         Line::from(Span::styled(
@@ -730,11 +1476,11 @@ fn render_hints(f: &mut Frame, state: &AppState, area: Rect) {
             " R rescan   Tab switch panel   ↑↓/jk nav   Q quit ".to_string()
         }
         AppPhase::Playing | AppPhase::SeekInput => {
-            " Tab panels   ↑↓ nav   Space select   P pause   N next   S stop   F seek   Q quit "
+            " Tab panels   ↑↓ nav   Space select   P pause   N next   S stop   F seek   U subs   [ ] sub delay   O playlists   W save queue   C cast URL   X kill stream   L limits   Q quit "
                 .to_string()
         }
         _ => {
-            " Tab panels   ↑↓/jk nav   Space select   A all   Enter confirm/play   R rescan   Q quit ".to_string()
+            " Tab panels   ↑↓/jk nav   Space select   A all   V view   Enter confirm/play   O playlists   W save   C cast URL   U subs   R rescan   X kill stream   L limits   Q quit ".to_string()
         }
     };
 
@@ -780,6 +1526,234 @@ fn render_seek_popup(f: &mut Frame, state: &AppState, area: Rect) {
     );
 }
 
+fn render_subtitle_popup(f: &mut Frame, menu: &SubtitleMenu, area: Rect) {
+    let popup = centered_fixed(42, menu.options.len() as u16 + 4, area);
+    let block = Block::default()
+        .title(" Subtitles ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+
+    let mut lines = vec![Line::from(Span::styled(
+        "  Track to cast with (Enter picks):",
+        Style::default().fg(Color::DarkGray),
+    ))];
+    lines.extend(menu.options.iter().enumerate().map(|(i, option)| {
+        if i == menu.cursor {
+            Line::from(Span::styled(
+                format!("  → {}", option.label()),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ))
+        } else {
+            Line::from(format!("    {}", option.label()))
+        }
+    }));
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+fn render_playlist_name_popup(f: &mut Frame, state: &AppState, input: &str, area: Rect) {
+    let popup = centered_fixed(56, 5, area);
+    let block = Block::default()
+        .title(format!(
+            " Save {} tracks as playlist ",
+            state.queue_to_save().len()
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(Span::styled(
+                "  Name (saved as <name>.m3u8 in the media folder):",
+                Style::default().fg(Color::DarkGray),
+            )),
+            Line::from(Span::styled(
+                format!("  > {}_", input),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        ]),
+        inner,
+    );
+}
+
+fn render_url_popup(f: &mut Frame, input: &str, area: Rect) {
+    let popup = centered_fixed(72, 5, area);
+    let block = Block::default()
+        .title(" Cast URL ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+    // Long URLs scroll so the end being typed stays visible
+    let visible: String = {
+        let chars: Vec<char> = input.chars().collect();
+        let width = (inner.width as usize).saturating_sub(6);
+        chars[chars.len().saturating_sub(width)..].iter().collect()
+    };
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(Span::styled(
+                "  http(s) URL to relay to the renderer:",
+                Style::default().fg(Color::DarkGray),
+            )),
+            Line::from(Span::styled(
+                format!("  > {}_", visible),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        ]),
+        inner,
+    );
+}
+
+fn render_resume_popup(f: &mut Frame, state: &AppState, saved: &SavedQueue, area: Rect) {
+    let (queue, position) = state.saved_queue_files(saved);
+    let track = queue
+        .get(position)
+        .and_then(|&i| state.media_files.get(i))
+        .map(|file| file.relative_path.as_str())
+        .unwrap_or_default();
+    let popup = centered_fixed(64, 6, area);
+    let block = Block::default()
+        .title(" Resume last queue? ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!(
+                "  Track {}/{}: {}",
+                position + 1,
+                queue.len(),
+                track
+            )),
+            Line::from(Span::styled(
+                format!(
+                    "  {}",
+                    saved
+                        .elapsed
+                        .as_deref()
+                        .map(|elapsed| format!("stopped at {}", elapsed))
+                        .unwrap_or_default()
+                ),
+                Style::default().fg(Color::DarkGray),
+            )),
+            Line::from(Span::styled(
+                "  Enter/Y resume   Esc/N discard",
+                Style::default().fg(Color::DarkGray),
+            )),
+        ]),
+        inner,
+    );
+}
+
+/// Playlist files with how many of their entries are in the library.
+fn render_playlist_popup(f: &mut Frame, state: &AppState, menu: &PlaylistMenu, area: Rect) {
+    let popup = centered_fixed(60, menu.playlists.len().min(12) as u16 + 4, area);
+    let block = Block::default()
+        .title(" Playlists ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+
+    let mut lines = vec![Line::from(Span::styled(
+        "  Playlist to play (Enter loads):",
+        Style::default().fg(Color::DarkGray),
+    ))];
+    // Keep the cursor in view; at most 12 rows are shown.
+    let scroll = (menu.cursor + 1).saturating_sub(12);
+    lines.extend(
+        menu.playlists
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(12)
+            .map(|(i, playlist)| {
+                let label = format!(
+                    "{}  ({}/{} found)",
+                    playlist.title,
+                    state.playlist_files(playlist).len(),
+                    playlist.entries.len()
+                );
+                if i == menu.cursor {
+                    Line::from(Span::styled(
+                        format!("  → {}", label),
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                } else {
+                    Line::from(format!("    {}", label))
+                }
+            }),
+    );
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Limit editor, with the current rate of each client being served below.
+fn render_limit_popup(f: &mut Frame, state: &AppState, input: &str, area: Rect) {
+    let clients = state.throttle.client_rates();
+    let popup = centered_fixed(64, 6 + clients.len().min(8) as u16, area);
+    let block = Block::default()
+        .title(" Bandwidth limits ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "  e.g. global=40M client=10M video/*=8M (empty = none):",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(
+            format!("  > {}_", input),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    if clients.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  No active streams",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for (ip, rate) in clients.iter().take(8) {
+        lines.push(Line::from(Span::styled(
+            format!("  {:<40} {:>12}", ip, format_rate(*rate)),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Subtitle offset in seconds with its sign, e.g. `+1.2 s` or `-0.3 s`.
+fn format_offset(offset_ms: i64) -> String {
+    format!("{:+.1} s", offset_ms as f64 / 1000.0)
+}
+
+/// Byte count in megabytes with one decimal, e.g. `"12.3 MB"`.
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1e6)
+}
+
 fn centered_fixed(width: u16, height: u16, area: Rect) -> Rect {
     let x = area.x + area.width.saturating_sub(width) / 2;
     let y = area.y + area.height.saturating_sub(height) / 2;